@@ -1,7 +1,17 @@
+mod rate_limit;
 mod settings;
-use crate::github::{Github, GithubClient, GITHUB_BASE_URL};
-use anyhow::Result;
-use settings::{diff::retrieve::RetrieveSettings, FileReader, SettingsCmd, SettingsReader};
+use crate::github::{
+    app::App,
+    retry::{RateLimitPolicy, RetryPolicy},
+    Auth, Github, GithubClient, GITHUB_BASE_URL,
+};
+use anyhow::{anyhow, Result};
+use rate_limit::RateLimit;
+use settings::{
+    diff::retrieve::RetrieveSettings, format::FileFormat, FileReader, SettingsCmd, SettingsReader,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 /// Supported commands and options.  
@@ -12,8 +22,53 @@ pub struct GramOpt {
     ///
     /// This is a Personal Access token that gram can use to authenticate with
     /// github. It can also be set as an environment variable called GITHUB_TOKEN.
+    /// It is not needed when authenticating as a Github App, or for commands
+    /// which do not call github such as `settings validate`.
     #[structopt(long, short, env = "GITHUB_TOKEN")]
-    token: String,
+    token: Option<String>,
+
+    /// Id of a Github App to authenticate as instead of using a token.
+    #[structopt(long, env = "GRAM_APP_ID", requires = "private-key")]
+    app_id: Option<u64>,
+
+    /// Path to the PEM private key of the Github App.
+    #[structopt(long, env = "GRAM_APP_PRIVATE_KEY", requires = "app-id")]
+    private_key: Option<PathBuf>,
+
+    /// Id of the installation of the Github App to act as.
+    ///
+    /// If not given, the installation is looked up from the owner and
+    /// repository gram is called for.
+    #[structopt(long, env = "GRAM_APP_INSTALLATION_ID", requires = "app-id")]
+    installation_id: Option<u64>,
+
+    /// Url of the github api, defaults to https://api.github.com.
+    ///
+    /// For Github Enterprise Server this is the `/api/v3` url of the server,
+    /// e.g. https://github.example.com/api/v3.
+    #[structopt(long, env = "GITHUB_API_URL")]
+    api_url: Option<String>,
+
+    /// Path to a PEM bundle of extra CA certificates to trust when calling
+    /// the github api.
+    #[structopt(long, env = "GRAM_CA_BUNDLE")]
+    ca_bundle: Option<PathBuf>,
+
+    /// What to do when the github rate limit is used up, either wait for it
+    /// to reset or fail.
+    #[structopt(long, env = "GRAM_RATE_LIMIT", default_value = "wait")]
+    rate_limit: RateLimitPolicy,
+
+    /// How many times to retry a call which hit a rate limit or failed with
+    /// a server error. Calls which create something are not retried after a
+    /// server error, as github may have created it anyway.
+    #[structopt(long, default_value = "3")]
+    max_retries: u32,
+
+    /// Format of the settings files given to a command, one of toml, yaml or
+    /// json. Worked out from their extension if not given.
+    #[structopt(long, global = true)]
+    file_format: Option<FileFormat>,
 
     /// Subcommands
     #[structopt(subcommand)]
@@ -28,6 +83,7 @@ pub enum GramOptCommand {
         #[structopt(flatten)]
         cmd: SettingsCmd,
     },
+    RateLimit(RateLimit),
 }
 
 impl GramOpt {
@@ -37,25 +93,92 @@ impl GramOpt {
     /// the github client or settings reader on its contract. The github client may
     /// be used with a token, and this is the first place we can access that token.
     pub async fn handle(self) -> Result<()> {
-        let github = Github::new(self.token.clone(), GITHUB_BASE_URL);
-        let reader = SettingsReader::new();
-        self.handle_internal(github, reader).await
+        let reader = SettingsReader::new(self.file_format);
+        let api_url = self
+            .api_url
+            .clone()
+            .unwrap_or_else(|| GITHUB_BASE_URL.to_owned());
+        // Only built for commands which call github, so the others do not
+        // need a token.
+        let auth = self.auth();
+        let ca_bundle = self.ca_bundle.clone();
+        let retry = RetryPolicy {
+            rate_limit: self.rate_limit,
+            max_retries: self.max_retries,
+            ..RetryPolicy::default()
+        };
+        let connect = || -> Result<Github> {
+            let ca_bundle = read_ca_bundle(ca_bundle.as_deref())?;
+            let github = Github::with_ca_bundle(auth?, &api_url, ca_bundle.as_deref())?;
+            Ok(github
+                .with_retry_policy(retry)
+                .on_rate_limit_wait(|wait| eprintln!("{}", wait)))
+        };
+        self.handle_internal(connect, reader).await
     }
 
-    /// Handle the command and args given to `gram`.
-    async fn handle_internal<G, F>(self, github: G, reader: F) -> Result<()>
+    /// Authenticate as a Github App if one is given, otherwise with the token.
+    fn auth(&self) -> Result<Auth> {
+        match (&self.app_id, &self.private_key, &self.token) {
+            (Some(app_id), Some(private_key), _) => {
+                let key = fs::read(private_key).map_err(|e| {
+                    anyhow!(
+                        "Failed to read private key {}: {}",
+                        private_key.display(),
+                        e
+                    )
+                })?;
+                Ok(Auth::App(App::new(*app_id, &key, self.installation_id)?))
+            }
+            (_, _, Some(token)) => Ok(Auth::Token(token.clone())),
+            _ => Err(anyhow!("Either a token or a Github App is required")),
+        }
+    }
+
+    /// Handle the command and args given to `gram`, connecting to github
+    /// only for the commands which call it.
+    async fn handle_internal<G, C, F>(self, connect: C, reader: F) -> Result<()>
     where
         G: GithubClient + Send + Sync,
+        C: FnOnce() -> Result<G>,
         F: FileReader,
     {
         match self.command {
             GramOptCommand::Settings { cmd } => match cmd {
                 SettingsCmd::Diff(diff) => {
+                    let github = connect()?;
+                    let retriever = RetrieveSettings::new(&github);
+                    diff.handle(reader, retriever, &github).await
+                }
+                SettingsCmd::Plan(plan) => {
+                    let github = connect()?;
                     let retriever = RetrieveSettings::new(&github);
-                    diff.handle(reader, retriever).await
+                    plan.handle(reader, retriever).await
                 }
+                SettingsCmd::Apply(apply) => {
+                    let github = connect()?;
+                    let retriever = RetrieveSettings::new(&github);
+                    apply.handle(reader, retriever, &github).await
+                }
+                SettingsCmd::Export(export) => {
+                    let github = connect()?;
+                    let retriever = RetrieveSettings::new(&github);
+                    export.handle(retriever, reader.file_format()).await
+                }
+                SettingsCmd::Resolve(resolve) => resolve.handle(reader),
+                SettingsCmd::Validate(validate) => validate.handle(reader),
+                SettingsCmd::Schema(schema) => schema.handle(),
+                SettingsCmd::Import(import) => import.handle(reader),
             },
+            GramOptCommand::RateLimit(rate_limit) => rate_limit.handle(&connect()?).await,
         }
     }
 }
 
+/// Read the PEM bundle of extra CA certificates, if one is given.
+fn read_ca_bundle(path: Option<&Path>) -> Result<Option<Vec<u8>>> {
+    path.map(|path| {
+        fs::read(path).map_err(|e| anyhow!("Failed to read CA bundle {}: {}", path.display(), e))
+    })
+    .transpose()
+}
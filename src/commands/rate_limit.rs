@@ -0,0 +1,70 @@
+use crate::github::{GithubClient, RateLimits};
+use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+use structopt::StructOpt;
+
+/// Show how much of the github api rate limit is left.
+#[derive(Debug, StructOpt)]
+pub struct RateLimit {}
+
+impl RateLimit {
+    pub async fn handle<C>(self, client: &C) -> Result<()>
+    where
+        C: GithubClient + Send + Sync,
+    {
+        let limits = client.rate_limit().await?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        print!("{}", RateLimit::render(&limits, now));
+        Ok(())
+    }
+
+    /// One line per resource, `now` is in seconds since the unix epoch.
+    fn render(limits: &RateLimits, now: u64) -> String {
+        limits
+            .resources
+            .iter()
+            .fold(String::new(), |mut acc, (resource, limit)| {
+                acc.push_str(&format!(
+                    "{}: {} of {} remaining, resets in {} seconds\n",
+                    resource,
+                    limit.remaining,
+                    limit.limit,
+                    limit.reset.saturating_sub(now)
+                ));
+                acc
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RateLimit;
+    use crate::github::{self, RateLimits};
+
+    #[test]
+    fn render_should_show_a_line_per_resource() {
+        // arrange
+        let mut limits = RateLimits::default();
+        let limit = |remaining, reset| github::RateLimit {
+            limit: 5000,
+            remaining,
+            reset,
+        };
+        limits
+            .resources
+            .insert("search".to_owned(), limit(30, 1010));
+        limits
+            .resources
+            .insert("core".to_owned(), limit(4990, 1600));
+
+        // act
+        let rendered = RateLimit::render(&limits, 1000);
+
+        // assert
+        assert_eq!(
+            "core: 4990 of 5000 remaining, resets in 600 seconds\n\
+             search: 30 of 5000 remaining, resets in 10 seconds\n",
+            rendered
+        );
+    }
+}
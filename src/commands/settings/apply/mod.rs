@@ -0,0 +1,2408 @@
+use super::{
+    diff::{
+        actions_variable_key, environment_key, environment_rules as diff_environment_rules,
+        label_key,
+        model::{SettingDiff, Value},
+        protected_rules,
+        retrieve::{Retrieve, Sections},
+        secret_key, unexpected_collaborators, unexpected_labels, unexpected_secrets,
+        unexpected_teams, webhook_fields, webhook_key, Diff, DiffableSettings,
+        ACTIONS_ALLOWED_ACTIONS_KEY, ACTIONS_CAN_APPROVE_PULL_REQUESTS_KEY,
+        ACTIONS_DEFAULT_WORKFLOW_PERMISSIONS_KEY, ACTIONS_ENABLED_KEY,
+        ACTIONS_GITHUB_OWNED_ALLOWED_KEY, ACTIONS_PATTERNS_ALLOWED_KEY,
+        ACTIONS_VERIFIED_ALLOWED_KEY, COLLABORATORS, DESCRIPTION_KEY, HOMEPAGE_KEY,
+        OPTIONS_ALLOW_AUTO_MERGE_KEY, OPTIONS_ALLOW_FORKING_KEY, OPTIONS_ALLOW_MERGE_COMMIT_KEY,
+        OPTIONS_ALLOW_REBASE_MERGE_KEY, OPTIONS_ALLOW_SQUASH_MERGE_KEY,
+        OPTIONS_ALLOW_UPDATE_BRANCH_KEY, OPTIONS_DEFAULT_BRANCH_KEY,
+        OPTIONS_DELETE_BRANCH_ON_MERGE_KEY, OPTIONS_HAS_DISCUSSIONS_KEY, OPTIONS_HAS_ISSUES_KEY,
+        OPTIONS_HAS_PROJECTS_KEY, OPTIONS_HAS_WIKI_KEY, OPTIONS_IS_TEMPLATE_KEY,
+        OPTIONS_MERGE_COMMIT_MESSAGE_KEY, OPTIONS_MERGE_COMMIT_TITLE_KEY,
+        OPTIONS_SQUASH_MERGE_COMMIT_MESSAGE_KEY, OPTIONS_SQUASH_MERGE_COMMIT_TITLE_KEY,
+        OPTIONS_VISIBILITY_KEY, OPTIONS_WEB_COMMIT_SIGNOFF_REQUIRED_KEY, PROTECTED, TEAMS,
+        TOPICS_KEY,
+    },
+    normalize_color,
+    plan::SettingsPlan,
+    Actions, AllowedActions, DeploymentBranches, Environment, GramSettings, Permission,
+    ProtectedBranch, Secret, Webhook,
+};
+use crate::{
+    commands::FileReader,
+    github::{
+        sealed::seal, ActionsPermissions, BranchProtectionUpdate, DeploymentBranchPolicy, Enabled,
+        EnvironmentUpdate, GithubClient, HookConfigUpdate, HookUpdate, LabelUpdate,
+        RepositoryUpdate, RequiredPullRequestReviewsUpdate, RequiredStatusChecks, Restrictions,
+        ReviewerId, SelectedActions, Variable, WorkflowPermissions,
+    },
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Apply settings defined in a settings toml file to a repository.
+///
+/// gram will only change settings defined in the given toml file. Any
+/// setting which is not in that file is left as it is.
+///
+/// Alternatively, apply a plan saved by `gram settings plan`. The plan is
+/// refused if the repository has changed since it was made.
+#[derive(Debug, StructOpt)]
+pub struct Apply {
+    /// The owner of the repository.
+    #[structopt(short, long, required_unless = "plan")]
+    pub owner: Option<String>,
+
+    /// The name of the repository.
+    #[structopt(short, long, required_unless = "plan")]
+    pub repo: Option<String>,
+
+    /// Path to the settings TOML file.
+    #[structopt(name = "file", short, long, required_unless = "plan")]
+    pub settings_file: Option<PathBuf>,
+
+    /// Path to a plan file to apply instead of a settings file.
+    #[structopt(long, conflicts_with_all = &["owner", "repo", "file"])]
+    pub plan: Option<PathBuf>,
+}
+
+/// A change to a single settings key.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Change {
+    /// The settings key this change is for, as shown in a diff.
+    pub key: String,
+    /// The value of the key before the change, if it has one.
+    pub before: Option<Value>,
+    /// The value of the key after the change, if it keeps one.
+    pub after: Option<Value>,
+    pub action: Action,
+}
+
+/// A single call to github which makes a change to a repository.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "kebab-case")]
+pub enum Action {
+    UpdateRepository(RepositoryUpdate),
+    ReplaceTopics(Vec<String>),
+    ProtectBranch {
+        branch: String,
+        protection: BranchProtectionUpdate,
+    },
+    UnprotectBranch(String),
+    CreateLabel(LabelUpdate),
+    UpdateLabel {
+        label: String,
+        update: LabelUpdate,
+    },
+    DeleteLabel(String),
+    AddCollaborator {
+        username: String,
+        permission: Permission,
+    },
+    RemoveCollaborator(String),
+    AddTeam {
+        slug: String,
+        permission: Permission,
+    },
+    RemoveTeam(String),
+    /// Webhooks hold the name of their secret's environment variable, the
+    /// secret is only read when the action is run so it is never saved in a plan.
+    CreateWebhook(Webhook),
+    UpdateWebhook {
+        id: u64,
+        webhook: Webhook,
+    },
+    /// Environments hold reviewers by name, their ids are looked up when the
+    /// action is run.
+    UpdateEnvironment(Environment),
+    AddDeploymentBranchPattern {
+        environment: String,
+        pattern: String,
+    },
+    RemoveDeploymentBranchPattern {
+        environment: String,
+        pattern: String,
+    },
+    CreateEnvironmentVariable {
+        environment: String,
+        variable: Variable,
+    },
+    UpdateEnvironmentVariable {
+        environment: String,
+        variable: Variable,
+    },
+    /// Secrets hold where their value is read from, the value is only read
+    /// and encrypted when the action is run so it is never saved in a plan.
+    SetSecret {
+        name: String,
+        secret: Secret,
+    },
+    DeleteSecret(String),
+    UpdateActionsPermissions(ActionsPermissions),
+    UpdateSelectedActions(SelectedActions),
+    UpdateWorkflowPermissions(WorkflowPermissions),
+    CreateActionsVariable(Variable),
+    UpdateActionsVariable(Variable),
+}
+
+impl Action {
+    /// Make this change using the given github client.
+    pub async fn run<C>(&self, client: &C, owner: &str, repo: &str) -> Result<()>
+    where
+        C: GithubClient + Send + Sync,
+    {
+        match self {
+            Action::UpdateRepository(update) => {
+                client.update_repository(owner, repo, update).await?;
+            }
+            Action::ReplaceTopics(topics) => client.replace_topics(owner, repo, topics).await?,
+            Action::ProtectBranch { branch, protection } => {
+                let protection =
+                    keep_unmanaged_protection(client, owner, repo, branch, protection).await?;
+                client
+                    .protect_branch(owner, repo, branch, &protection)
+                    .await?
+            }
+            Action::UnprotectBranch(branch) => client.unprotect_branch(owner, repo, branch).await?,
+            Action::CreateLabel(label) => client.create_label(owner, repo, label).await?,
+            Action::UpdateLabel { label, update } => {
+                client.update_label(owner, repo, label, update).await?
+            }
+            Action::DeleteLabel(label) => client.delete_label(owner, repo, label).await?,
+            Action::AddCollaborator {
+                username,
+                permission,
+            } => match invitation(client, owner, repo, username).await? {
+                Some(id) => {
+                    let permissions = match permission {
+                        Permission::Pull => "read".to_owned(),
+                        Permission::Push => "write".to_owned(),
+                        permission => permission.to_string(),
+                    };
+                    client
+                        .update_invitation(owner, repo, id, &permissions)
+                        .await?
+                }
+                None => {
+                    client
+                        .add_collaborator(owner, repo, username, &permission.to_string())
+                        .await?
+                }
+            },
+            Action::RemoveCollaborator(username) => {
+                match invitation(client, owner, repo, username).await? {
+                    Some(id) => client.delete_invitation(owner, repo, id).await?,
+                    None => client.remove_collaborator(owner, repo, username).await?,
+                }
+            }
+            Action::AddTeam { slug, permission } => {
+                client
+                    .add_team(owner, repo, slug, &permission.to_string())
+                    .await?
+            }
+            Action::RemoveTeam(slug) => client.remove_team(owner, repo, slug).await?,
+            Action::CreateWebhook(webhook) => {
+                let mut hook = hook_update(webhook)?;
+                hook.name = Some("web".to_owned());
+                client.create_hook(owner, repo, &hook).await?
+            }
+            Action::UpdateWebhook { id, webhook } => {
+                client
+                    .update_hook(owner, repo, *id, &hook_update(webhook)?)
+                    .await?
+            }
+            Action::UpdateEnvironment(environment) => {
+                let update = environment_update(client, owner, environment).await?;
+                client
+                    .update_environment(owner, repo, &environment.name, &update)
+                    .await?
+            }
+            Action::AddDeploymentBranchPattern {
+                environment,
+                pattern,
+            } => {
+                client
+                    .create_deployment_branch_policy(owner, repo, environment, pattern)
+                    .await?
+            }
+            Action::RemoveDeploymentBranchPattern {
+                environment,
+                pattern,
+            } => {
+                // Policies are deleted by id, which is not kept in settings.
+                let policies = client
+                    .deployment_branch_policies(owner, repo, environment)
+                    .await?;
+                if let Some(policy) = policies.iter().find(|policy| &policy.name == pattern) {
+                    client
+                        .delete_deployment_branch_policy(owner, repo, environment, policy.id)
+                        .await?
+                }
+            }
+            Action::CreateEnvironmentVariable {
+                environment,
+                variable,
+            } => {
+                client
+                    .create_environment_variable(owner, repo, environment, variable)
+                    .await?
+            }
+            Action::UpdateEnvironmentVariable {
+                environment,
+                variable,
+            } => {
+                client
+                    .update_environment_variable(owner, repo, environment, variable)
+                    .await?
+            }
+            Action::SetSecret { name, secret } => {
+                let value = secret_value(name, secret)?;
+                let public_key = client.secrets_public_key(owner, repo).await?;
+                client
+                    .put_secret(owner, repo, name, &seal(&public_key, &value)?)
+                    .await?
+            }
+            Action::DeleteSecret(name) => client.delete_secret(owner, repo, name).await?,
+            Action::UpdateActionsPermissions(permissions) => {
+                client
+                    .update_actions_permissions(owner, repo, permissions)
+                    .await?
+            }
+            Action::UpdateSelectedActions(selected) => {
+                client
+                    .update_selected_actions(owner, repo, selected)
+                    .await?
+            }
+            Action::UpdateWorkflowPermissions(permissions) => {
+                client
+                    .update_workflow_permissions(owner, repo, permissions)
+                    .await?
+            }
+            Action::CreateActionsVariable(variable) => {
+                client
+                    .create_actions_variable(owner, repo, variable)
+                    .await?
+            }
+            Action::UpdateActionsVariable(variable) => {
+                client
+                    .update_actions_variable(owner, repo, variable)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    /// The http method and path of the github call this action makes.
+    pub fn call(&self, owner: &str, repo: &str) -> String {
+        match self {
+            Action::UpdateRepository(_) => format!("PATCH /repos/{}/{}", owner, repo),
+            Action::ReplaceTopics(_) => format!("PUT /repos/{}/{}/topics", owner, repo),
+            Action::ProtectBranch { branch, .. } => format!(
+                "PUT /repos/{}/{}/branches/{}/protection",
+                owner, repo, branch
+            ),
+            Action::UnprotectBranch(branch) => format!(
+                "DELETE /repos/{}/{}/branches/{}/protection",
+                owner, repo, branch
+            ),
+            Action::CreateLabel(_) => format!("POST /repos/{}/{}/labels", owner, repo),
+            Action::UpdateLabel { label, .. } => {
+                format!("PATCH /repos/{}/{}/labels/{}", owner, repo, label)
+            }
+            Action::DeleteLabel(label) => {
+                format!("DELETE /repos/{}/{}/labels/{}", owner, repo, label)
+            }
+            Action::AddCollaborator { username, .. } => {
+                format!("PUT /repos/{}/{}/collaborators/{}", owner, repo, username)
+            }
+            Action::RemoveCollaborator(username) => {
+                format!(
+                    "DELETE /repos/{}/{}/collaborators/{}",
+                    owner, repo, username
+                )
+            }
+            Action::AddTeam { slug, .. } => format!(
+                "PUT /orgs/{}/teams/{}/repos/{}/{}",
+                owner, slug, owner, repo
+            ),
+            Action::RemoveTeam(slug) => format!(
+                "DELETE /orgs/{}/teams/{}/repos/{}/{}",
+                owner, slug, owner, repo
+            ),
+            Action::CreateWebhook(_) => format!("POST /repos/{}/{}/hooks", owner, repo),
+            Action::UpdateWebhook { id, .. } => {
+                format!("PATCH /repos/{}/{}/hooks/{}", owner, repo, id)
+            }
+            Action::UpdateEnvironment(environment) => format!(
+                "PUT /repos/{}/{}/environments/{}",
+                owner, repo, environment.name
+            ),
+            Action::AddDeploymentBranchPattern { environment, .. } => format!(
+                "POST /repos/{}/{}/environments/{}/deployment-branch-policies",
+                owner, repo, environment
+            ),
+            Action::RemoveDeploymentBranchPattern { environment, .. } => format!(
+                "DELETE /repos/{}/{}/environments/{}/deployment-branch-policies/{{id}}",
+                owner, repo, environment
+            ),
+            Action::CreateEnvironmentVariable { environment, .. } => format!(
+                "POST /repos/{}/{}/environments/{}/variables",
+                owner, repo, environment
+            ),
+            Action::UpdateEnvironmentVariable {
+                environment,
+                variable,
+            } => format!(
+                "PATCH /repos/{}/{}/environments/{}/variables/{}",
+                owner, repo, environment, variable.name
+            ),
+            Action::SetSecret { name, .. } => {
+                format!("PUT /repos/{}/{}/actions/secrets/{}", owner, repo, name)
+            }
+            Action::DeleteSecret(name) => {
+                format!("DELETE /repos/{}/{}/actions/secrets/{}", owner, repo, name)
+            }
+            Action::UpdateActionsPermissions(_) => {
+                format!("PUT /repos/{}/{}/actions/permissions", owner, repo)
+            }
+            Action::UpdateSelectedActions(_) => format!(
+                "PUT /repos/{}/{}/actions/permissions/selected-actions",
+                owner, repo
+            ),
+            Action::UpdateWorkflowPermissions(_) => {
+                format!("PUT /repos/{}/{}/actions/permissions/workflow", owner, repo)
+            }
+            Action::CreateActionsVariable(_) => {
+                format!("POST /repos/{}/{}/actions/variables", owner, repo)
+            }
+            Action::UpdateActionsVariable(variable) => format!(
+                "PATCH /repos/{}/{}/actions/variables/{}",
+                owner, repo, variable.name
+            ),
+        }
+    }
+
+    /// The actual values this action was built from, by key, besides the
+    /// value of the key it changes.
+    ///
+    /// E.g. a branch's protection holds the actual value of each rule which
+    /// is not configured, so a plan checks none of them have changed.
+    pub fn reads(&self, actual: &GramSettings) -> BTreeMap<String, Value> {
+        let environment = |name: &str| {
+            actual
+                .environments
+                .iter()
+                .flatten()
+                .filter(|environment| environment.name == name)
+                .flat_map(diff_environment_rules)
+                .collect()
+        };
+        let keys = |keys: &[&str]| {
+            let current = HashMap::from(DiffableSettings(actual));
+            keys.iter()
+                .filter_map(|key| Some(((*key).to_owned(), current.get(*key)?.clone())))
+                .collect()
+        };
+        match self {
+            Action::ProtectBranch { branch, .. } => actual
+                .protected
+                .iter()
+                .flatten()
+                .filter(|protected| protected.name == *branch)
+                .flat_map(protected_rules)
+                .collect(),
+            Action::UpdateWebhook { webhook, .. } => actual
+                .webhooks
+                .iter()
+                .flatten()
+                .filter(|existing| existing.url == webhook.url)
+                .flat_map(webhook_fields)
+                .collect(),
+            Action::UpdateEnvironment(Environment { name, .. })
+            | Action::AddDeploymentBranchPattern {
+                environment: name, ..
+            }
+            | Action::RemoveDeploymentBranchPattern {
+                environment: name, ..
+            }
+            | Action::CreateEnvironmentVariable {
+                environment: name, ..
+            }
+            | Action::UpdateEnvironmentVariable {
+                environment: name, ..
+            } => environment(name),
+            Action::UpdateActionsPermissions(_) | Action::UpdateSelectedActions(_) => {
+                keys(&[ACTIONS_ENABLED_KEY, ACTIONS_ALLOWED_ACTIONS_KEY])
+            }
+            Action::CreateActionsVariable(variable) | Action::UpdateActionsVariable(variable) => {
+                keys(&[&actions_variable_key(&variable.name)])
+            }
+            _ => BTreeMap::new(),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::UpdateRepository(update) => write!(f, "update repository {:?}", update),
+            Action::ReplaceTopics(topics) => write!(f, "set topics [{}]", topics.join(" ")),
+            Action::ProtectBranch { branch, protection } => {
+                write!(f, "protect branch [{}] {:?}", branch, protection)
+            }
+            Action::UnprotectBranch(branch) => write!(f, "unprotect branch [{}]", branch),
+            Action::CreateLabel(label) => write!(f, "create label {:?}", label),
+            Action::UpdateLabel { label, update } => {
+                write!(f, "update label [{}] {:?}", label, update)
+            }
+            Action::DeleteLabel(label) => write!(f, "delete label [{}]", label),
+            Action::AddCollaborator {
+                username,
+                permission,
+            } => write!(f, "give collaborator [{}] [{}]", username, permission),
+            Action::RemoveCollaborator(username) => {
+                write!(f, "remove collaborator [{}]", username)
+            }
+            Action::AddTeam { slug, permission } => {
+                write!(f, "give team [{}] [{}]", slug, permission)
+            }
+            Action::RemoveTeam(slug) => write!(f, "remove team [{}]", slug),
+            Action::CreateWebhook(webhook) => write!(f, "create webhook [{}]", webhook.url),
+            Action::UpdateWebhook { webhook, .. } => {
+                write!(f, "update webhook [{}]", webhook.url)
+            }
+            Action::UpdateEnvironment(environment) => {
+                write!(
+                    f,
+                    "update environment [{}] {:?}",
+                    environment.name, environment
+                )
+            }
+            Action::AddDeploymentBranchPattern {
+                environment,
+                pattern,
+            } => write!(
+                f,
+                "let branches matching [{}] deploy to [{}]",
+                pattern, environment
+            ),
+            Action::RemoveDeploymentBranchPattern {
+                environment,
+                pattern,
+            } => write!(
+                f,
+                "stop branches matching [{}] deploying to [{}]",
+                pattern, environment
+            ),
+            Action::CreateEnvironmentVariable {
+                environment,
+                variable,
+            } => write!(
+                f,
+                "create variable [{}] on environment [{}] {:?}",
+                variable.name, environment, variable.value
+            ),
+            Action::UpdateEnvironmentVariable {
+                environment,
+                variable,
+            } => write!(
+                f,
+                "update variable [{}] on environment [{}] {:?}",
+                variable.name, environment, variable.value
+            ),
+            Action::SetSecret { name, secret } => {
+                write!(f, "set secret [{}] from {}", name, secret)
+            }
+            Action::DeleteSecret(name) => write!(f, "delete secret [{}]", name),
+            Action::UpdateActionsPermissions(permissions) => {
+                write!(f, "update actions permissions {:?}", permissions)
+            }
+            Action::UpdateSelectedActions(selected) => {
+                write!(f, "update selected actions {:?}", selected)
+            }
+            Action::UpdateWorkflowPermissions(permissions) => {
+                write!(f, "update workflow permissions {:?}", permissions)
+            }
+            Action::CreateActionsVariable(variable) => write!(
+                f,
+                "create actions variable [{}] {:?}",
+                variable.name, variable.value
+            ),
+            Action::UpdateActionsVariable(variable) => write!(
+                f,
+                "update actions variable [{}] {:?}",
+                variable.name, variable.value
+            ),
+        }
+    }
+}
+
+impl Apply {
+    pub async fn handle<F, R, C>(self, reader: F, retriever: R, client: &C) -> Result<()>
+    where
+        F: FileReader,
+        R: Retrieve,
+        C: GithubClient + Send + Sync,
+    {
+        let (owner, repo, changes) = match (&self.plan, &self.settings_file) {
+            (Some(plan_file), _) => {
+                let plan = SettingsPlan::read(&reader, plan_file)?;
+                let keys = plan
+                    .changes
+                    .iter()
+                    .map(|planned| planned.change.key.as_str());
+                let sections = Sections::holding(keys);
+                let actual_settings = retriever
+                    .retrieve(&plan.owner, &plan.repo, sections)
+                    .await?;
+                plan.verify(&actual_settings)?;
+                let changes = plan.changes.into_iter().map(|planned| planned.change);
+                (plan.owner, plan.repo, changes.collect())
+            }
+            (None, Some(settings_file)) => {
+                let (owner, repo) = match (self.owner, self.repo) {
+                    (Some(owner), Some(repo)) => (owner, repo),
+                    _ => return Err(anyhow!("An owner and repo are required without a plan")),
+                };
+                let (configured_settings, _) = reader.read_settings(settings_file)?;
+                let sections = Sections::configured(&configured_settings);
+                let actual_settings = retriever.retrieve(&owner, &repo, sections).await?;
+                let changes = Apply::changes(&configured_settings, &actual_settings)?;
+                (owner, repo, changes)
+            }
+            (None, None) => return Err(anyhow!("Either a settings file or a plan is required")),
+        };
+        // Several keys can need the same call, e.g. each rule of a protected
+        // branch, so only make each call once.
+        let mut applied = Vec::<Action>::new();
+        for change in changes {
+            if applied.contains(&change.action) {
+                continue;
+            }
+            change.action.run(client, &owner, &repo).await?;
+            println!("Applied: {}", change.action);
+            applied.push(change.action);
+        }
+        Ok(())
+    }
+
+    /// Get the changes needed to turn the actual settings into the configured settings.
+    ///
+    /// Each change is made for a [SettingDiff](../diff/model/enum.SettingDiff.html)
+    /// found by [Diff](../diff/struct.Diff.html), so only keys which differ from the
+    /// actual value produce a change. Changes github would make destructive,
+    /// e.g. removing the secret of a webhook, are refused.
+    pub fn changes(configured: &GramSettings, actual: &GramSettings) -> Result<Vec<Change>> {
+        let diffs = Diff::diff(DiffableSettings(configured), DiffableSettings(actual))
+            .into_iter()
+            .map(|diff| (diff.key().to_owned(), diff))
+            .collect::<HashMap<String, SettingDiff>>();
+        let drifted = |key: &str| diffs.contains_key(key);
+        let change = |key: &str, action: Action| Change {
+            key: key.to_owned(),
+            before: diffs.get(key).and_then(SettingDiff::actual).cloned(),
+            after: diffs.get(key).and_then(SettingDiff::expected).cloned(),
+            action,
+        };
+        let mut changes = Vec::new();
+        // Every drifted key of the repository itself is set by the same call.
+        let mut repository = RepositoryUpdate::default();
+        let mut repository_keys = Vec::new();
+        let mut update = |key: &str, set: &dyn Fn(&mut RepositoryUpdate)| {
+            if drifted(key) {
+                set(&mut repository);
+                repository_keys.push(key.to_owned());
+            }
+        };
+        update(DESCRIPTION_KEY, &|u| {
+            u.description = configured.description.clone();
+        });
+        update(HOMEPAGE_KEY, &|u| {
+            u.homepage = configured.homepage.clone();
+        });
+        if let Some(options) = &configured.options {
+            update(OPTIONS_VISIBILITY_KEY, &|u| {
+                u.visibility = options.visibility.map(|visibility| visibility.to_string());
+            });
+            update(OPTIONS_DEFAULT_BRANCH_KEY, &|u| {
+                u.default_branch = options.default_branch.clone();
+            });
+            update(OPTIONS_HAS_ISSUES_KEY, &|u| {
+                u.has_issues = options.has_issues;
+            });
+            update(OPTIONS_HAS_PROJECTS_KEY, &|u| {
+                u.has_projects = options.has_projects;
+            });
+            update(OPTIONS_HAS_WIKI_KEY, &|u| {
+                u.has_wiki = options.has_wiki;
+            });
+            update(OPTIONS_HAS_DISCUSSIONS_KEY, &|u| {
+                u.has_discussions = options.has_discussions;
+            });
+            update(OPTIONS_IS_TEMPLATE_KEY, &|u| {
+                u.is_template = options.is_template;
+            });
+            update(OPTIONS_ALLOW_FORKING_KEY, &|u| {
+                u.allow_forking = options.allow_forking;
+            });
+            update(OPTIONS_WEB_COMMIT_SIGNOFF_REQUIRED_KEY, &|u| {
+                u.web_commit_signoff_required = options.web_commit_signoff_required;
+            });
+            update(OPTIONS_ALLOW_SQUASH_MERGE_KEY, &|u| {
+                u.allow_squash_merge = options.allow_squash_merge;
+            });
+            update(OPTIONS_ALLOW_MERGE_COMMIT_KEY, &|u| {
+                u.allow_merge_commit = options.allow_merge_commit;
+            });
+            update(OPTIONS_ALLOW_REBASE_MERGE_KEY, &|u| {
+                u.allow_rebase_merge = options.allow_rebase_merge;
+            });
+            update(OPTIONS_ALLOW_AUTO_MERGE_KEY, &|u| {
+                u.allow_auto_merge = options.allow_auto_merge;
+            });
+            update(OPTIONS_ALLOW_UPDATE_BRANCH_KEY, &|u| {
+                u.allow_update_branch = options.allow_update_branch;
+            });
+            update(OPTIONS_DELETE_BRANCH_ON_MERGE_KEY, &|u| {
+                u.delete_branch_on_merge = options.delete_branch_on_merge;
+            });
+            update(OPTIONS_SQUASH_MERGE_COMMIT_TITLE_KEY, &|u| {
+                u.squash_merge_commit_title = options.squash_merge_commit_title.clone();
+            });
+            update(OPTIONS_SQUASH_MERGE_COMMIT_MESSAGE_KEY, &|u| {
+                u.squash_merge_commit_message = options.squash_merge_commit_message.clone();
+            });
+            update(OPTIONS_MERGE_COMMIT_TITLE_KEY, &|u| {
+                u.merge_commit_title = options.merge_commit_title.clone();
+            });
+            update(OPTIONS_MERGE_COMMIT_MESSAGE_KEY, &|u| {
+                u.merge_commit_message = options.merge_commit_message.clone();
+            });
+        }
+        changes.extend(
+            repository_keys
+                .iter()
+                .map(|key| change(key, Action::UpdateRepository(repository.clone()))),
+        );
+        if drifted(TOPICS_KEY) {
+            let topics = configured.topics.clone().unwrap_or_default();
+            changes.push(change(TOPICS_KEY, Action::ReplaceTopics(topics)));
+        }
+        if let Some(branches) = &configured.protected {
+            let existing = actual
+                .protected
+                .iter()
+                .flatten()
+                .map(|branch| (branch.name.as_str(), branch))
+                .collect::<HashMap<&str, &ProtectedBranch>>();
+            let protect = |branch: &ProtectedBranch| Action::ProtectBranch {
+                branch: branch.name.clone(),
+                protection: protection_update(branch, existing.get(branch.name.as_str()).copied()),
+            };
+            if drifted(PROTECTED) {
+                changes.extend(
+                    branches
+                        .iter()
+                        .filter(|branch| !existing.contains_key(branch.name.as_str()))
+                        .map(|branch| change(PROTECTED, protect(branch))),
+                );
+                let wanted = branches
+                    .iter()
+                    .map(|branch| branch.name.as_str())
+                    .collect::<HashSet<&str>>();
+                let mut extra = existing
+                    .keys()
+                    .filter(|name| !wanted.contains(*name))
+                    .map(|name| change(PROTECTED, Action::UnprotectBranch((*name).to_owned())))
+                    .collect::<Vec<Change>>();
+                extra.sort_by_key(|change| change.action.to_string());
+                changes.extend(extra);
+            }
+            for branch in branches {
+                let mut rule_keys = protected_rules(branch)
+                    .into_iter()
+                    .map(|(key, _)| key)
+                    .filter(|key| drifted(key))
+                    .collect::<Vec<String>>();
+                rule_keys.sort();
+                changes.extend(rule_keys.iter().map(|key| change(key, protect(branch))));
+            }
+        }
+        for label in configured.labels.iter().flatten() {
+            let key = label_key(&label.name, None);
+            if drifted(&key) {
+                let create = LabelUpdate {
+                    name: Some(label.name.clone()),
+                    color: label.color.as_deref().map(normalize_color),
+                    description: label.description.clone(),
+                };
+                changes.push(change(&key, Action::CreateLabel(create)));
+                continue;
+            }
+            let mut update = |field: &str, set: &dyn Fn(&mut LabelUpdate)| {
+                let key = label_key(&label.name, Some(field));
+                if drifted(&key) {
+                    let mut update = LabelUpdate::default();
+                    set(&mut update);
+                    let label = label.name.clone();
+                    changes.push(change(&key, Action::UpdateLabel { label, update }));
+                }
+            };
+            update("color", &|u| {
+                u.color = label.color.as_deref().map(normalize_color)
+            });
+            update("description", &|u| {
+                u.description = label.description.clone();
+            });
+        }
+        for label in unexpected_labels(configured, actual) {
+            let key = label_key(&label, None);
+            changes.push(change(&key, Action::DeleteLabel(label)));
+        }
+        for collaborator in configured.collaborators.iter().flatten() {
+            let key = format!("{}.{}", COLLABORATORS, collaborator.username);
+            if drifted(&key) {
+                let action = Action::AddCollaborator {
+                    username: collaborator.username.clone(),
+                    permission: collaborator.permission,
+                };
+                changes.push(change(&key, action));
+            }
+        }
+        for username in unexpected_collaborators(configured, actual) {
+            let key = format!("{}.{}", COLLABORATORS, username);
+            changes.push(change(&key, Action::RemoveCollaborator(username)));
+        }
+        for team in configured.teams.iter().flatten() {
+            let key = format!("{}.{}", TEAMS, team.slug);
+            if drifted(&key) {
+                let action = Action::AddTeam {
+                    slug: team.slug.clone(),
+                    permission: team.permission,
+                };
+                changes.push(change(&key, action));
+            }
+        }
+        for slug in unexpected_teams(configured, actual) {
+            let key = format!("{}.{}", TEAMS, slug);
+            changes.push(change(&key, Action::RemoveTeam(slug)));
+        }
+        for webhook in configured.webhooks.iter().flatten() {
+            let key = webhook_key(&webhook.url, None);
+            if drifted(&key) {
+                changes.push(change(&key, Action::CreateWebhook(webhook.clone())));
+                continue;
+            }
+            let existing = actual
+                .webhooks
+                .iter()
+                .flatten()
+                .find(|existing| existing.url == webhook.url);
+            let id = match existing.and_then(|existing| existing.id) {
+                Some(id) => id,
+                None => continue,
+            };
+            // Github replaces the whole config of a webhook, so keep the
+            // actual content type if it is not configured.
+            let update = Webhook {
+                content_type: webhook
+                    .content_type
+                    .or_else(|| existing.and_then(|existing| existing.content_type)),
+                ..webhook.clone()
+            };
+            // Each drifted field needs the same call, so it is only made once.
+            let key = ["content-type", "events", "active", "secret"]
+                .iter()
+                .map(|field| webhook_key(&webhook.url, Some(field)))
+                .find(|key| drifted(key));
+            if let Some(key) = key {
+                // Github would remove the secret, as it is never returned it
+                // cannot be sent back.
+                if webhook.secret_env.is_none() && existing.is_some_and(|e| e.has_secret) {
+                    return Err(anyhow!(
+                        "Refusing to update webhook [{}] as it would lose its secret, \
+                         set secret-env to keep it",
+                        webhook.url
+                    ));
+                }
+                changes.push(change(
+                    &key,
+                    Action::UpdateWebhook {
+                        id,
+                        webhook: update,
+                    },
+                ));
+            }
+        }
+        for environment in configured.environments.iter().flatten() {
+            let existing = actual
+                .environments
+                .iter()
+                .flatten()
+                .find(|existing| existing.name == environment.name);
+            let update = environment_rules(environment, existing);
+            let key = environment_key(&environment.name, None);
+            if drifted(&key) {
+                changes.push(change(&key, Action::UpdateEnvironment(update.clone())));
+            }
+            for field in &[
+                "wait-timer",
+                "reviewers",
+                "reviewer-teams",
+                "deployment-branches",
+            ] {
+                let key = environment_key(&environment.name, Some(field));
+                if drifted(&key) {
+                    changes.push(change(&key, Action::UpdateEnvironment(update.clone())));
+                }
+            }
+            let key = environment_key(&environment.name, Some("branch-patterns"));
+            if let (true, Some(patterns)) = (drifted(&key), &environment.branch_patterns) {
+                let actual_patterns = existing
+                    .and_then(|existing| existing.branch_patterns.clone())
+                    .unwrap_or_default();
+                // Patterns can only be added once the environment takes them.
+                let custom = Some(DeploymentBranches::Custom);
+                if existing.and_then(|existing| existing.deployment_branches) != custom {
+                    changes.push(change(&key, Action::UpdateEnvironment(update.clone())));
+                }
+                for pattern in patterns.iter().filter(|p| !actual_patterns.contains(p)) {
+                    let action = Action::AddDeploymentBranchPattern {
+                        environment: environment.name.clone(),
+                        pattern: pattern.clone(),
+                    };
+                    changes.push(change(&key, action));
+                }
+                for pattern in actual_patterns.iter().filter(|p| !patterns.contains(p)) {
+                    let action = Action::RemoveDeploymentBranchPattern {
+                        environment: environment.name.clone(),
+                        pattern: pattern.clone(),
+                    };
+                    changes.push(change(&key, action));
+                }
+            }
+            for (name, value) in environment.variables.iter().flatten() {
+                let key = environment_key(&environment.name, Some(&format!("variables.{}", name)));
+                if drifted(&key) {
+                    let environment = environment.name.clone();
+                    let variable = Variable {
+                        name: name.clone(),
+                        value: value.clone(),
+                    };
+                    let exists = existing
+                        .and_then(|existing| existing.variables.as_ref())
+                        .is_some_and(|variables| variables.contains_key(name));
+                    let action = match exists {
+                        true => Action::UpdateEnvironmentVariable {
+                            environment,
+                            variable,
+                        },
+                        false => Action::CreateEnvironmentVariable {
+                            environment,
+                            variable,
+                        },
+                    };
+                    changes.push(change(&key, action));
+                }
+            }
+        }
+        if let Some(actions) = &configured.actions {
+            let actual = actual.actions.as_ref();
+            let permissions = actions_permissions(actions, actual);
+            for key in &[ACTIONS_ENABLED_KEY, ACTIONS_ALLOWED_ACTIONS_KEY] {
+                if drifted(key) {
+                    let action = Action::UpdateActionsPermissions(permissions.clone());
+                    changes.push(change(key, action));
+                }
+            }
+            let selected = SelectedActions {
+                github_owned_allowed: actions.github_owned_allowed,
+                verified_allowed: actions.verified_allowed,
+                patterns_allowed: actions.patterns_allowed.clone(),
+            };
+            for key in &[
+                ACTIONS_GITHUB_OWNED_ALLOWED_KEY,
+                ACTIONS_VERIFIED_ALLOWED_KEY,
+                ACTIONS_PATTERNS_ALLOWED_KEY,
+            ] {
+                if drifted(key) {
+                    // Selected actions can only be changed once they are allowed.
+                    let allowed = actual.and_then(|actual| actual.allowed_actions);
+                    if allowed != Some(AllowedActions::Selected) {
+                        let action = Action::UpdateActionsPermissions(permissions.clone());
+                        changes.push(change(key, action));
+                    }
+                    changes.push(change(key, Action::UpdateSelectedActions(selected.clone())));
+                }
+            }
+            let workflow = WorkflowPermissions {
+                default_workflow_permissions: actions
+                    .default_workflow_permissions
+                    .map(|permission| permission.to_string()),
+                can_approve_pull_request_reviews: actions.can_approve_pull_requests,
+            };
+            for key in &[
+                ACTIONS_DEFAULT_WORKFLOW_PERMISSIONS_KEY,
+                ACTIONS_CAN_APPROVE_PULL_REQUESTS_KEY,
+            ] {
+                if drifted(key) {
+                    let action = Action::UpdateWorkflowPermissions(workflow.clone());
+                    changes.push(change(key, action));
+                }
+            }
+            for (name, value) in actions.variables.iter().flatten() {
+                let key = actions_variable_key(name);
+                if drifted(&key) {
+                    let variable = Variable {
+                        name: name.clone(),
+                        value: value.clone(),
+                    };
+                    let exists = actual
+                        .and_then(|actual| actual.variables.as_ref())
+                        .is_some_and(|variables| variables.contains_key(name));
+                    let action = match exists {
+                        true => Action::UpdateActionsVariable(variable),
+                        false => Action::CreateActionsVariable(variable),
+                    };
+                    changes.push(change(&key, action));
+                }
+            }
+        }
+        // Values of secrets cannot be compared, so only missing secrets are set.
+        for (name, secret) in configured.secrets.iter().flatten() {
+            let key = secret_key(name);
+            if drifted(&key) {
+                let action = Action::SetSecret {
+                    name: name.clone(),
+                    secret: secret.clone(),
+                };
+                changes.push(change(&key, action));
+            }
+        }
+        for name in unexpected_secrets(configured, actual) {
+            let key = secret_key(&name);
+            changes.push(change(&key, Action::DeleteSecret(name)));
+        }
+        Ok(changes)
+    }
+}
+
+/// Build the protection rules of an environment from its configured rules.
+///
+/// Github replaces all protection of an environment at once, so rules which
+/// are not configured keep their actual value, if the environment exists.
+/// Branch patterns and variables are changed separately, so are left out.
+fn environment_rules(configured: &Environment, actual: Option<&Environment>) -> Environment {
+    let patterns = configured
+        .branch_patterns
+        .as_ref()
+        .map(|_| DeploymentBranches::Custom);
+    Environment {
+        name: configured.name.clone(),
+        wait_timer: configured
+            .wait_timer
+            .or_else(|| actual.and_then(|actual| actual.wait_timer)),
+        reviewers: configured
+            .reviewers
+            .clone()
+            .or_else(|| actual.and_then(|actual| actual.reviewers.clone())),
+        reviewer_teams: configured
+            .reviewer_teams
+            .clone()
+            .or_else(|| actual.and_then(|actual| actual.reviewer_teams.clone())),
+        deployment_branches: configured
+            .deployment_branches
+            .or(patterns)
+            .or_else(|| actual.and_then(|actual| actual.deployment_branches)),
+        branch_patterns: None,
+        variables: None,
+    }
+}
+
+/// Build the actions permissions of a repository from its configured settings.
+///
+/// Github needs to know whether actions are enabled whenever the allowed
+/// actions change, so an unconfigured `enabled` keeps its actual value.
+/// Configuring selected actions implies only selected actions are allowed.
+fn actions_permissions(configured: &Actions, actual: Option<&Actions>) -> ActionsPermissions {
+    let enabled = configured
+        .enabled
+        .or_else(|| actual.and_then(|actual| actual.enabled))
+        .unwrap_or(true);
+    let selected = match (
+        configured.github_owned_allowed,
+        configured.verified_allowed,
+        &configured.patterns_allowed,
+    ) {
+        (None, None, None) => None,
+        _ => Some(AllowedActions::Selected),
+    };
+    let allowed_actions = configured
+        .allowed_actions
+        .or(selected)
+        .or_else(|| actual.and_then(|actual| actual.allowed_actions));
+    ActionsPermissions {
+        enabled,
+        // Github refuses allowed actions when actions are disabled.
+        allowed_actions: allowed_actions
+            .filter(|_| enabled)
+            .map(|allowed| allowed.to_string()),
+    }
+}
+
+/// Build the github body for an environment, looking up the id of each reviewer.
+async fn environment_update<C>(
+    client: &C,
+    owner: &str,
+    environment: &Environment,
+) -> Result<EnvironmentUpdate>
+where
+    C: GithubClient + Send + Sync,
+{
+    let reviewers = match (&environment.reviewers, &environment.reviewer_teams) {
+        (None, None) => None,
+        (users, teams) => {
+            let mut reviewers = Vec::new();
+            for username in users.iter().flatten() {
+                let user = client.user(username).await?;
+                reviewers.push(ReviewerId {
+                    kind: "User".to_owned(),
+                    id: user.id,
+                });
+            }
+            for slug in teams.iter().flatten() {
+                let team = client.team(owner, slug).await?;
+                reviewers.push(ReviewerId {
+                    kind: "Team".to_owned(),
+                    id: team.id,
+                });
+            }
+            Some(reviewers)
+        }
+    };
+    let deployment_branch_policy = match environment.deployment_branches {
+        None | Some(DeploymentBranches::All) => None,
+        Some(DeploymentBranches::Protected) => Some(DeploymentBranchPolicy {
+            protected_branches: true,
+            custom_branch_policies: false,
+        }),
+        Some(DeploymentBranches::Custom) => Some(DeploymentBranchPolicy {
+            protected_branches: false,
+            custom_branch_policies: true,
+        }),
+    };
+    Ok(EnvironmentUpdate {
+        wait_timer: environment.wait_timer,
+        reviewers,
+        deployment_branch_policy,
+    })
+}
+
+/// Read the value of a secret from its environment variable or file.
+fn secret_value(name: &str, secret: &Secret) -> Result<Vec<u8>> {
+    match (&secret.env, &secret.file) {
+        (Some(var), None) => env::var(var).map(String::into_bytes).map_err(|_| {
+            anyhow!(
+                "Environment variable [{}] with the value of secret [{}] is not set",
+                var,
+                name
+            )
+        }),
+        (None, Some(file)) => fs::read(file).map_err(|e| {
+            anyhow!(
+                "Failed to read the value of secret [{}] from {}: {}",
+                name,
+                file.display(),
+                e
+            )
+        }),
+        _ => Err(anyhow!(
+            "Secret [{}] needs exactly one of env or file to read its value from",
+            name
+        )),
+    }
+}
+
+/// Build the github body for a webhook, reading its secret from the environment.
+fn hook_update(webhook: &Webhook) -> Result<HookUpdate> {
+    let secret = match &webhook.secret_env {
+        Some(name) => Some(env::var(name).map_err(|_| {
+            anyhow!(
+                "Environment variable [{}] with the secret of webhook [{}] is not set",
+                name,
+                webhook.url
+            )
+        })?),
+        None => None,
+    };
+    Ok(HookUpdate {
+        name: None,
+        active: webhook.active,
+        events: webhook.events.clone(),
+        config: HookConfigUpdate {
+            url: webhook.url.clone(),
+            content_type: webhook
+                .content_type
+                .map(|content_type| content_type.to_string()),
+            secret,
+        },
+    })
+}
+
+/// Build the protection for a branch from its configured rules.
+///
+/// Github replaces all protection on a branch at once, so rules which are not
+/// configured keep their actual value, if the branch is already protected.
+/// Parts of the protection gram does not manage are added when the action is
+/// run, see `keep_unmanaged_protection`.
+fn protection_update(
+    configured: &ProtectedBranch,
+    actual: Option<&ProtectedBranch>,
+) -> BranchProtectionUpdate {
+    let rule = |get: &dyn Fn(&ProtectedBranch) -> Option<bool>| {
+        get(configured).or_else(|| actual.and_then(get))
+    };
+    let contexts = configured
+        .required_status_checks
+        .clone()
+        .or_else(|| actual.and_then(|actual| actual.required_status_checks.clone()))
+        .unwrap_or_default();
+    let strict = rule(&|branch| branch.strict_status_checks).unwrap_or(false);
+    let required_status_checks = if contexts.is_empty() && !strict {
+        None
+    } else {
+        Some(RequiredStatusChecks { strict, contexts })
+    };
+    let review_count = configured
+        .required_approving_review_count
+        .or_else(|| actual.and_then(|actual| actual.required_approving_review_count))
+        .unwrap_or(0);
+    let dismiss_stale_reviews = rule(&|branch| branch.dismiss_stale_reviews).unwrap_or(false);
+    let require_code_owner_reviews =
+        rule(&|branch| branch.require_code_owner_reviews).unwrap_or(false);
+    let required_pull_request_reviews =
+        if review_count == 0 && !dismiss_stale_reviews && !require_code_owner_reviews {
+            None
+        } else {
+            Some(RequiredPullRequestReviewsUpdate {
+                dismiss_stale_reviews,
+                require_code_owner_reviews,
+                required_approving_review_count: review_count,
+                ..RequiredPullRequestReviewsUpdate::default()
+            })
+        };
+    BranchProtectionUpdate {
+        required_status_checks,
+        enforce_admins: rule(&|branch| branch.enforce_admins),
+        required_pull_request_reviews,
+        required_linear_history: rule(&|branch| branch.required_linear_history),
+        allow_force_pushes: rule(&|branch| branch.allow_force_pushes),
+        allow_deletions: rule(&|branch| branch.allow_deletions),
+        ..BranchProtectionUpdate::default()
+    }
+}
+
+/// Get the id of the invitation a user has not accepted yet, if they have one.
+///
+/// Invited users count as collaborators, their invitation is changed instead
+/// of inviting them again.
+async fn invitation<C>(client: &C, owner: &str, repo: &str, username: &str) -> Result<Option<u64>>
+where
+    C: GithubClient + Send + Sync,
+{
+    Ok(client
+        .invitations(owner, repo)
+        .await?
+        .into_iter()
+        .find(|invitation| {
+            invitation
+                .invitee
+                .as_ref()
+                .and_then(|invitee| invitee.login.as_deref())
+                .is_some_and(|login| login.eq_ignore_ascii_case(username))
+        })
+        .map(|invitation| invitation.id))
+}
+
+/// Add the parts of a branch's current protection which gram does not manage,
+/// such as push restrictions and who can dismiss reviews.
+///
+/// Github replaces all protection on a branch at once, so without these the
+/// update would turn them off. A branch which requires pull requests keeps
+/// requiring them, even if it needs no approvals.
+async fn keep_unmanaged_protection<C>(
+    client: &C,
+    owner: &str,
+    repo: &str,
+    branch: &str,
+    protection: &BranchProtectionUpdate,
+) -> Result<BranchProtectionUpdate>
+where
+    C: GithubClient + Send + Sync,
+{
+    let mut protection = protection.clone();
+    let protected = client.protected_branches(owner, repo).await?;
+    if !protected.iter().any(|protected| protected.name == branch) {
+        return Ok(protection);
+    }
+    let actual = client.branch_protection(owner, repo, branch).await?;
+    let enabled = |rule: Option<Enabled>| rule.map(|rule| rule.enabled);
+    protection.restrictions = actual.restrictions.as_ref().map(Restrictions::update);
+    protection.required_conversation_resolution = enabled(actual.required_conversation_resolution);
+    protection.block_creations = enabled(actual.block_creations);
+    protection.lock_branch = enabled(actual.lock_branch);
+    protection.allow_fork_syncing = enabled(actual.allow_fork_syncing);
+    if let Some(reviews) = actual.required_pull_request_reviews {
+        let update = protection
+            .required_pull_request_reviews
+            .get_or_insert_with(RequiredPullRequestReviewsUpdate::default);
+        update.require_last_push_approval = Some(reviews.require_last_push_approval);
+        update.dismissal_restrictions = reviews
+            .dismissal_restrictions
+            .as_ref()
+            .map(Restrictions::update);
+        update.bypass_pull_request_allowances = reviews
+            .bypass_pull_request_allowances
+            .as_ref()
+            .map(Restrictions::update);
+    }
+    Ok(protection)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Action, Apply, Change};
+    use crate::{
+        commands::settings::{
+            diff::{
+                model::Value,
+                retrieve::{Retrieve, Sections},
+            },
+            spans::Spans,
+            Actions, AllowedActions, Collaborator, ContentType, DeploymentBranches, Environment,
+            FileReader, GramSettings, Label, Labels, Options, Permission, ProtectedBranch, Secret,
+            Team, Visibility, Webhook, WorkflowPermission,
+        },
+        github::{
+            fake::FakeGithub, Account, ActionsPermissions, ActionsPublicKey, Branch, BranchPolicy,
+            BranchProtection, BranchProtectionUpdate, Invitation, LabelUpdate, RepositoryUpdate,
+            RequiredPullRequestReviews, RequiredPullRequestReviewsUpdate, Restrictions,
+            RestrictionsUpdate, SelectedActions, Variable, WorkflowPermissions,
+        },
+    };
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use crypto_box::SecretKey;
+    use std::path::{Path, PathBuf};
+
+    struct SucceedingFileReader {
+        settings: GramSettings,
+    }
+
+    impl FileReader for SucceedingFileReader {
+        fn read_to_string<P: AsRef<Path>>(&self, _: P) -> Result<String, std::io::Error> {
+            unimplemented!()
+        }
+
+        fn read_settings(&self, _: &PathBuf) -> Result<(GramSettings, Spans)> {
+            Ok((self.settings.clone(), Spans::default()))
+        }
+    }
+
+    struct FakeRetriever {
+        settings: GramSettings,
+    }
+
+    #[async_trait]
+    impl Retrieve for FakeRetriever {
+        async fn retrieve(&self, _owner: &str, _repo: &str, _: Sections) -> Result<GramSettings> {
+            Ok(self.settings.clone())
+        }
+    }
+
+    fn branches(names: &[&str]) -> Option<Vec<ProtectedBranch>> {
+        Some(
+            names
+                .iter()
+                .map(|name| ProtectedBranch {
+                    name: (*name).to_owned(),
+                    ..ProtectedBranch::default()
+                })
+                .collect(),
+        )
+    }
+
+    fn default_apply() -> Apply {
+        Apply {
+            owner: Some("owner".to_owned()),
+            repo: Some("repo".to_owned()),
+            settings_file: Some(PathBuf::new()),
+            plan: None,
+        }
+    }
+
+    fn label(name: &str, color: Option<&str>) -> Label {
+        Label {
+            name: name.to_owned(),
+            color: color.map(str::to_owned),
+            description: None,
+        }
+    }
+
+    fn actions(changes: Vec<Change>) -> Vec<Action> {
+        changes.into_iter().map(|change| change.action).collect()
+    }
+
+    #[test]
+    fn changes_should_be_empty_if_settings_match() {
+        // arrange
+        let settings = GramSettings {
+            description: Some("a".to_owned()),
+            options: Some(Options {
+                allow_squash_merge: Some(true),
+                ..Options::default()
+            }),
+            protected: branches(&["master"]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let changes = Apply::changes(&settings, &settings).unwrap();
+
+        // assert
+        assert!(
+            changes.is_empty(),
+            "expected no changes, got {:#?}",
+            changes
+        );
+    }
+
+    #[test]
+    fn changes_should_only_update_configured_settings_which_differ() {
+        // arrange
+        let configured = GramSettings {
+            description: None,
+            options: Some(Options {
+                allow_squash_merge: Some(false),
+                allow_merge_commit: Some(true),
+                ..Options::default()
+            }),
+            protected: None,
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            description: Some("b".to_owned()),
+            options: Some(Options {
+                allow_squash_merge: Some(true),
+                allow_merge_commit: Some(true),
+                allow_rebase_merge: Some(true),
+                delete_branch_on_merge: Some(true),
+                ..Options::default()
+            }),
+            protected: branches(&["master"]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![Action::UpdateRepository(RepositoryUpdate {
+                allow_squash_merge: Some(false),
+                ..RepositoryUpdate::default()
+            })],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_replace_topics_and_update_repository_features() {
+        // arrange
+        let configured = GramSettings {
+            homepage: Some("https://example.com".to_owned()),
+            topics: Some(vec!["cli".to_owned(), "github".to_owned()]),
+            options: Some(Options {
+                visibility: Some(Visibility::Private),
+                has_wiki: Some(false),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            topics: Some(vec!["github".to_owned()]),
+            options: Some(Options {
+                visibility: Some(Visibility::Public),
+                has_wiki: Some(false),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        let update = Action::UpdateRepository(RepositoryUpdate {
+            homepage: Some("https://example.com".to_owned()),
+            visibility: Some("private".to_owned()),
+            ..RepositoryUpdate::default()
+        });
+        assert_eq!(
+            vec![
+                update.clone(),
+                update,
+                Action::ReplaceTopics(vec!["cli".to_owned(), "github".to_owned()]),
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_set_options_github_does_not_return() {
+        // arrange
+        let configured = GramSettings {
+            options: Some(Options {
+                allow_auto_merge: Some(true),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            options: Some(Options::default()),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![Action::UpdateRepository(RepositoryUpdate {
+                allow_auto_merge: Some(true),
+                ..RepositoryUpdate::default()
+            })],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_only_protect_the_branch_whose_rule_drifted() {
+        // arrange
+        let branch = |name: &str, enforce_admins: bool| ProtectedBranch {
+            name: name.to_owned(),
+            enforce_admins: Some(enforce_admins),
+            ..ProtectedBranch::default()
+        };
+        let configured = GramSettings {
+            protected: Some(vec![branch("release", true), branch("release.1", true)]),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            protected: Some(vec![branch("release", true), branch("release.1", false)]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![Action::ProtectBranch {
+                branch: "release.1".to_owned(),
+                protection: BranchProtectionUpdate {
+                    enforce_admins: Some(true),
+                    ..BranchProtectionUpdate::default()
+                },
+            }],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_protect_missing_branches_and_unprotect_extra_branches() {
+        // arrange
+        let configured = GramSettings {
+            protected: branches(&["master", "release"]),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            protected: branches(&["master", "develop"]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![
+                Action::ProtectBranch {
+                    branch: "release".to_owned(),
+                    protection: BranchProtectionUpdate::default(),
+                },
+                Action::UnprotectBranch("develop".to_owned())
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_protect_a_branch_with_configured_rules_over_actual_rules() {
+        // arrange
+        let configured = GramSettings {
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                required_approving_review_count: Some(2),
+                enforce_admins: Some(true),
+                ..ProtectedBranch::default()
+            }]),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                required_approving_review_count: Some(1),
+                dismiss_stale_reviews: Some(true),
+                enforce_admins: Some(false),
+                allow_deletions: Some(false),
+                ..ProtectedBranch::default()
+            }]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let changes = Apply::changes(&configured, &actual).unwrap();
+
+        // assert
+        let keys = changes
+            .iter()
+            .map(|change| change.key.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "protected.master.enforce-admins",
+                "protected.master.required-approving-review-count"
+            ],
+            keys
+        );
+        let expected = Action::ProtectBranch {
+            branch: "master".to_owned(),
+            protection: BranchProtectionUpdate {
+                required_pull_request_reviews: Some(RequiredPullRequestReviewsUpdate {
+                    dismiss_stale_reviews: true,
+                    require_code_owner_reviews: false,
+                    required_approving_review_count: 2,
+                    ..RequiredPullRequestReviewsUpdate::default()
+                }),
+                enforce_admins: Some(true),
+                allow_deletions: Some(false),
+                ..BranchProtectionUpdate::default()
+            },
+        };
+        assert_eq!(vec![expected.clone(), expected], actions(changes));
+    }
+
+    #[tokio::test]
+    async fn handle_should_only_make_the_same_call_once() {
+        // arrange
+        let reader = SucceedingFileReader {
+            settings: GramSettings {
+                protected: Some(vec![ProtectedBranch {
+                    name: "master".to_owned(),
+                    enforce_admins: Some(true),
+                    allow_deletions: Some(false),
+                    ..ProtectedBranch::default()
+                }]),
+                ..GramSettings::default()
+            },
+        };
+        let retriever = FakeRetriever {
+            settings: GramSettings::default(),
+        };
+        let client = FakeGithub::default();
+
+        // act
+        let result = default_apply().handle(reader, retriever, &client).await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+        let calls = client.calls();
+        assert_eq!(1, calls.len());
+        assert!(calls[0].starts_with("protect owner/repo master"));
+    }
+
+    #[tokio::test]
+    async fn handle_should_update_the_repository_in_one_call() {
+        // arrange
+        let reader = SucceedingFileReader {
+            settings: GramSettings {
+                description: Some("description".to_owned()),
+                options: Some(Options {
+                    has_wiki: Some(false),
+                    allow_squash_merge: Some(false),
+                    ..Options::default()
+                }),
+                ..GramSettings::default()
+            },
+        };
+        let retriever = FakeRetriever {
+            settings: GramSettings {
+                options: Some(Options {
+                    has_wiki: Some(true),
+                    allow_squash_merge: Some(true),
+                    ..Options::default()
+                }),
+                ..GramSettings::default()
+            },
+        };
+        let client = FakeGithub::default();
+
+        // act
+        let result = default_apply().handle(reader, retriever, &client).await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+        let update = RepositoryUpdate {
+            description: Some("description".to_owned()),
+            has_wiki: Some(false),
+            allow_squash_merge: Some(false),
+            ..RepositoryUpdate::default()
+        };
+        assert_eq!(
+            vec![format!("update owner/repo {:?}", update)],
+            client.calls()
+        );
+    }
+
+    #[test]
+    fn changes_should_create_update_and_prune_labels() {
+        // arrange
+        let configured = GramSettings {
+            labels: Some(Labels {
+                prune: Some(true),
+                items: vec![
+                    label("bug", Some("#D73A4A")),
+                    label("docs", Some("#0075CA")),
+                ],
+            }),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            labels: Some(vec![label("bug", Some("ffffff")), label("wontfix", None)].into()),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![
+                Action::UpdateLabel {
+                    label: "bug".to_owned(),
+                    update: LabelUpdate {
+                        color: Some("d73a4a".to_owned()),
+                        ..LabelUpdate::default()
+                    }
+                },
+                Action::CreateLabel(LabelUpdate {
+                    name: Some("docs".to_owned()),
+                    color: Some("0075ca".to_owned()),
+                    ..LabelUpdate::default()
+                }),
+                Action::DeleteLabel("wontfix".to_owned()),
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_give_and_remove_access_for_collaborators_and_teams() {
+        // arrange
+        let configured = GramSettings {
+            collaborators: Some(vec![Collaborator {
+                username: "alice".to_owned(),
+                permission: Permission::Admin,
+            }]),
+            teams: Some(Vec::new()),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            collaborators: Some(vec![Collaborator {
+                username: "bob".to_owned(),
+                permission: Permission::Push,
+            }]),
+            teams: Some(vec![Team {
+                slug: "core".to_owned(),
+                permission: Permission::Pull,
+            }]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![
+                Action::AddCollaborator {
+                    username: "alice".to_owned(),
+                    permission: Permission::Admin
+                },
+                Action::RemoveCollaborator("bob".to_owned()),
+                Action::RemoveTeam("core".to_owned()),
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn changes_should_record_values_before_and_after() {
+        // arrange
+        let configured = GramSettings {
+            description: Some("a".to_owned()),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            description: Some("b".to_owned()),
+            ..GramSettings::default()
+        };
+
+        // act
+        let changes = Apply::changes(&configured, &actual).unwrap();
+
+        // assert
+        assert_eq!(1, changes.len());
+        assert_eq!("description", changes[0].key);
+        assert_eq!(Some(Value::from("b".to_owned())), changes[0].before);
+        assert_eq!(Some(Value::from("a".to_owned())), changes[0].after);
+    }
+
+    #[tokio::test]
+    async fn handle_should_run_each_action_against_the_repository() {
+        // arrange
+        let reader = SucceedingFileReader {
+            settings: GramSettings {
+                description: Some("a".to_owned()),
+                protected: branches(&["master"]),
+                ..GramSettings::default()
+            },
+        };
+        let retriever = FakeRetriever {
+            settings: GramSettings::default(),
+        };
+        let client = FakeGithub::default();
+
+        // act
+        let result = default_apply().handle(reader, retriever, &client).await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+        let calls = client.calls();
+        assert_eq!(2, calls.len());
+        assert!(calls[0].starts_with("update owner/repo"));
+        assert!(calls[1].starts_with("protect owner/repo master"));
+    }
+
+    #[test]
+    fn changes_should_create_missing_webhooks_and_update_drifted_ones_once() {
+        // arrange
+        let webhook = |url: &str, active| Webhook {
+            url: url.to_owned(),
+            events: Some(vec!["push".to_owned()]),
+            active: Some(active),
+            ..Webhook::default()
+        };
+        let configured = GramSettings {
+            webhooks: Some(vec![
+                webhook("https://chat.example.com/hook", true),
+                Webhook {
+                    secret_env: Some("CI_HOOK_SECRET".to_owned()),
+                    ..webhook("https://ci.example.com/hook", true)
+                },
+            ]),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            webhooks: Some(vec![Webhook {
+                content_type: Some(ContentType::Json),
+                id: Some(7),
+                ..webhook("https://ci.example.com/hook", false)
+            }]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let changes = Apply::changes(&configured, &actual).unwrap();
+
+        // assert
+        let keys = changes
+            .iter()
+            .map(|change| change.key.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "webhooks.https://chat.example.com/hook",
+                "webhooks.https://ci.example.com/hook.active",
+            ],
+            keys
+        );
+        assert_eq!(
+            Action::UpdateWebhook {
+                id: 7,
+                webhook: Webhook {
+                    content_type: Some(ContentType::Json),
+                    secret_env: Some("CI_HOOK_SECRET".to_owned()),
+                    ..webhook("https://ci.example.com/hook", true)
+                },
+            },
+            changes[1].action
+        );
+    }
+
+    #[test]
+    fn changes_should_refuse_to_update_a_webhook_which_would_lose_its_secret() {
+        // arrange
+        let webhook = |active| Webhook {
+            url: "https://ci.example.com/hook".to_owned(),
+            active: Some(active),
+            ..Webhook::default()
+        };
+        let configured = GramSettings {
+            webhooks: Some(vec![webhook(true)]),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            webhooks: Some(vec![Webhook {
+                id: Some(7),
+                has_secret: true,
+                ..webhook(false)
+            }]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let result = Apply::changes(&configured, &actual);
+
+        // assert
+        assert_eq!(
+            "Refusing to update webhook [https://ci.example.com/hook] as it would lose its \
+             secret, set secret-env to keep it",
+            result.err().unwrap().to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn run_should_change_the_invitation_of_an_invited_user() {
+        // arrange
+        let client = FakeGithub {
+            invitations: Some(vec![Invitation {
+                id: 7,
+                invitee: Some(Account {
+                    login: Some("Octocat".to_owned()),
+                    ..Account::default()
+                }),
+                permissions: "read".to_owned(),
+            }]),
+            ..FakeGithub::default()
+        };
+        let add = Action::AddCollaborator {
+            username: "octocat".to_owned(),
+            permission: Permission::Push,
+        };
+        let remove = Action::RemoveCollaborator("octocat".to_owned());
+
+        // act
+        add.run(&client, "owner", "repo").await.unwrap();
+        remove.run(&client, "owner", "repo").await.unwrap();
+
+        // assert
+        assert_eq!(
+            vec![
+                "update invitation owner/repo 7 write".to_owned(),
+                "delete invitation owner/repo 7".to_owned(),
+            ],
+            client.calls()
+        );
+    }
+
+    #[tokio::test]
+    async fn run_should_keep_the_protection_gram_does_not_manage() {
+        // arrange
+        let user = Account {
+            login: Some("octocat".to_owned()),
+            ..Account::default()
+        };
+        let team = Account {
+            slug: Some("core".to_owned()),
+            ..Account::default()
+        };
+        let client = FakeGithub {
+            protected_branches: Some(vec![Branch {
+                name: "master".to_owned(),
+            }]),
+            branch_protection: Some(BranchProtection {
+                required_pull_request_reviews: Some(RequiredPullRequestReviews {
+                    dismissal_restrictions: Some(Restrictions {
+                        teams: vec![team],
+                        ..Restrictions::default()
+                    }),
+                    ..RequiredPullRequestReviews::default()
+                }),
+                restrictions: Some(Restrictions {
+                    users: vec![user],
+                    ..Restrictions::default()
+                }),
+                ..BranchProtection::default()
+            }),
+            ..FakeGithub::default()
+        };
+        let action = Action::ProtectBranch {
+            branch: "master".to_owned(),
+            protection: BranchProtectionUpdate {
+                enforce_admins: Some(true),
+                ..BranchProtectionUpdate::default()
+            },
+        };
+
+        // act
+        let result = action.run(&client, "owner", "repo").await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+        let expected = BranchProtectionUpdate {
+            enforce_admins: Some(true),
+            required_pull_request_reviews: Some(RequiredPullRequestReviewsUpdate {
+                require_last_push_approval: Some(false),
+                dismissal_restrictions: Some(RestrictionsUpdate {
+                    teams: vec!["core".to_owned()],
+                    ..RestrictionsUpdate::default()
+                }),
+                ..RequiredPullRequestReviewsUpdate::default()
+            }),
+            restrictions: Some(RestrictionsUpdate {
+                users: vec!["octocat".to_owned()],
+                ..RestrictionsUpdate::default()
+            }),
+            ..BranchProtectionUpdate::default()
+        };
+        assert_eq!(
+            vec![format!("protect owner/repo master {:?}", expected)],
+            client.calls()
+        );
+    }
+
+    #[tokio::test]
+    async fn run_should_read_the_webhook_secret_from_the_environment_without_showing_it() {
+        // arrange
+        std::env::set_var("GRAM_TEST_WEBHOOK_SECRET", "hunter2");
+        let action = Action::CreateWebhook(Webhook {
+            url: "https://ci.example.com/hook".to_owned(),
+            secret_env: Some("GRAM_TEST_WEBHOOK_SECRET".to_owned()),
+            ..Webhook::default()
+        });
+        let client = FakeGithub::default();
+
+        // act
+        let result = action.run(&client, "owner", "repo").await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+        let calls = client.calls();
+        assert!(calls[0].starts_with("create hook owner/repo"));
+        assert!(calls[0].contains("********"));
+        assert!(!calls[0].contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn run_should_error_if_the_webhook_secret_is_not_set() {
+        // arrange
+        let action = Action::CreateWebhook(Webhook {
+            url: "https://ci.example.com/hook".to_owned(),
+            secret_env: Some("GRAM_TEST_UNSET_WEBHOOK_SECRET".to_owned()),
+            ..Webhook::default()
+        });
+        let client = FakeGithub::default();
+
+        // act
+        let result = action.run(&client, "owner", "repo").await;
+
+        // assert
+        assert_eq!(
+            "Environment variable [GRAM_TEST_UNSET_WEBHOOK_SECRET] with the secret of webhook \
+             [https://ci.example.com/hook] is not set",
+            result.err().unwrap().to_string()
+        );
+        assert!(client.calls().is_empty());
+    }
+
+    #[test]
+    fn changes_should_make_an_environment_take_patterns_before_adding_them() {
+        // arrange
+        let configured = GramSettings {
+            environments: Some(vec![Environment {
+                name: "production".to_owned(),
+                reviewers: Some(vec!["alice".to_owned()]),
+                branch_patterns: Some(vec!["release/*".to_owned()]),
+                variables: Some(
+                    vec![
+                        ("REGION".to_owned(), "eu-west-1".to_owned()),
+                        ("TIER".to_owned(), "prod".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Environment::default()
+            }]),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            environments: Some(vec![Environment {
+                name: "production".to_owned(),
+                wait_timer: Some(10),
+                reviewers: Some(Vec::new()),
+                reviewer_teams: Some(vec!["core".to_owned()]),
+                deployment_branches: Some(DeploymentBranches::Protected),
+                branch_patterns: Some(Vec::new()),
+                variables: Some(
+                    vec![("REGION".to_owned(), "us-east-1".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+            }]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        let update = Action::UpdateEnvironment(Environment {
+            name: "production".to_owned(),
+            wait_timer: Some(10),
+            reviewers: Some(vec!["alice".to_owned()]),
+            reviewer_teams: Some(vec!["core".to_owned()]),
+            deployment_branches: Some(DeploymentBranches::Custom),
+            ..Environment::default()
+        });
+        let variable = |name: &str, value: &str| Variable {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+        assert_eq!(
+            vec![
+                update.clone(),
+                update,
+                Action::AddDeploymentBranchPattern {
+                    environment: "production".to_owned(),
+                    pattern: "release/*".to_owned(),
+                },
+                Action::UpdateEnvironmentVariable {
+                    environment: "production".to_owned(),
+                    variable: variable("REGION", "eu-west-1"),
+                },
+                Action::CreateEnvironmentVariable {
+                    environment: "production".to_owned(),
+                    variable: variable("TIER", "prod"),
+                },
+            ],
+            actions
+        );
+    }
+
+    #[tokio::test]
+    async fn run_should_look_up_reviewer_ids_and_remove_patterns_by_id() {
+        // arrange
+        let client = FakeGithub {
+            account: Some(Account {
+                id: 42,
+                ..Account::default()
+            }),
+            deployment_branch_policies: Some(vec![BranchPolicy {
+                id: 7,
+                name: "old/*".to_owned(),
+                kind: None,
+            }]),
+            ..FakeGithub::default()
+        };
+        let update = Action::UpdateEnvironment(Environment {
+            name: "production".to_owned(),
+            reviewers: Some(vec!["alice".to_owned()]),
+            deployment_branches: Some(DeploymentBranches::Protected),
+            ..Environment::default()
+        });
+        let remove = Action::RemoveDeploymentBranchPattern {
+            environment: "production".to_owned(),
+            pattern: "old/*".to_owned(),
+        };
+
+        // act
+        update.run(&client, "owner", "repo").await.unwrap();
+        remove.run(&client, "owner", "repo").await.unwrap();
+
+        // assert
+        assert_eq!(
+            vec![
+                "update environment owner/repo production EnvironmentUpdate { wait_timer: None, \
+                 reviewers: Some([ReviewerId { kind: \"User\", id: 42 }]), \
+                 deployment_branch_policy: Some(DeploymentBranchPolicy { \
+                 protected_branches: true, custom_branch_policies: false }) }",
+                "delete branch policy owner/repo production 7",
+            ],
+            client.calls()
+        );
+    }
+
+    #[test]
+    fn changes_should_set_missing_secrets_and_delete_unconfigured_ones() {
+        // arrange
+        let secret = |env: Option<&str>| Secret {
+            env: env.map(str::to_owned),
+            file: None,
+        };
+        let configured = GramSettings {
+            secrets: Some(
+                vec![
+                    ("DEPLOY_TOKEN".to_owned(), secret(Some("DEPLOY_TOKEN"))),
+                    ("NPM_TOKEN".to_owned(), secret(Some("NPM_TOKEN"))),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            secrets: Some(
+                vec![
+                    ("NPM_TOKEN".to_owned(), secret(None)),
+                    ("OLD_TOKEN".to_owned(), secret(None)),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..GramSettings::default()
+        };
+
+        // act
+        let actions = actions(Apply::changes(&configured, &actual).unwrap());
+
+        // assert
+        assert_eq!(
+            vec![
+                Action::SetSecret {
+                    name: "DEPLOY_TOKEN".to_owned(),
+                    secret: secret(Some("DEPLOY_TOKEN")),
+                },
+                Action::DeleteSecret("OLD_TOKEN".to_owned()),
+            ],
+            actions
+        );
+        assert_eq!(
+            "set secret [DEPLOY_TOKEN] from env DEPLOY_TOKEN",
+            actions[0].to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn run_should_seal_the_secret_with_the_repository_public_key() {
+        // arrange
+        std::env::set_var("GRAM_TEST_DEPLOY_TOKEN", "hunter2");
+        let secret_key = SecretKey::from_bytes([7; 32]);
+        let client = FakeGithub {
+            secrets_public_key: Some(ActionsPublicKey {
+                key_id: "568250167242549743".to_owned(),
+                key: base64::encode(secret_key.public_key().as_bytes()),
+            }),
+            ..FakeGithub::default()
+        };
+        let action = Action::SetSecret {
+            name: "DEPLOY_TOKEN".to_owned(),
+            secret: Secret {
+                env: Some("GRAM_TEST_DEPLOY_TOKEN".to_owned()),
+                file: None,
+            },
+        };
+
+        // act
+        let result = action.run(&client, "owner", "repo").await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+        let calls = client.calls();
+        let call = calls[0].split(' ').collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "put",
+                "secret",
+                "owner/repo",
+                "DEPLOY_TOKEN",
+                "568250167242549743"
+            ],
+            call[..5]
+        );
+        let encrypted = base64::decode(call[5]).unwrap();
+        assert_eq!(b"hunter2".to_vec(), secret_key.unseal(&encrypted).unwrap());
+    }
+
+    #[tokio::test]
+    async fn run_should_error_without_exactly_one_source_for_a_secret() {
+        // arrange
+        let client = FakeGithub::default();
+        let action = |env: Option<&str>, file: Option<&str>| Action::SetSecret {
+            name: "DEPLOY_TOKEN".to_owned(),
+            secret: Secret {
+                env: env.map(str::to_owned),
+                file: file.map(PathBuf::from),
+            },
+        };
+
+        // act
+        let neither = action(None, None).run(&client, "owner", "repo").await;
+        let both = action(Some("A"), Some("a"))
+            .run(&client, "owner", "repo")
+            .await;
+        let unset = action(Some("GRAM_TEST_UNSET_SECRET"), None)
+            .run(&client, "owner", "repo")
+            .await;
+
+        // assert
+        let expected =
+            "Secret [DEPLOY_TOKEN] needs exactly one of env or file to read its value from";
+        assert_eq!(expected, neither.err().unwrap().to_string());
+        assert_eq!(expected, both.err().unwrap().to_string());
+        assert_eq!(
+            "Environment variable [GRAM_TEST_UNSET_SECRET] with the value of secret \
+             [DEPLOY_TOKEN] is not set",
+            unset.err().unwrap().to_string()
+        );
+        assert!(client.calls().is_empty());
+    }
+
+    #[test]
+    fn changes_should_allow_selected_actions_before_changing_them() {
+        // arrange
+        let configured = GramSettings {
+            actions: Some(Actions {
+                patterns_allowed: Some(vec!["docker/*".to_owned()]),
+                default_workflow_permissions: Some(WorkflowPermission::Read),
+                can_approve_pull_requests: Some(false),
+                variables: Some(
+                    vec![
+                        ("REGION".to_owned(), "eu-west-1".to_owned()),
+                        ("TIER".to_owned(), "prod".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Actions::default()
+            }),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            actions: Some(Actions {
+                enabled: Some(true),
+                allowed_actions: Some(AllowedActions::All),
+                default_workflow_permissions: Some(WorkflowPermission::Write),
+                can_approve_pull_requests: Some(true),
+                variables: Some(
+                    vec![("REGION".to_owned(), "us-east-1".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Actions::default()
+            }),
+            ..GramSettings::default()
+        };
+
+        // act
+        let changes = Apply::changes(&configured, &actual).unwrap();
+
+        // assert
+        let workflow = Action::UpdateWorkflowPermissions(WorkflowPermissions {
+            default_workflow_permissions: Some("read".to_owned()),
+            can_approve_pull_request_reviews: Some(false),
+        });
+        let variable = |name: &str, value: &str| Variable {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+        assert_eq!(
+            vec![
+                Action::UpdateActionsPermissions(ActionsPermissions {
+                    enabled: true,
+                    allowed_actions: Some("selected".to_owned()),
+                }),
+                Action::UpdateSelectedActions(SelectedActions {
+                    patterns_allowed: Some(vec!["docker/*".to_owned()]),
+                    ..SelectedActions::default()
+                }),
+                workflow.clone(),
+                workflow,
+                Action::UpdateActionsVariable(variable("REGION", "eu-west-1")),
+                Action::CreateActionsVariable(variable("TIER", "prod")),
+            ],
+            actions(changes)
+        );
+    }
+
+    #[test]
+    fn changes_should_not_allow_actions_when_disabling_them() {
+        // arrange
+        let configured = GramSettings {
+            actions: Some(Actions {
+                enabled: Some(false),
+                ..Actions::default()
+            }),
+            ..GramSettings::default()
+        };
+        let actual = GramSettings {
+            actions: Some(Actions {
+                enabled: Some(true),
+                allowed_actions: Some(AllowedActions::LocalOnly),
+                ..Actions::default()
+            }),
+            ..GramSettings::default()
+        };
+
+        // act
+        let changes = Apply::changes(&configured, &actual).unwrap();
+
+        // assert
+        assert_eq!(
+            vec![Action::UpdateActionsPermissions(ActionsPermissions {
+                enabled: false,
+                allowed_actions: None,
+            })],
+            actions(changes)
+        );
+    }
+
+    #[tokio::test]
+    async fn handle_should_error_if_an_action_fails() {
+        // arrange
+        let reader = SucceedingFileReader {
+            settings: GramSettings {
+                description: Some("a".to_owned()),
+                ..GramSettings::default()
+            },
+        };
+        let retriever = FakeRetriever {
+            settings: GramSettings::default(),
+        };
+        let client = FakeGithub {
+            fail_writes: true,
+            ..FakeGithub::default()
+        };
+
+        // act
+        let result = default_apply().handle(reader, retriever, &client).await;
+
+        // assert
+        assert!(result.is_err());
+    }
+}
@@ -1,10 +1,24 @@
+pub mod model;
+pub mod org;
+pub mod report;
 pub mod retrieve;
-use super::{GramSettings, Options};
-use crate::commands::FileReader;
+use super::{
+    normalize_color,
+    remote::{parse_repository, remote_repository, DEFAULT_SETTINGS_FILE},
+    spans::Spans,
+    Actions, Collaborator, Environment, GramSettings, Label, Options, ProtectedBranch, Team,
+    Webhook,
+};
+use crate::{commands::FileReader, github::GithubClient};
 use anyhow::{anyhow, Result};
-use retrieve::Retrieve;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use futures::stream::{self, StreamExt};
+use model::{SettingDiff, Value};
+use org::{Failure, Manifest, OrgReport};
+use report::{Format, Report};
+use retrieve::{Retrieve, Sections};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 /// Diff actual settings with expected settings
@@ -12,137 +26,878 @@ use structopt::StructOpt;
 ///
 /// gram will only diff settings defined in the given toml file. It
 /// will not mention any settings which are not defined in that file.
+///
+/// The diff can also be written as json, yaml, a JUnit XML report or
+/// Github Actions annotations on the settings file for CI. It is printed
+/// to stdout and gram still fails if there is drift.
+///
+/// Without an owner and repo, the repository the `origin` remote of the
+/// checkout the current directory is in points at is diffed, against
+/// `.github/gram/settings.toml` unless another settings file is given.
+///
+/// With `--org`, every repository of an organization is diffed against the
+/// settings file a manifest gives for it.
 #[derive(Debug, StructOpt)]
 pub struct Diff {
+    /// The repository to diff, as owner/repo or a github url.
+    #[structopt(name = "repository")]
+    pub repository: Option<String>,
+
     /// The owner of the repository.
     #[structopt(short, long)]
-    pub owner: String,
+    pub owner: Option<String>,
 
     /// The name of the repository.
     #[structopt(short, long)]
-    pub repo: String,
+    pub repo: Option<String>,
 
-    /// Path to the settings TOML file.
+    /// Path to the settings TOML file, defaults to .github/gram/settings.toml.
     #[structopt(name = "file", short, long)]
-    pub settings_file: PathBuf,
+    pub settings_file: Option<PathBuf>,
+
+    /// The git remote to find the repository from when none is given.
+    #[structopt(long, default_value = "origin")]
+    pub remote: String,
+
+    /// Diff every repository of this organization instead of a single one.
+    #[structopt(
+        long,
+        requires = "manifest",
+        conflicts_with_all = &["repository", "owner", "repo", "file"]
+    )]
+    pub org: Option<String>,
+
+    /// Path to a manifest TOML file mapping repository names to settings files.
+    #[structopt(long, requires = "org")]
+    pub manifest: Option<PathBuf>,
+
+    /// How many repositories to diff at once with `--org`.
+    #[structopt(long, default_value = "8")]
+    pub concurrency: usize,
+
+    /// The format to write the diff in, one of text, json, yaml, junit or github.
+    #[structopt(long, default_value = "text")]
+    pub format: Format,
 }
 
-struct DiffableSettings<'a>(&'a GramSettings);
+pub(crate) struct DiffableSettings<'a>(pub &'a GramSettings);
 
 impl Diff {
-    pub async fn handle<F, R>(self, reader: F, retriever: R) -> Result<()>
+    pub async fn handle<F, R, C>(self, reader: F, retriever: R, client: &C) -> Result<()>
     where
         F: FileReader,
         R: Retrieve,
+        C: GithubClient + Send + Sync,
     {
-        let configured_settings = reader.read_settings(&self.settings_file)?;
-        let actual_settings = retriever.retrieve(&self.owner, &self.repo).await?;
-        let mut diffs = Diff::diff(
-            DiffableSettings(&configured_settings),
-            DiffableSettings(&actual_settings),
+        match (&self.org, &self.manifest) {
+            (Some(org), Some(manifest)) => {
+                self.handle_org(org, manifest, &reader, &retriever, client)
+                    .await
+            }
+            _ => self.handle_repo(&reader, &retriever).await,
+        }
+    }
+
+    async fn handle_repo<F, R>(&self, reader: &F, retriever: &R) -> Result<()>
+    where
+        F: FileReader,
+        R: Retrieve,
+    {
+        let (owner, repo) = self.repository(reader)?;
+        let settings_file = self
+            .settings_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SETTINGS_FILE));
+        let (configured_settings, spans) = reader.read_settings(&settings_file)?;
+        let sections = Sections::configured(&configured_settings);
+        let actual_settings = retriever.retrieve(&owner, &repo, sections).await?;
+        let report = Diff::report(
+            &owner,
+            &repo,
+            &settings_file,
+            &configured_settings,
+            spans,
+            &actual_settings,
         );
-        match diffs.as_slice() {
-            [] => Ok(()),
-            [..] => {
-                diffs.sort();
-                let errors = diffs.iter().fold(String::new(), |mut acc, diff| {
-                    acc.push_str(diff);
-                    acc.push('\n');
-                    acc
-                });
-                Err(anyhow!("Actual settings differ from expected!\n{}", errors))
+        match self.format {
+            Format::Text => match report.diffs.as_slice() {
+                [] => Ok(()),
+                [..] => Err(anyhow!(
+                    "Actual settings differ from expected!\n{}",
+                    report.render(Format::Text)?
+                )),
+            },
+            format => {
+                print!("{}", report.render(format)?);
+                match report.diffs.as_slice() {
+                    [] => Ok(()),
+                    [..] => Err(anyhow!("Actual settings differ from expected!")),
+                }
+            }
+        }
+    }
+
+    /// The owner and name of the repository to diff.
+    ///
+    /// `--owner` and `--repo` win over the repository argument, which wins
+    /// over the git remote.
+    fn repository<F: FileReader>(&self, reader: &F) -> Result<(String, String)> {
+        if let (Some(owner), Some(repo)) = (&self.owner, &self.repo) {
+            return Ok((owner.clone(), repo.clone()));
+        }
+        let (owner, repo) = match &self.repository {
+            Some(repository) => parse_repository(repository)?,
+            None => remote_repository(reader, &env::current_dir()?, &self.remote)?,
+        };
+        Ok((
+            self.owner.clone().unwrap_or(owner),
+            self.repo.clone().unwrap_or(repo),
+        ))
+    }
+
+    async fn handle_org<F, R, C>(
+        &self,
+        org: &str,
+        manifest: &Path,
+        reader: &F,
+        retriever: &R,
+        client: &C,
+    ) -> Result<()>
+    where
+        F: FileReader,
+        R: Retrieve,
+        C: GithubClient + Send + Sync,
+    {
+        let report = self
+            .org_report(org, manifest, reader, retriever, client)
+            .await?;
+        print!("{}", report.render(self.format)?);
+        match report.failed() {
+            0 => Ok(()),
+            failed => Err(anyhow!(
+                "Settings of {} of {} repositories differ from expected or could not be diffed!",
+                failed,
+                report.repositories.len() + report.failures.len()
+            )),
+        }
+    }
+
+    /// Diff each repository of an organization which the manifest has settings for.
+    async fn org_report<'a, F, R, C>(
+        &self,
+        org: &'a str,
+        manifest: &Path,
+        reader: &F,
+        retriever: &R,
+        client: &C,
+    ) -> Result<OrgReport<'a>>
+    where
+        F: FileReader,
+        R: Retrieve,
+        C: GithubClient + Send + Sync,
+    {
+        let manifest = Manifest::read(reader, manifest)?;
+        let mut repos = client
+            .org_repositories(org)
+            .await?
+            .into_iter()
+            .filter(|repo| !repo.archived)
+            .filter_map(|repo| {
+                let settings_file = manifest.settings(&repo.name)?.to_owned();
+                Some((repo.name, settings_file))
+            })
+            .collect::<Vec<(String, PathBuf)>>();
+        repos.sort();
+        let mut profiles = HashMap::new();
+        for (_, settings_file) in &repos {
+            if !profiles.contains_key(settings_file) {
+                let settings = reader.read_settings(settings_file)?;
+                profiles.insert(settings_file.clone(), settings);
             }
         }
+        let results = stream::iter(repos)
+            .map(|(repo, settings_file)| {
+                let sections = Sections::configured(&profiles[&settings_file].0);
+                async move {
+                    let actual = retriever.retrieve(org, &repo, sections).await;
+                    (repo, settings_file, actual)
+                }
+            })
+            .buffer_unordered(self.concurrency.max(1))
+            .collect::<Vec<_>>()
+            .await;
+        let mut report = OrgReport {
+            org,
+            repositories: Vec::new(),
+            failures: Vec::new(),
+        };
+        for (repo, settings_file, actual) in results {
+            let (configured, spans) = &profiles[&settings_file];
+            match actual {
+                Ok(actual) => report.repositories.push(Diff::report(
+                    org,
+                    &repo,
+                    &settings_file,
+                    configured,
+                    spans.clone(),
+                    &actual,
+                )),
+                Err(e) => report.failures.push(Failure {
+                    repo,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        report.repositories.sort_by(|a, b| a.repo.cmp(&b.repo));
+        report.failures.sort_by(|a, b| a.repo.cmp(&b.repo));
+        Ok(report)
+    }
+
+    /// Diff the settings of a repository, keeping every key which was compared.
+    fn report<'a>(
+        owner: &'a str,
+        repo: &str,
+        file: &Path,
+        configured: &GramSettings,
+        spans: Spans,
+        actual: &GramSettings,
+    ) -> Report<'a> {
+        let mut keys = HashMap::from(DiffableSettings(configured))
+            .into_keys()
+            .collect::<Vec<String>>();
+        let diffs = Diff::diff(DiffableSettings(configured), DiffableSettings(actual));
+        keys.extend(diffs.iter().map(|diff| diff.key().to_owned()));
+        keys.sort();
+        keys.dedup();
+        Report {
+            owner,
+            repo: repo.to_owned(),
+            keys,
+            diffs,
+            file: file.to_owned(),
+            spans,
+        }
     }
 
-    /// Get the diff between two [GramSettings](commands.struct.GramSettings.html).
-    fn diff(left: DiffableSettings, right: DiffableSettings) -> Vec<String> {
+    /// Get the diff between two [GramSettings](commands.struct.GramSettings.html),
+    /// sorted by key.
+    pub(crate) fn diff(left: DiffableSettings, right: DiffableSettings) -> Vec<SettingDiff> {
+        let unexpected = unexpected_keys(left.0, right.0);
+        let members = member_keys(left.0);
         let hm = HashMap::from(left);
-        let other_hm = HashMap::from(right);
-        hm.iter()
-            .map(|(key, expected_val)| {
-                let other_val = other_hm.get(key);
-                if other_val == None {
-                    return Some(format!(
-                        "[{}]: expected [{}] but it has no value",
-                        key, expected_val
-                    ));
+        let mut other_hm = HashMap::from(right);
+        let removed = unexpected
+            .into_iter()
+            .filter_map(|key| {
+                other_hm
+                    .remove(&key)
+                    .map(|actual| SettingDiff::Removed { key, actual })
+            })
+            .collect::<Vec<SettingDiff>>();
+        let mut diffs = hm
+            .into_iter()
+            .filter_map(|(key, expected)| match other_hm.get(&key) {
+                None if members.contains(&key) => Some(SettingDiff::Added { key, expected }),
+                // Every repository has a value for each option, one github
+                // didn't return is unknown rather than off.
+                None if key.starts_with("options.") => {
+                    Some(SettingDiff::Unverifiable { key, expected })
                 }
-                other_val.and_then(|other_val| {
-                    if expected_val != other_val {
-                        Some(format!(
-                            "[{}]: expected [{}] got [{}]",
-                            key, expected_val, other_val
-                        ))
-                    } else {
-                        None
-                    }
-                })
+                None => Some(SettingDiff::Missing { key, expected }),
+                Some(actual) if *actual != expected => Some(SettingDiff::Changed {
+                    key,
+                    expected,
+                    actual: actual.clone(),
+                }),
+                Some(_) => None,
             })
-            .flatten()
-            .collect::<Vec<String>>()
+            .collect::<Vec<SettingDiff>>();
+        diffs.extend(removed);
+        diffs.sort_by(|a, b| a.key().cmp(b.key()));
+        diffs
+    }
+}
+
+pub(crate) static DESCRIPTION_KEY: &str = "description";
+pub(crate) static HOMEPAGE_KEY: &str = "homepage";
+pub(crate) static TOPICS_KEY: &str = "topics";
+pub(crate) static OPTIONS_VISIBILITY_KEY: &str = "options.visibility";
+pub(crate) static OPTIONS_DEFAULT_BRANCH_KEY: &str = "options.default-branch";
+pub(crate) static OPTIONS_HAS_ISSUES_KEY: &str = "options.has-issues";
+pub(crate) static OPTIONS_HAS_PROJECTS_KEY: &str = "options.has-projects";
+pub(crate) static OPTIONS_HAS_WIKI_KEY: &str = "options.has-wiki";
+pub(crate) static OPTIONS_HAS_DISCUSSIONS_KEY: &str = "options.has-discussions";
+pub(crate) static OPTIONS_IS_TEMPLATE_KEY: &str = "options.is-template";
+pub(crate) static OPTIONS_ALLOW_FORKING_KEY: &str = "options.allow-forking";
+pub(crate) static OPTIONS_WEB_COMMIT_SIGNOFF_REQUIRED_KEY: &str =
+    "options.web-commit-signoff-required";
+pub(crate) static OPTIONS_ALLOW_SQUASH_MERGE_KEY: &str = "options.allow-squash-merge";
+pub(crate) static OPTIONS_ALLOW_MERGE_COMMIT_KEY: &str = "options.allow-merge-commit";
+pub(crate) static OPTIONS_ALLOW_REBASE_MERGE_KEY: &str = "options.allow-rebase-merge";
+pub(crate) static OPTIONS_ALLOW_AUTO_MERGE_KEY: &str = "options.allow-auto-merge";
+pub(crate) static OPTIONS_ALLOW_UPDATE_BRANCH_KEY: &str = "options.allow-update-branch";
+pub(crate) static OPTIONS_DELETE_BRANCH_ON_MERGE_KEY: &str = "options.delete-branch-on-merge";
+pub(crate) static OPTIONS_SQUASH_MERGE_COMMIT_TITLE_KEY: &str = "options.squash-merge-commit-title";
+pub(crate) static OPTIONS_SQUASH_MERGE_COMMIT_MESSAGE_KEY: &str =
+    "options.squash-merge-commit-message";
+pub(crate) static OPTIONS_MERGE_COMMIT_TITLE_KEY: &str = "options.merge-commit-title";
+pub(crate) static OPTIONS_MERGE_COMMIT_MESSAGE_KEY: &str = "options.merge-commit-message";
+pub(crate) static ACTIONS_ENABLED_KEY: &str = "actions.enabled";
+pub(crate) static ACTIONS_ALLOWED_ACTIONS_KEY: &str = "actions.allowed-actions";
+pub(crate) static ACTIONS_GITHUB_OWNED_ALLOWED_KEY: &str = "actions.github-owned-allowed";
+pub(crate) static ACTIONS_VERIFIED_ALLOWED_KEY: &str = "actions.verified-allowed";
+pub(crate) static ACTIONS_PATTERNS_ALLOWED_KEY: &str = "actions.patterns-allowed";
+pub(crate) static ACTIONS_DEFAULT_WORKFLOW_PERMISSIONS_KEY: &str =
+    "actions.default-workflow-permissions";
+pub(crate) static ACTIONS_CAN_APPROVE_PULL_REQUESTS_KEY: &str = "actions.can-approve-pull-requests";
+pub(crate) static PROTECTED: &str = "protected";
+pub(crate) static LABELS: &str = "labels";
+pub(crate) static LABEL_PRESENT: &str = "present";
+pub(crate) static COLLABORATORS: &str = "collaborators";
+pub(crate) static TEAMS: &str = "teams";
+pub(crate) static WEBHOOKS: &str = "webhooks";
+pub(crate) static WEBHOOK_PRESENT: &str = "present";
+pub(crate) static ENVIRONMENTS: &str = "environments";
+pub(crate) static ENVIRONMENT_PRESENT: &str = "present";
+pub(crate) static SECRETS: &str = "secrets";
+/// The value of every secret on the repository, their values are never known.
+pub(crate) static SECRET_PRESENT: &str = "present";
+/// The value of a webhook secret which is set, the secret itself is never known.
+pub(crate) static WEBHOOK_SECRET_SET: &str = "set";
+
+/// Get the key for a rule of a protected branch, e.g. `protected.master.enforce-admins`.
+pub(crate) fn protected_rule_key(branch: &str, rule: &str) -> String {
+    format!("{}.{}.{}", PROTECTED, branch, rule)
+}
+
+/// Get the key for a label, e.g. `labels.bug`, or one of its fields with
+/// `labels.bug.color`.
+pub(crate) fn label_key(label: &str, field: Option<&str>) -> String {
+    match field {
+        Some(field) => format!("{}.{}.{}", LABELS, label, field),
+        None => format!("{}.{}", LABELS, label),
+    }
+}
+
+/// Get the key for a webhook, e.g. `webhooks.https://example.com`, or one of
+/// its fields with `webhooks.https://example.com.events`.
+pub(crate) fn webhook_key(url: &str, field: Option<&str>) -> String {
+    match field {
+        Some(field) => format!("{}.{}.{}", WEBHOOKS, url, field),
+        None => format!("{}.{}", WEBHOOKS, url),
+    }
+}
+
+/// Get the key for an environment, e.g. `environments.production`, or one of
+/// its rules with `environments.production.wait-timer`.
+pub(crate) fn environment_key(environment: &str, field: Option<&str>) -> String {
+    match field {
+        Some(field) => format!("{}.{}.{}", ENVIRONMENTS, environment, field),
+        None => format!("{}.{}", ENVIRONMENTS, environment),
+    }
+}
+
+/// Get the key for an actions secret, e.g. `secrets.DEPLOY_TOKEN`.
+pub(crate) fn secret_key(secret: &str) -> String {
+    format!("{}.{}", SECRETS, secret)
+}
+
+/// Get the key for an actions variable, e.g. `actions.variables.REGION`.
+pub(crate) fn actions_variable_key(variable: &str) -> String {
+    format!("actions.variables.{}", variable)
+}
+
+/// Get the keys of settings which exist on the repository but should not.
+///
+/// These are labels which are not configured when `labels.prune` is set, and
+/// collaborators, teams or secrets which are not configured when any of
+/// them is set.
+fn unexpected_keys(configured: &GramSettings, actual: &GramSettings) -> Vec<String> {
+    let labels = unexpected_labels(configured, actual)
+        .into_iter()
+        .map(|label| label_key(&label, None));
+    let collaborators = unexpected_collaborators(configured, actual)
+        .into_iter()
+        .map(|username| format!("{}.{}", COLLABORATORS, username));
+    let teams = unexpected_teams(configured, actual)
+        .into_iter()
+        .map(|slug| format!("{}.{}", TEAMS, slug));
+    let secrets = unexpected_secrets(configured, actual)
+        .into_iter()
+        .map(|secret| secret_key(&secret));
+    labels
+        .chain(collaborators)
+        .chain(teams)
+        .chain(secrets)
+        .collect()
+}
+
+/// Names of labels on the repository which are not configured, if `labels.prune` is set.
+pub(crate) fn unexpected_labels(configured: &GramSettings, actual: &GramSettings) -> Vec<String> {
+    let (configured, actual) = (configured.labels.as_ref(), actual.labels.as_ref());
+    if configured.and_then(|labels| labels.prune) != Some(true) {
+        return Vec::new();
     }
+    unconfigured(
+        configured.map(|labels| labels.items.as_slice()),
+        actual.map(|labels| labels.items.as_slice()),
+        |label: &Label| &label.name,
+    )
 }
 
-static DESCRIPTION_KEY: &str = "description";
-static OPTIONS_ALLOW_SQUASH_MERGE_KEY: &str = "options.allow-squash-merge";
-static OPTIONS_ALLOW_MERGE_COMMIT_KEY: &str = "options.allow-merge-commit";
-static OPTIONS_ALLOW_REBASE_MERGE_KEY: &str = "options.allow-rebase-merge";
-static OPTIONS_DELETE_BRANCH_ON_MERGE_KEY: &str = "options.delete-branch-on-merge";
-static PROTECTED: &str = "protected";
+/// Usernames of collaborators on the repository which are not configured.
+pub(crate) fn unexpected_collaborators(
+    configured: &GramSettings,
+    actual: &GramSettings,
+) -> Vec<String> {
+    if configured.collaborators.is_none() {
+        return Vec::new();
+    }
+    unconfigured(
+        configured.collaborators.as_deref(),
+        actual.collaborators.as_deref(),
+        |collaborator: &Collaborator| &collaborator.username,
+    )
+}
+
+/// Slugs of teams on the repository which are not configured.
+pub(crate) fn unexpected_teams(configured: &GramSettings, actual: &GramSettings) -> Vec<String> {
+    if configured.teams.is_none() {
+        return Vec::new();
+    }
+    unconfigured(
+        configured.teams.as_deref(),
+        actual.teams.as_deref(),
+        |team: &Team| &team.slug,
+    )
+}
+
+/// Names of actions secrets on the repository which are not configured.
+pub(crate) fn unexpected_secrets(configured: &GramSettings, actual: &GramSettings) -> Vec<String> {
+    let configured = match &configured.secrets {
+        Some(secrets) => secrets,
+        None => return Vec::new(),
+    };
+    actual
+        .secrets
+        .iter()
+        .flatten()
+        .map(|(secret, _)| secret)
+        .filter(|secret| !configured.contains_key(*secret))
+        .cloned()
+        .collect()
+}
+
+/// Sorted names of the actual entries which have no configured entry of the same name.
+fn unconfigured<T, F>(configured: Option<&[T]>, actual: Option<&[T]>, name: F) -> Vec<String>
+where
+    F: Fn(&T) -> &String,
+{
+    let configured = configured.unwrap_or_default();
+    let mut names = actual
+        .unwrap_or_default()
+        .iter()
+        .filter(|entry| !configured.iter().any(|c| name(c) == name(entry)))
+        .map(|entry| name(entry).clone())
+        .collect::<Vec<String>>();
+    names.sort();
+    names
+}
+
+/// Get the keys of settings which are members of a collection, e.g. labels.
+///
+/// When one of these is missing from the repository it is reported as added.
+fn member_keys(settings: &GramSettings) -> HashSet<String> {
+    let labels = settings
+        .labels
+        .iter()
+        .flatten()
+        .map(|label| label_key(&label.name, None));
+    let collaborators = settings
+        .collaborators
+        .iter()
+        .flatten()
+        .map(|collaborator| format!("{}.{}", COLLABORATORS, collaborator.username));
+    let teams = settings
+        .teams
+        .iter()
+        .flatten()
+        .map(|team| format!("{}.{}", TEAMS, team.slug));
+    let webhooks = settings
+        .webhooks
+        .iter()
+        .flatten()
+        .map(|webhook| webhook_key(&webhook.url, None));
+    let environments = settings
+        .environments
+        .iter()
+        .flatten()
+        .map(|environment| environment_key(&environment.name, None));
+    let secrets = settings
+        .secrets
+        .iter()
+        .flatten()
+        .map(|(secret, _)| secret_key(secret));
+    labels
+        .chain(collaborators)
+        .chain(teams)
+        .chain(webhooks)
+        .chain(environments)
+        .chain(secrets)
+        .collect()
+}
 
 // TODO: it would be nicer to use a macro/proc-macro to generate this
 // instance. Then the keys can be taken directly from the field names.
 //
 // Tell clippy to ignore the implicit hasher here. We want to use the default.
 #[allow(clippy::implicit_hasher)]
-impl<'a> From<DiffableSettings<'a>> for HashMap<&'a str, String> {
+impl<'a> From<DiffableSettings<'a>> for HashMap<String, Value> {
     fn from(settings: DiffableSettings) -> Self {
         let GramSettings {
+            // Merged in when settings are read, so not a setting of the repository.
+            extends: _,
             description,
+            homepage,
+            topics,
             options,
             protected,
+            labels,
+            collaborators,
+            teams,
+            webhooks,
+            environments,
+            secrets,
+            actions,
         } = settings.0;
         let mut hm = Self::new();
-        description
-            .as_ref()
-            .map(|val| hm.insert(DESCRIPTION_KEY, val.to_owned()));
+        let mut insert = |key: &str, val: Option<Value>| {
+            val.map(|val| hm.insert(key.to_owned(), val));
+        };
+        insert(DESCRIPTION_KEY, description.clone().map(Value::from));
+        insert(HOMEPAGE_KEY, homepage.clone().map(Value::from));
+        insert(TOPICS_KEY, topics.clone().map(Value::list));
         if let Some(opts) = options.as_ref() {
             let Options {
+                visibility,
+                default_branch,
+                has_issues,
+                has_projects,
+                has_wiki,
+                has_discussions,
+                is_template,
+                allow_forking,
+                web_commit_signoff_required,
                 allow_squash_merge,
                 allow_merge_commit,
                 allow_rebase_merge,
+                allow_auto_merge,
+                allow_update_branch,
                 delete_branch_on_merge,
+                squash_merge_commit_title,
+                squash_merge_commit_message,
+                merge_commit_title,
+                merge_commit_message,
             } = opts;
-            allow_squash_merge
-                .map(|allow| hm.insert(OPTIONS_ALLOW_SQUASH_MERGE_KEY, allow.to_string()));
-            allow_merge_commit
-                .map(|allow| hm.insert(OPTIONS_ALLOW_MERGE_COMMIT_KEY, allow.to_string()));
-            allow_rebase_merge
-                .map(|allow| hm.insert(OPTIONS_ALLOW_REBASE_MERGE_KEY, allow.to_string()));
-            delete_branch_on_merge
-                .map(|delete| hm.insert(OPTIONS_DELETE_BRANCH_ON_MERGE_KEY, delete.to_string()));
+            let string = |val: &Option<String>| val.clone().map(Value::from);
+            insert(
+                OPTIONS_VISIBILITY_KEY,
+                visibility.map(|val| Value::from(val.to_string())),
+            );
+            insert(OPTIONS_DEFAULT_BRANCH_KEY, string(default_branch));
+            insert(OPTIONS_HAS_ISSUES_KEY, has_issues.map(Value::from));
+            insert(OPTIONS_HAS_PROJECTS_KEY, has_projects.map(Value::from));
+            insert(OPTIONS_HAS_WIKI_KEY, has_wiki.map(Value::from));
+            insert(
+                OPTIONS_HAS_DISCUSSIONS_KEY,
+                has_discussions.map(Value::from),
+            );
+            insert(OPTIONS_IS_TEMPLATE_KEY, is_template.map(Value::from));
+            insert(OPTIONS_ALLOW_FORKING_KEY, allow_forking.map(Value::from));
+            insert(
+                OPTIONS_WEB_COMMIT_SIGNOFF_REQUIRED_KEY,
+                web_commit_signoff_required.map(Value::from),
+            );
+            insert(
+                OPTIONS_ALLOW_SQUASH_MERGE_KEY,
+                allow_squash_merge.map(Value::from),
+            );
+            insert(
+                OPTIONS_ALLOW_MERGE_COMMIT_KEY,
+                allow_merge_commit.map(Value::from),
+            );
+            insert(
+                OPTIONS_ALLOW_REBASE_MERGE_KEY,
+                allow_rebase_merge.map(Value::from),
+            );
+            insert(
+                OPTIONS_ALLOW_AUTO_MERGE_KEY,
+                allow_auto_merge.map(Value::from),
+            );
+            insert(
+                OPTIONS_ALLOW_UPDATE_BRANCH_KEY,
+                allow_update_branch.map(Value::from),
+            );
+            insert(
+                OPTIONS_DELETE_BRANCH_ON_MERGE_KEY,
+                delete_branch_on_merge.map(Value::from),
+            );
+            insert(
+                OPTIONS_SQUASH_MERGE_COMMIT_TITLE_KEY,
+                string(squash_merge_commit_title),
+            );
+            insert(
+                OPTIONS_SQUASH_MERGE_COMMIT_MESSAGE_KEY,
+                string(squash_merge_commit_message),
+            );
+            insert(OPTIONS_MERGE_COMMIT_TITLE_KEY, string(merge_commit_title));
+            insert(
+                OPTIONS_MERGE_COMMIT_MESSAGE_KEY,
+                string(merge_commit_message),
+            );
+        }
+        if let Some(actions) = actions.as_ref() {
+            let Actions {
+                enabled,
+                allowed_actions,
+                github_owned_allowed,
+                verified_allowed,
+                patterns_allowed,
+                default_workflow_permissions,
+                can_approve_pull_requests,
+                variables,
+            } = actions;
+            insert(ACTIONS_ENABLED_KEY, enabled.map(Value::from));
+            insert(
+                ACTIONS_ALLOWED_ACTIONS_KEY,
+                allowed_actions.map(|val| Value::from(val.to_string())),
+            );
+            insert(
+                ACTIONS_GITHUB_OWNED_ALLOWED_KEY,
+                github_owned_allowed.map(Value::from),
+            );
+            insert(
+                ACTIONS_VERIFIED_ALLOWED_KEY,
+                verified_allowed.map(Value::from),
+            );
+            insert(
+                ACTIONS_PATTERNS_ALLOWED_KEY,
+                patterns_allowed.clone().map(Value::list),
+            );
+            insert(
+                ACTIONS_DEFAULT_WORKFLOW_PERMISSIONS_KEY,
+                default_workflow_permissions.map(|val| Value::from(val.to_string())),
+            );
+            insert(
+                ACTIONS_CAN_APPROVE_PULL_REQUESTS_KEY,
+                can_approve_pull_requests.map(Value::from),
+            );
+            for (variable, value) in variables.iter().flatten() {
+                insert(
+                    &actions_variable_key(variable),
+                    Some(Value::from(value.clone())),
+                );
+            }
         }
         if let Some(branches) = protected {
-            let branches_str = branches
-                .iter()
-                .fold(String::new(), |mut acc, branch| {
-                    acc.push_str(&branch.name);
-                    acc.push_str(" ");
-                    acc
-                })
-                .trim()
-                .to_owned();
-            hm.insert(PROTECTED, branches_str);
+            let names = branches.iter().map(|branch| branch.name.clone()).collect();
+            hm.insert(PROTECTED.to_owned(), Value::list(names));
+            for branch in branches {
+                hm.extend(protected_rules(branch));
+            }
+        }
+        for label in labels.iter().flatten() {
+            hm.extend(label_fields(label));
+        }
+        for collaborator in collaborators.iter().flatten() {
+            hm.insert(
+                format!("{}.{}", COLLABORATORS, collaborator.username),
+                Value::from(collaborator.permission.to_string()),
+            );
+        }
+        for team in teams.iter().flatten() {
+            hm.insert(
+                format!("{}.{}", TEAMS, team.slug),
+                Value::from(team.permission.to_string()),
+            );
+        }
+        for webhook in webhooks.iter().flatten() {
+            hm.extend(webhook_fields(webhook));
+        }
+        for environment in environments.iter().flatten() {
+            hm.extend(environment_rules(environment));
+        }
+        // Only whether a secret exists can be compared, github never returns values.
+        for secret in secrets.iter().flatten().map(|(secret, _)| secret) {
+            hm.insert(secret_key(secret), Value::from(SECRET_PRESENT.to_owned()));
         }
         hm
     }
 }
 
+/// Get the keys and values for a webhook and each of its fields which are set.
+///
+/// Only whether a secret is set is compared, so its value never ends up in a diff.
+pub(crate) fn webhook_fields(webhook: &Webhook) -> Vec<(String, Value)> {
+    let Webhook {
+        url,
+        content_type,
+        events,
+        active,
+        secret_env,
+        id: _,
+        has_secret,
+    } = webhook;
+    let secret = if secret_env.is_some() || *has_secret {
+        Some(Value::from(WEBHOOK_SECRET_SET.to_owned()))
+    } else {
+        None
+    };
+    vec![
+        (None, Some(Value::from(WEBHOOK_PRESENT.to_owned()))),
+        (
+            Some("content-type"),
+            content_type.map(|content_type| Value::from(content_type.to_string())),
+        ),
+        (Some("events"), events.clone().map(Value::list)),
+        (Some("active"), active.map(Value::from)),
+        (Some("secret"), secret),
+    ]
+    .into_iter()
+    .filter_map(|(field, val)| val.map(|val| (webhook_key(url, field), val)))
+    .collect()
+}
+
+/// Get the keys and values of an environment and each of its rules which are set.
+///
+/// Each variable has its own key, e.g. `environments.production.variables.REGION`.
+pub(crate) fn environment_rules(environment: &Environment) -> Vec<(String, Value)> {
+    let Environment {
+        name,
+        wait_timer,
+        reviewers,
+        reviewer_teams,
+        deployment_branches,
+        branch_patterns,
+        variables,
+    } = environment;
+    let variables = variables.iter().flatten().map(|(variable, value)| {
+        (
+            format!("variables.{}", variable),
+            Some(Value::from(value.clone())),
+        )
+    });
+    vec![
+        (None, Some(Value::from(ENVIRONMENT_PRESENT.to_owned()))),
+        (Some("wait-timer".to_owned()), wait_timer.map(Value::from)),
+        (
+            Some("reviewers".to_owned()),
+            reviewers.clone().map(Value::list),
+        ),
+        (
+            Some("reviewer-teams".to_owned()),
+            reviewer_teams.clone().map(Value::list),
+        ),
+        (
+            Some("deployment-branches".to_owned()),
+            deployment_branches.map(|branches| Value::from(branches.to_string())),
+        ),
+        (
+            Some("branch-patterns".to_owned()),
+            branch_patterns.clone().map(Value::list),
+        ),
+    ]
+    .into_iter()
+    .chain(variables.map(|(field, val)| (Some(field), val)))
+    .filter_map(|(field, val)| val.map(|val| (environment_key(name, field.as_deref()), val)))
+    .collect()
+}
+
+/// Get the keys and values for a label and each of its fields which are set.
+fn label_fields(label: &Label) -> Vec<(String, Value)> {
+    let Label {
+        name,
+        color,
+        description,
+    } = label;
+    let color = color.as_deref().map(normalize_color).map(Value::from);
+    vec![
+        (
+            label_key(name, None),
+            Some(Value::from(LABEL_PRESENT.to_owned())),
+        ),
+        (label_key(name, Some("color")), color),
+        (
+            label_key(name, Some("description")),
+            description.clone().map(Value::from),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(key, val)| val.map(|val| (key, val)))
+    .collect()
+}
+
+/// Get the keys and values of each rule set on a protected branch.
+pub(crate) fn protected_rules(branch: &ProtectedBranch) -> Vec<(String, Value)> {
+    let ProtectedBranch {
+        name,
+        required_approving_review_count,
+        dismiss_stale_reviews,
+        require_code_owner_reviews,
+        required_status_checks,
+        strict_status_checks,
+        enforce_admins,
+        required_linear_history,
+        allow_force_pushes,
+        allow_deletions,
+    } = branch;
+    vec![
+        (
+            "required-approving-review-count",
+            required_approving_review_count.map(Value::from),
+        ),
+        (
+            "dismiss-stale-reviews",
+            dismiss_stale_reviews.map(Value::from),
+        ),
+        (
+            "require-code-owner-reviews",
+            require_code_owner_reviews.map(Value::from),
+        ),
+        (
+            "required-status-checks",
+            required_status_checks.clone().map(Value::list),
+        ),
+        (
+            "strict-status-checks",
+            strict_status_checks.map(Value::from),
+        ),
+        ("enforce-admins", enforce_admins.map(Value::from)),
+        (
+            "required-linear-history",
+            required_linear_history.map(Value::from),
+        ),
+        ("allow-force-pushes", allow_force_pushes.map(Value::from)),
+        ("allow-deletions", allow_deletions.map(Value::from)),
+    ]
+    .into_iter()
+    .filter_map(|(rule, val)| val.map(|val| (protected_rule_key(name, rule), val)))
+    .collect()
+}
+
 #[cfg(test)]
 mod test {
-    use super::{retrieve::Retrieve, Diff, FileReader};
-    use crate::commands::settings::{GramSettings, Options, ProtectedBranch};
+    use super::{
+        model::{SettingDiff, Value},
+        report::Format,
+        retrieve::{Retrieve, Sections},
+        Diff, DiffableSettings, FileReader,
+    };
+    use crate::commands::settings::{
+        spans::Spans, Collaborator, ContentType, GramSettings, Label, Labels, Options, Permission,
+        ProtectedBranch, Secret, Team, Webhook,
+    };
+    use crate::github::{fake::FakeGithub, OrgRepository};
     use anyhow::anyhow;
     use async_trait::async_trait;
 
@@ -154,28 +909,13 @@ mod test {
         settings: &'a GramSettings,
     }
 
-    struct FailingFileReader {}
-
     impl<'a> FileReader for SucceedingFileReader<'a> {
         fn read_to_string<P: AsRef<Path>>(&self, _: P) -> Result<String, std::io::Error> {
             unimplemented!()
         }
 
-        fn read_settings(&self, _: &PathBuf) -> anyhow::Result<GramSettings> {
-            Ok(self.settings.clone())
-        }
-    }
-
-    impl<'a> FileReader for FailingFileReader {
-        fn read_to_string<P: AsRef<Path>>(
-            &self,
-            _path: P,
-        ) -> anyhow::Result<String, std::io::Error> {
-            unimplemented!()
-        }
-
-        fn read_settings(&self, _settings_location: &PathBuf) -> anyhow::Result<GramSettings> {
-            Err(anyhow!(""))
+        fn read_settings(&self, _: &PathBuf) -> anyhow::Result<(GramSettings, Spans)> {
+            Ok((self.settings.clone(), Spans::default()))
         }
     }
 
@@ -185,7 +925,12 @@ mod test {
 
     #[async_trait]
     impl Retrieve for FakeRetriever {
-        async fn retrieve(&self, _owner: &str, _repo: &str) -> anyhow::Result<GramSettings> {
+        async fn retrieve(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _: Sections,
+        ) -> anyhow::Result<GramSettings> {
             match &self.settings {
                 Some(settings) => Ok(settings.clone()),
                 None => Err(anyhow!("")),
@@ -201,15 +946,19 @@ mod test {
                 allow_merge_commit: Some(true),
                 allow_rebase_merge: Some(true),
                 delete_branch_on_merge: Some(true),
+                ..Options::default()
             }),
             protected: Some(vec![
                 ProtectedBranch {
                     name: "a".to_owned(),
+                    ..ProtectedBranch::default()
                 },
                 ProtectedBranch {
                     name: "b".to_owned(),
+                    ..ProtectedBranch::default()
                 },
             ]),
+            ..GramSettings::default()
         }
     }
 
@@ -221,18 +970,27 @@ mod test {
                 allow_merge_commit: Some(false),
                 allow_rebase_merge: Some(false),
                 delete_branch_on_merge: Some(false),
+                ..Options::default()
             }),
             protected: Some(vec![ProtectedBranch {
                 name: "b".to_owned(),
+                ..ProtectedBranch::default()
             }]),
+            ..GramSettings::default()
         }
     }
 
     fn default_diff() -> Diff {
         Diff {
-            owner: "".to_owned(),
-            repo: "".to_owned(),
-            settings_file: PathBuf::new(),
+            repository: None,
+            owner: Some("".to_owned()),
+            repo: Some("".to_owned()),
+            settings_file: Some(PathBuf::new()),
+            remote: "origin".to_owned(),
+            org: None,
+            manifest: None,
+            concurrency: 8,
+            format: Format::Text,
         }
     }
 
@@ -241,7 +999,7 @@ mod test {
             .iter()
             .fold(String::new(), |mut acc, branch| {
                 acc.push_str(&branch.name);
-                acc.push_str(" ");
+                acc.push(' ');
                 acc
             })
             .trim()
@@ -263,7 +1021,7 @@ mod test {
         };
 
         // act
-        let result = diff.handle(reader, retriever).await;
+        let result = diff.handle(reader, retriever, &FakeGithub::default()).await;
 
         // assert
         assert!(result.is_err());
@@ -313,4 +1071,485 @@ mod test {
         assert_eq!(delete_branch_on_merge_error, diffs[4]);
         assert_eq!(protected_branch_master_error, diffs[5]);
     }
+
+    fn label(name: &str, color: Option<&str>, description: Option<&str>) -> Label {
+        Label {
+            name: name.to_owned(),
+            color: color.map(str::to_owned),
+            description: description.map(str::to_owned),
+        }
+    }
+
+    #[tokio::test]
+    async fn diff_should_report_missing_changed_and_pruned_labels() {
+        // arrange
+        let local_settings = GramSettings {
+            labels: Some(Labels {
+                prune: Some(true),
+                items: vec![
+                    label("bug", Some("#D73A4A"), None),
+                    label("docs", None, Some("Documentation")),
+                ],
+            }),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            labels: Some(
+                vec![
+                    label("bug", Some("d73a4a"), Some("Something is broken")),
+                    label("wontfix", Some("ffffff"), None),
+                ]
+                .into(),
+            ),
+            ..GramSettings::default()
+        };
+        let reader = SucceedingFileReader {
+            settings: &local_settings,
+        };
+        let retriever = FakeRetriever {
+            settings: Some(repo_settings),
+        };
+
+        // act
+        let result = default_diff()
+            .handle(reader, retriever, &FakeGithub::default())
+            .await;
+
+        // assert
+        assert!(result.is_err());
+        assert_eq!(
+            "Actual settings differ from expected!\n\
+             [labels.docs]: expected [present] but it has no value\n\
+             [labels.docs.description]: expected [Documentation] but it has no value\n\
+             [labels.wontfix]: expected no value got [present]\n",
+            format!("{}", result.err().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_should_ignore_extra_labels_unless_pruning() {
+        // arrange
+        let local_settings = GramSettings {
+            labels: Some(vec![label("bug", None, None)].into()),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            labels: Some(vec![label("bug", None, None), label("wontfix", None, None)].into()),
+            ..GramSettings::default()
+        };
+        let reader = SucceedingFileReader {
+            settings: &local_settings,
+        };
+        let retriever = FakeRetriever {
+            settings: Some(repo_settings),
+        };
+
+        // act
+        let result = default_diff()
+            .handle(reader, retriever, &FakeGithub::default())
+            .await;
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+    }
+
+    #[tokio::test]
+    async fn diff_should_report_collaborator_and_team_permissions_per_entry() {
+        // arrange
+        let collaborator = |username: &str, permission| Collaborator {
+            username: username.to_owned(),
+            permission,
+        };
+        let local_settings = GramSettings {
+            collaborators: Some(vec![
+                collaborator("alice", Permission::Admin),
+                collaborator("bob", Permission::Push),
+            ]),
+            teams: Some(vec![Team {
+                slug: "core".to_owned(),
+                permission: Permission::Maintain,
+            }]),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            collaborators: Some(vec![
+                collaborator("alice", Permission::Push),
+                collaborator("mallory", Permission::Admin),
+            ]),
+            teams: Some(vec![Team {
+                slug: "core".to_owned(),
+                permission: Permission::Maintain,
+            }]),
+            ..GramSettings::default()
+        };
+        let reader = SucceedingFileReader {
+            settings: &local_settings,
+        };
+        let retriever = FakeRetriever {
+            settings: Some(repo_settings),
+        };
+
+        // act
+        let result = default_diff()
+            .handle(reader, retriever, &FakeGithub::default())
+            .await;
+
+        // assert
+        assert!(result.is_err());
+        assert_eq!(
+            "Actual settings differ from expected!\n\
+             [collaborators.alice]: expected [admin] got [push]\n\
+             [collaborators.bob]: expected [push] but it has no value\n\
+             [collaborators.mallory]: expected no value got [admin]\n",
+            format!("{}", result.err().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_should_report_webhook_drift_without_the_secret() {
+        // arrange
+        let local_settings = GramSettings {
+            webhooks: Some(vec![
+                Webhook {
+                    url: "https://ci.example.com/hook".to_owned(),
+                    content_type: Some(ContentType::Json),
+                    events: Some(vec!["push".to_owned(), "pull_request".to_owned()]),
+                    secret_env: Some("CI_HOOK_SECRET".to_owned()),
+                    ..Webhook::default()
+                },
+                Webhook {
+                    url: "https://chat.example.com/hook".to_owned(),
+                    ..Webhook::default()
+                },
+            ]),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            webhooks: Some(vec![
+                Webhook {
+                    url: "https://ci.example.com/hook".to_owned(),
+                    content_type: Some(ContentType::Form),
+                    events: Some(vec!["pull_request".to_owned(), "push".to_owned()]),
+                    active: Some(true),
+                    id: Some(1),
+                    ..Webhook::default()
+                },
+                Webhook {
+                    url: "https://other.example.com/hook".to_owned(),
+                    id: Some(2),
+                    ..Webhook::default()
+                },
+            ]),
+            ..GramSettings::default()
+        };
+        let reader = SucceedingFileReader {
+            settings: &local_settings,
+        };
+        let retriever = FakeRetriever {
+            settings: Some(repo_settings),
+        };
+
+        // act
+        let result = default_diff()
+            .handle(reader, retriever, &FakeGithub::default())
+            .await;
+
+        // assert
+        assert!(result.is_err());
+        assert_eq!(
+            "Actual settings differ from expected!\n\
+             [webhooks.https://chat.example.com/hook]: expected [present] but it has no value\n\
+             [webhooks.https://ci.example.com/hook.content-type]: expected [json] got [form]\n\
+             [webhooks.https://ci.example.com/hook.secret]: expected [set] but it has no value\n",
+            format!("{}", result.err().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn diff_should_report_missing_and_extra_secrets() {
+        // arrange
+        let secrets = |names: &[&str]| {
+            let secrets = names
+                .iter()
+                .map(|name| (name.to_string(), Secret::default()))
+                .collect();
+            Some(secrets)
+        };
+        let local_settings = GramSettings {
+            secrets: secrets(&["DEPLOY_TOKEN", "NPM_TOKEN"]),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            secrets: secrets(&["NPM_TOKEN", "OLD_TOKEN"]),
+            ..GramSettings::default()
+        };
+        let reader = SucceedingFileReader {
+            settings: &local_settings,
+        };
+        let retriever = FakeRetriever {
+            settings: Some(repo_settings),
+        };
+
+        // act
+        let result = default_diff()
+            .handle(reader, retriever, &FakeGithub::default())
+            .await;
+
+        // assert
+        assert!(result.is_err());
+        assert_eq!(
+            "Actual settings differ from expected!\n\
+             [secrets.DEPLOY_TOKEN]: expected [present] but it has no value\n\
+             [secrets.OLD_TOKEN]: expected no value got [present]\n",
+            format!("{}", result.err().unwrap())
+        );
+    }
+
+    #[test]
+    fn diff_should_compare_protected_branches_regardless_of_order() {
+        // arrange
+        let mut reversed = local_settings();
+        reversed.protected.as_mut().unwrap().reverse();
+
+        // act
+        let diffs = Diff::diff(
+            DiffableSettings(&local_settings()),
+            DiffableSettings(&reversed),
+        );
+
+        // assert
+        assert_eq!(Vec::<SettingDiff>::new(), diffs);
+    }
+
+    #[test]
+    fn diff_should_type_values_and_tell_added_members_from_missing_settings() {
+        // arrange
+        let local_settings = GramSettings {
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                required_approving_review_count: Some(2),
+                required_status_checks: Some(vec!["test".to_owned(), "lint".to_owned()]),
+                ..ProtectedBranch::default()
+            }]),
+            labels: Some(vec![label("bug", None, None)].into()),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                required_approving_review_count: Some(1),
+                ..ProtectedBranch::default()
+            }]),
+            labels: Some(Vec::new().into()),
+            ..GramSettings::default()
+        };
+
+        // act
+        let diffs = Diff::diff(
+            DiffableSettings(&local_settings),
+            DiffableSettings(&repo_settings),
+        );
+
+        // assert
+        assert_eq!(
+            vec![
+                SettingDiff::Added {
+                    key: "labels.bug".to_owned(),
+                    expected: Value::String("present".to_owned()),
+                },
+                SettingDiff::Changed {
+                    key: "protected.master.required-approving-review-count".to_owned(),
+                    expected: Value::Int(2),
+                    actual: Value::Int(1),
+                },
+                SettingDiff::Missing {
+                    key: "protected.master.required-status-checks".to_owned(),
+                    expected: Value::List(vec!["lint".to_owned(), "test".to_owned()]),
+                },
+            ],
+            diffs
+        );
+    }
+
+    #[test]
+    fn diff_should_report_options_github_does_not_return_as_unverifiable() {
+        // arrange
+        let local_settings = GramSettings {
+            options: Some(Options {
+                has_wiki: Some(false),
+                allow_auto_merge: Some(true),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        };
+        let repo_settings = GramSettings {
+            options: Some(Options {
+                has_wiki: Some(true),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        };
+
+        // act
+        let diffs = Diff::diff(
+            DiffableSettings(&local_settings),
+            DiffableSettings(&repo_settings),
+        );
+
+        // assert
+        assert_eq!(
+            vec![
+                SettingDiff::Unverifiable {
+                    key: "options.allow-auto-merge".to_owned(),
+                    expected: Value::Bool(true),
+                },
+                SettingDiff::Changed {
+                    key: "options.has-wiki".to_owned(),
+                    expected: Value::Bool(false),
+                    actual: Value::Bool(true),
+                },
+            ],
+            diffs
+        );
+        assert_eq!(
+            "[options.allow-auto-merge]: expected [true] but github did not return it, \
+             so it can't be checked",
+            diffs[0].to_string()
+        );
+    }
+
+    struct OrgFileReader;
+
+    impl FileReader for OrgFileReader {
+        fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, std::io::Error> {
+            match path.as_ref().to_str() {
+                Some("gram/manifest.toml") => Ok("[[profiles]]\n\
+                     repos = [\"*-service\"]\n\
+                     settings = \"service.toml\"\n"
+                    .to_owned()),
+                Some("gram/service.toml") => Ok("description = \"a service\"\n".to_owned()),
+                _ => Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
+            }
+        }
+    }
+
+    /// Gives each repository the description of its name, failing for `broken-service`.
+    struct OrgRetriever;
+
+    #[async_trait]
+    impl Retrieve for OrgRetriever {
+        async fn retrieve(
+            &self,
+            _owner: &str,
+            repo: &str,
+            _: Sections,
+        ) -> anyhow::Result<GramSettings> {
+            match repo {
+                "broken-service" => Err(anyhow!("not found")),
+                "good-service" => Ok(GramSettings {
+                    description: Some("a service".to_owned()),
+                    ..GramSettings::default()
+                }),
+                _ => Ok(GramSettings {
+                    description: Some(repo.to_owned()),
+                    ..GramSettings::default()
+                }),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn org_report_should_diff_each_matching_repository_of_the_org() {
+        // arrange
+        let repo = |name: &str, archived| OrgRepository {
+            name: name.to_owned(),
+            archived,
+        };
+        let client = FakeGithub {
+            org_repositories: Some(vec![
+                repo("website", false),
+                repo("old-service", true),
+                repo("good-service", false),
+                repo("broken-service", false),
+                repo("drifted-service", false),
+            ]),
+            ..FakeGithub::default()
+        };
+        let diff = Diff {
+            repository: None,
+            owner: None,
+            repo: None,
+            settings_file: None,
+            remote: "origin".to_owned(),
+            org: Some("org".to_owned()),
+            manifest: Some(PathBuf::from("gram/manifest.toml")),
+            concurrency: 2,
+            format: Format::Text,
+        };
+
+        // act
+        let report = diff
+            .org_report(
+                "org",
+                Path::new("gram/manifest.toml"),
+                &OrgFileReader,
+                &OrgRetriever,
+                &client,
+            )
+            .await
+            .unwrap();
+
+        // assert
+        assert_eq!(2, report.failed());
+        assert_eq!(
+            "org/drifted-service: 1 difference(s)\n  \
+             [description]: expected [a service] got [drifted-service]\n\
+             org/good-service: ok\n\
+             org/broken-service: failed, not found\n\
+             3 repositories checked, 1 differ from expected, 1 failed\n",
+            report.render(Format::Text).unwrap()
+        );
+    }
+
+    struct GitConfigReader;
+
+    impl FileReader for GitConfigReader {
+        fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, std::io::Error> {
+            match path.as_ref().ends_with(".git/config") {
+                true => Ok(
+                    "[remote \"origin\"]\n\turl = git@github.com:remote-owner/remote-repo.git\n"
+                        .to_owned(),
+                ),
+                false => Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
+            }
+        }
+    }
+
+    #[test]
+    fn repository_should_prefer_flags_then_the_argument_then_the_git_remote() {
+        // arrange
+        let diff = |repository: Option<&str>, owner: Option<&str>| Diff {
+            repository: repository.map(str::to_owned),
+            owner: owner.map(str::to_owned),
+            repo: None,
+            ..default_diff()
+        };
+
+        // act
+        let remote = diff(None, None).repository(&GitConfigReader).unwrap();
+        let argument = diff(Some("https://github.com/owner/repo"), None)
+            .repository(&GitConfigReader)
+            .unwrap();
+        let flag = diff(Some("owner/repo"), Some("other"))
+            .repository(&GitConfigReader)
+            .unwrap();
+
+        // assert
+        assert_eq!(
+            ("remote-owner".to_owned(), "remote-repo".to_owned()),
+            remote
+        );
+        assert_eq!(("owner".to_owned(), "repo".to_owned()), argument);
+        assert_eq!(("other".to_owned(), "repo".to_owned()), flag);
+    }
 }
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The value of a single settings key.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Int(u64),
+    String(String),
+    /// A list where order does not matter, it is kept sorted so lists compare as sets.
+    List(Vec<String>),
+}
+
+impl Value {
+    pub fn list(mut values: Vec<String>) -> Self {
+        values.sort();
+        Value::List(values)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Self {
+        Value::Bool(val)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(val: u32) -> Self {
+        Value::Int(u64::from(val))
+    }
+}
+
+impl From<String> for Value {
+    fn from(val: String) -> Self {
+        Value::String(val)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(val) => write!(f, "{}", val),
+            Value::Int(val) => write!(f, "{}", val),
+            Value::String(val) => write!(f, "{}", val),
+            Value::List(vals) => write!(f, "{}", vals.join(" ")),
+        }
+    }
+}
+
+/// A single difference between configured and actual settings.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum SettingDiff {
+    /// The setting has a different value on the repository.
+    Changed {
+        key: String,
+        expected: Value,
+        actual: Value,
+    },
+    /// The setting has no value on the repository.
+    Missing { key: String, expected: Value },
+    /// The setting always has a value on a repository but github did not
+    /// return it, e.g. an older Github Enterprise Server, so it can't be checked.
+    Unverifiable { key: String, expected: Value },
+    /// A member of a collection, e.g. a label, which is configured but is
+    /// not on the repository, so would be added.
+    Added { key: String, expected: Value },
+    /// A member of a collection which is on the repository but is not
+    /// configured, so would be removed.
+    Removed { key: String, actual: Value },
+}
+
+impl SettingDiff {
+    pub fn key(&self) -> &str {
+        match self {
+            SettingDiff::Changed { key, .. }
+            | SettingDiff::Missing { key, .. }
+            | SettingDiff::Unverifiable { key, .. }
+            | SettingDiff::Added { key, .. }
+            | SettingDiff::Removed { key, .. } => key,
+        }
+    }
+
+    /// The configured value, if the setting is configured.
+    pub fn expected(&self) -> Option<&Value> {
+        match self {
+            SettingDiff::Changed { expected, .. }
+            | SettingDiff::Missing { expected, .. }
+            | SettingDiff::Unverifiable { expected, .. }
+            | SettingDiff::Added { expected, .. } => Some(expected),
+            SettingDiff::Removed { .. } => None,
+        }
+    }
+
+    /// The value on the repository, if it has one.
+    pub fn actual(&self) -> Option<&Value> {
+        match self {
+            SettingDiff::Changed { actual, .. } | SettingDiff::Removed { actual, .. } => {
+                Some(actual)
+            }
+            SettingDiff::Missing { .. }
+            | SettingDiff::Unverifiable { .. }
+            | SettingDiff::Added { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for SettingDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingDiff::Changed {
+                key,
+                expected,
+                actual,
+            } => write!(f, "[{}]: expected [{}] got [{}]", key, expected, actual),
+            SettingDiff::Missing { key, expected } | SettingDiff::Added { key, expected } => {
+                write!(f, "[{}]: expected [{}] but it has no value", key, expected)
+            }
+            SettingDiff::Unverifiable { key, expected } => write!(
+                f,
+                "[{}]: expected [{}] but github did not return it, so it can't be checked",
+                key, expected
+            ),
+            SettingDiff::Removed { key, actual } => {
+                write!(f, "[{}]: expected no value got [{}]", key, actual)
+            }
+        }
+    }
+}
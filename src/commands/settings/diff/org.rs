@@ -0,0 +1,305 @@
+//! Diffing every repository of an organization against a manifest.
+use super::report::{escape, escape_data, Format, Report, JUNIT_HEADER};
+use crate::commands::FileReader;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Maps repository names to the settings file they should match.
+///
+/// ```toml
+/// [[profiles]]
+/// repos = ["*-service", "api"]
+/// settings = "profiles/service.toml"
+/// ```
+///
+/// A repository uses the first profile with a matching pattern, repositories
+/// no profile matches are not diffed. Settings paths are relative to the
+/// manifest.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Profile {
+    /// Repository names, `*` matches any characters and `?` any one character.
+    pub repos: Vec<String>,
+    pub settings: PathBuf,
+}
+
+impl Manifest {
+    pub fn read<F: FileReader>(reader: &F, path: &Path) -> Result<Self> {
+        let manifest_str = reader.read_to_string(path)?;
+        let mut manifest = toml::from_str::<Self>(&manifest_str)
+            .map_err(|e| anyhow!("Failed to read manifest {}: {}", path.display(), e))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for profile in &mut manifest.profiles {
+            profile.settings = dir.join(&profile.settings);
+        }
+        Ok(manifest)
+    }
+
+    /// The settings file for a repository, if a profile matches it.
+    pub fn settings(&self, repo: &str) -> Option<&Path> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.repos.iter().any(|pattern| glob(pattern, repo)))
+            .map(|profile| profile.settings.as_path())
+    }
+}
+
+/// Match a name against a pattern where `*` matches any characters and `?`
+/// any one character.
+pub fn glob(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    let (mut p, mut n) = (0, 0);
+    // Where to resume if the chars after the last `*` stop matching.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// A repository which could not be diffed.
+#[derive(Debug, Serialize)]
+pub struct Failure {
+    pub repo: String,
+    pub error: String,
+}
+
+/// The results of diffing the repositories of an organization.
+#[derive(Debug, Serialize)]
+pub struct OrgReport<'a> {
+    pub org: &'a str,
+    pub repositories: Vec<Report<'a>>,
+    pub failures: Vec<Failure>,
+}
+
+impl<'a> OrgReport<'a> {
+    /// How many repositories differ from their settings or could not be diffed.
+    pub fn failed(&self) -> usize {
+        let drifted = self
+            .repositories
+            .iter()
+            .filter(|report| !report.diffs.is_empty())
+            .count();
+        drifted + self.failures.len()
+    }
+
+    pub fn render(&self, format: Format) -> Result<String> {
+        match format {
+            Format::Text => Ok(self.text()),
+            Format::Json => Ok(serde_json::to_string_pretty(self)?),
+            Format::Yaml => Ok(serde_yaml::to_string(self)?),
+            Format::Junit => Ok(self.junit()),
+            Format::Github => Ok(self.github()),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for report in &self.repositories {
+            match report.diffs.len() {
+                0 => text.push_str(&format!("{}/{}: ok\n", self.org, report.repo)),
+                count => {
+                    text.push_str(&format!(
+                        "{}/{}: {} difference(s)\n",
+                        self.org, report.repo, count
+                    ));
+                    for diff in &report.diffs {
+                        text.push_str(&format!("  {}\n", diff));
+                    }
+                }
+            }
+        }
+        for failure in &self.failures {
+            text.push_str(&format!(
+                "{}/{}: failed, {}\n",
+                self.org, failure.repo, failure.error
+            ));
+        }
+        text.push_str(&format!(
+            "{} repositories checked, {} differ from expected, {} failed\n",
+            self.repositories.len() + self.failures.len(),
+            self.failed() - self.failures.len(),
+            self.failures.len()
+        ));
+        text
+    }
+
+    fn junit(&self) -> String {
+        let mut xml = String::from(JUNIT_HEADER);
+        xml.push_str(&format!("<testsuites name=\"{}\">\n", escape(self.org)));
+        for report in &self.repositories {
+            xml.push_str(&report.testsuite());
+        }
+        for failure in &self.failures {
+            let name = escape(&format!("{}/{}", self.org, failure.repo));
+            let message = escape(&failure.error);
+            xml.push_str(&format!(
+                "<testsuite name=\"{}\" tests=\"1\" errors=\"1\">\n  \
+                 <testcase classname=\"{}\" name=\"settings\">\n    \
+                 <error message=\"{}\">{}</error>\n  \
+                 </testcase>\n\
+                 </testsuite>\n",
+                name, name, message, message
+            ));
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    fn github(&self) -> String {
+        let mut annotations = self
+            .repositories
+            .iter()
+            .map(Report::github)
+            .collect::<String>();
+        for failure in &self.failures {
+            annotations.push_str(&format!(
+                "::error::{}\n",
+                escape_data(&format!("{}/{}: {}", self.org, failure.repo, failure.error))
+            ));
+        }
+        annotations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{glob, Failure, Manifest, OrgReport};
+    use crate::commands::settings::{
+        diff::{
+            model::{SettingDiff, Value},
+            report::{Format, Report},
+        },
+        spans::Spans,
+        FileReader,
+    };
+    use std::path::{Path, PathBuf};
+
+    struct StringReader;
+
+    impl FileReader for StringReader {
+        fn read_to_string<P: AsRef<Path>>(&self, _: P) -> Result<String, std::io::Error> {
+            Ok(r#"
+[[profiles]]
+repos = ["*-service", "api"]
+settings = "service.toml"
+
+[[profiles]]
+repos = ["*"]
+settings = "default.toml"
+"#
+            .to_owned())
+        }
+    }
+
+    #[test]
+    fn glob_should_match_wildcards() {
+        // assert
+        assert!(glob("*-service", "billing-service"));
+        assert!(glob("infra-*", "infra-"));
+        assert!(glob("a*b*c", "aXbYbZc"));
+        assert!(glob("v?", "v2"));
+        assert!(!glob("v?", "v10"));
+        assert!(!glob("*-service", "service"));
+        assert!(!glob("api", "api-docs"));
+    }
+
+    #[test]
+    fn settings_should_use_the_first_matching_profile_relative_to_the_manifest() {
+        // arrange
+        let manifest = Manifest::read(&StringReader, Path::new("gram/manifest.toml")).unwrap();
+
+        // act
+        let service = manifest.settings("billing-service");
+        let api = manifest.settings("api");
+        let other = manifest.settings("docs");
+
+        // assert
+        assert_eq!(Some(Path::new("gram/service.toml")), service);
+        assert_eq!(Some(Path::new("gram/service.toml")), api);
+        assert_eq!(Some(Path::new("gram/default.toml")), other);
+    }
+
+    fn report() -> OrgReport<'static> {
+        let repo = |repo: &str, diffs| Report {
+            owner: "org",
+            repo: repo.to_owned(),
+            keys: vec!["description".to_owned()],
+            diffs,
+            file: PathBuf::from("settings.toml"),
+            spans: Spans::default(),
+        };
+        OrgReport {
+            org: "org",
+            repositories: vec![
+                repo("a", Vec::new()),
+                repo(
+                    "b",
+                    vec![SettingDiff::Changed {
+                        key: "description".to_owned(),
+                        expected: Value::String("x".to_owned()),
+                        actual: Value::String("y".to_owned()),
+                    }],
+                ),
+            ],
+            failures: vec![Failure {
+                repo: "c".to_owned(),
+                error: "not found".to_owned(),
+            }],
+        }
+    }
+
+    #[test]
+    fn render_should_summarize_every_repository_as_text() {
+        // act
+        let text = report().render(Format::Text).unwrap();
+
+        // assert
+        assert_eq!(
+            "org/a: ok\n\
+             org/b: 1 difference(s)\n  \
+             [description]: expected [x] got [y]\n\
+             org/c: failed, not found\n\
+             3 repositories checked, 1 differ from expected, 1 failed\n",
+            text
+        );
+    }
+
+    #[test]
+    fn render_should_write_a_junit_testsuite_per_repository() {
+        // act
+        let xml = report().render(Format::Junit).unwrap();
+
+        // assert
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"org\">\n"
+        ));
+        assert_eq!(3, xml.matches("<testsuite ").count());
+        assert!(xml.contains("<error message=\"not found\">not found</error>"));
+        assert!(xml.ends_with("</testsuites>\n"));
+    }
+}
@@ -0,0 +1,291 @@
+use super::model::SettingDiff;
+use crate::commands::settings::spans::Spans;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The formats a diff can be written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Format {
+    /// One human readable line per difference.
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    /// A JUnit XML report with a testcase per settings key.
+    Junit,
+    /// Github Actions workflow commands, annotating the settings file.
+    Github,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "junit" => Ok(Format::Junit),
+            "github" => Ok(Format::Github),
+            _ => Err(anyhow!(
+                "Unknown format [{}], expected one of text, json, yaml, junit or github",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Junit => "junit",
+            Format::Github => "github",
+        };
+        write!(f, "{}", format)
+    }
+}
+
+/// The result of diffing a single repository.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub owner: &'a str,
+    pub repo: String,
+    /// Every settings key which was compared, sorted.
+    #[serde(skip)]
+    pub keys: Vec<String>,
+    pub diffs: Vec<SettingDiff>,
+    /// The settings file the diffs are annotated on.
+    #[serde(skip)]
+    pub file: PathBuf,
+    /// The lines of the keys in the settings file.
+    #[serde(skip)]
+    pub spans: Spans,
+}
+
+impl<'a> Report<'a> {
+    /// Write this report in the given format.
+    pub fn render(&self, format: Format) -> Result<String> {
+        match format {
+            Format::Text => Ok(self.text()),
+            Format::Json => Ok(serde_json::to_string_pretty(self)?),
+            Format::Yaml => Ok(serde_yaml::to_string(self)?),
+            Format::Junit => Ok(self.junit()),
+            Format::Github => Ok(self.github()),
+        }
+    }
+
+    /// See https://docs.github.com/en/actions/reference/workflow-commands-for-github-actions
+    pub(super) fn github(&self) -> String {
+        let file = escape_property(&self.file.display().to_string());
+        self.diffs.iter().fold(String::new(), |mut acc, diff| {
+            acc.push_str(&format!("::error file={}", file));
+            if let Some(line) = self.spans.line(diff.key()) {
+                acc.push_str(&format!(",line={}", line));
+            }
+            acc.push_str(&format!("::{}\n", escape_data(&diff.to_string())));
+            acc
+        })
+    }
+
+    fn text(&self) -> String {
+        self.diffs.iter().fold(String::new(), |mut acc, diff| {
+            acc.push_str(&diff.to_string());
+            acc.push('\n');
+            acc
+        })
+    }
+
+    fn junit(&self) -> String {
+        let mut xml = String::from(JUNIT_HEADER);
+        xml.push_str(&self.testsuite());
+        xml
+    }
+
+    /// A JUnit testsuite for this repository.
+    pub(super) fn testsuite(&self) -> String {
+        let name = escape(&format!("{}/{}", self.owner, self.repo));
+        let mut xml = String::new();
+        xml.push_str(&format!(
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            name,
+            self.keys.len(),
+            self.diffs.len()
+        ));
+        for key in &self.keys {
+            let diff = self.diffs.iter().find(|diff| diff.key() == key);
+            xml.push_str(&format!(
+                "  <testcase classname=\"{}\" name=\"{}\"",
+                name,
+                escape(key)
+            ));
+            match diff {
+                Some(diff) => {
+                    let message = escape(&diff.to_string());
+                    xml.push_str(&format!(
+                        ">\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                        message, message
+                    ));
+                }
+                None => xml.push_str("/>\n"),
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+pub(super) static JUNIT_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+pub(super) fn escape(s: &str) -> String {
+    s.chars().fold(String::new(), |mut acc, c| {
+        match c {
+            '&' => acc.push_str("&amp;"),
+            '<' => acc.push_str("&lt;"),
+            '>' => acc.push_str("&gt;"),
+            '"' => acc.push_str("&quot;"),
+            '\'' => acc.push_str("&apos;"),
+            c => acc.push(c),
+        }
+        acc
+    })
+}
+
+pub(super) fn escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(s: &str) -> String {
+    escape_data(s).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Format, Report};
+    use crate::commands::settings::{
+        diff::model::{SettingDiff, Value},
+        spans::Spans,
+    };
+    use std::path::PathBuf;
+
+    fn report() -> Report<'static> {
+        Report {
+            owner: "owner",
+            repo: "repo".to_owned(),
+            keys: vec!["description".to_owned(), "labels.a&b".to_owned()],
+            diffs: vec![SettingDiff::Changed {
+                key: "description".to_owned(),
+                expected: Value::String("<a>".to_owned()),
+                actual: Value::String("b".to_owned()),
+            }],
+            file: PathBuf::from("settings.toml"),
+            spans: Spans::default(),
+        }
+    }
+
+    #[test]
+    fn format_should_parse_known_formats_and_reject_others() {
+        // act
+        let formats = ["text", "json", "yaml", "junit", "github"]
+            .iter()
+            .map(|s| s.parse::<Format>().unwrap())
+            .collect::<Vec<Format>>();
+        let unknown = "xml".parse::<Format>();
+
+        // assert
+        assert_eq!(
+            vec![
+                Format::Text,
+                Format::Json,
+                Format::Yaml,
+                Format::Junit,
+                Format::Github
+            ],
+            formats
+        );
+        assert_eq!(
+            "Unknown format [xml], expected one of text, json, yaml, junit or github",
+            format!("{}", unknown.err().unwrap())
+        );
+    }
+
+    #[test]
+    fn render_should_write_json_with_typed_values() {
+        // act
+        let json = report().render(Format::Json).unwrap();
+
+        // assert
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "owner": "owner",
+                "repo": "repo",
+                "diffs": [{
+                    "kind": "changed",
+                    "key": "description",
+                    "expected": "<a>",
+                    "actual": "b"
+                }]
+            }),
+            value
+        );
+    }
+
+    #[test]
+    fn render_should_write_yaml() {
+        // act
+        let yaml = report().render(Format::Yaml).unwrap();
+
+        // assert
+        let value = serde_yaml::from_str::<serde_yaml::Value>(&yaml).unwrap();
+        assert_eq!("changed", value["diffs"][0]["kind"].as_str().unwrap());
+        assert_eq!("description", value["diffs"][0]["key"].as_str().unwrap());
+    }
+
+    #[test]
+    fn render_should_write_a_junit_testcase_per_key_with_failures_for_drift() {
+        // act
+        let xml = report().render(Format::Junit).unwrap();
+
+        // assert
+        assert_eq!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <testsuite name=\"owner/repo\" tests=\"2\" failures=\"1\">\n  \
+             <testcase classname=\"owner/repo\" name=\"description\">\n    \
+             <failure message=\"[description]: expected [&lt;a&gt;] got [b]\">\
+             [description]: expected [&lt;a&gt;] got [b]</failure>\n  \
+             </testcase>\n  \
+             <testcase classname=\"owner/repo\" name=\"labels.a&amp;b\"/>\n\
+             </testsuite>\n",
+            xml
+        );
+    }
+
+    #[test]
+    fn render_should_annotate_the_line_of_each_drifted_key() {
+        // arrange
+        let mut report = report();
+        report.spans = Spans::parse("\ndescription = \"<a>\"\n");
+        report.diffs.push(SettingDiff::Removed {
+            key: "collaborators.mallory".to_owned(),
+            actual: Value::String("50%".to_owned()),
+        });
+
+        // act
+        let annotations = report.render(Format::Github).unwrap();
+
+        // assert
+        assert_eq!(
+            "::error file=settings.toml,line=2::[description]: expected [<a>] got [b]\n\
+             ::error file=settings.toml::[collaborators.mallory]: expected no value got [50%25]\n",
+            annotations
+        );
+    }
+}
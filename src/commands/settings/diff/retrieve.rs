@@ -1,13 +1,93 @@
 use crate::{
-    commands::settings::{GramSettings, Options, ProtectedBranch},
-    github::GithubClient,
+    commands::settings::{
+        normalize_color, Actions, AllowedActions, Collaborator, DeploymentBranches, Environment,
+        GramSettings, Label, Options, Permission, ProtectedBranch, Secret, Team, Visibility,
+        Webhook,
+    },
+    github::{self, BranchProtection, GithubClient, Permissions, ProtectionRule, Reviewer},
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use std::collections::BTreeMap;
 
 #[async_trait]
 pub trait Retrieve {
-    async fn retrieve(&self, owner: &str, repo: &str) -> Result<GramSettings>;
+    /// Get the actual settings of a repository, only filling in the given sections.
+    async fn retrieve(&self, owner: &str, repo: &str, sections: Sections) -> Result<GramSettings>;
+}
+
+/// The sections of settings which need their own calls to github.
+///
+/// Some of these need admin access to the repository, and each costs at
+/// least one call, so only the sections which are compared are retrieved.
+/// The description and options always are, they come with the repository.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sections {
+    pub topics: bool,
+    pub protected: bool,
+    pub labels: bool,
+    pub collaborators: bool,
+    pub teams: bool,
+    pub webhooks: bool,
+    pub environments: bool,
+    pub secrets: bool,
+    pub actions: bool,
+}
+
+impl Sections {
+    /// Every section, e.g. to export all settings.
+    pub fn all() -> Self {
+        Sections {
+            topics: true,
+            protected: true,
+            labels: true,
+            collaborators: true,
+            teams: true,
+            webhooks: true,
+            environments: true,
+            secrets: true,
+            actions: true,
+        }
+    }
+
+    /// The sections a settings file configures.
+    pub fn configured(settings: &GramSettings) -> Self {
+        Sections {
+            topics: settings.topics.is_some(),
+            protected: settings.protected.is_some(),
+            labels: settings.labels.is_some(),
+            collaborators: settings.collaborators.is_some(),
+            teams: settings.teams.is_some(),
+            webhooks: settings.webhooks.is_some(),
+            environments: settings.environments.is_some(),
+            secrets: settings.secrets.is_some(),
+            actions: settings.actions.is_some(),
+        }
+    }
+
+    /// The sections holding any of the given diff keys, e.g. those of a plan.
+    pub fn holding<'a, I>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut sections = Sections::default();
+        for key in keys {
+            let section = key.split('.').next().unwrap_or_default();
+            match section {
+                "topics" => sections.topics = true,
+                "protected" => sections.protected = true,
+                "labels" => sections.labels = true,
+                "collaborators" => sections.collaborators = true,
+                "teams" => sections.teams = true,
+                "webhooks" => sections.webhooks = true,
+                "environments" => sections.environments = true,
+                "secrets" => sections.secrets = true,
+                "actions" => sections.actions = true,
+                _ => (),
+            }
+        }
+        sections
+    }
 }
 
 pub struct RetrieveSettings<'a, C> {
@@ -21,6 +101,232 @@ where
     pub fn new(client: &'a C) -> Self {
         RetrieveSettings { client }
     }
+
+    /// Get each protected branch along with its protection, `None` if there are none.
+    async fn protected(&self, owner: &str, repo: &str) -> Result<Option<Vec<ProtectedBranch>>> {
+        let mut protected = Vec::new();
+        for branch in self.client.protected_branches(owner, repo).await? {
+            let protection = self
+                .client
+                .branch_protection(owner, repo, &branch.name)
+                .await?;
+            protected.push(protected_branch(branch.name, protection));
+        }
+        Ok(match protected.is_empty() {
+            true => None,
+            false => Some(protected),
+        })
+    }
+
+    async fn labels(&self, owner: &str, repo: &str) -> Result<Vec<Label>> {
+        let labels = self.client.labels(owner, repo).await?;
+        Ok(labels
+            .into_iter()
+            .map(|label| Label {
+                name: label.name,
+                color: Some(normalize_color(&label.color)),
+                description: label.description,
+            })
+            .collect())
+    }
+
+    /// Get the users with access, including those who have been invited but
+    /// not accepted yet, so they are not invited again.
+    async fn collaborators(&self, owner: &str, repo: &str) -> Result<Vec<Collaborator>> {
+        let mut collaborators = self
+            .client
+            .collaborators(owner, repo)
+            .await?
+            .into_iter()
+            .map(|collaborator| Collaborator {
+                username: collaborator.login,
+                permission: highest_permission(collaborator.permissions),
+            })
+            .collect::<Vec<Collaborator>>();
+        for invitation in self.client.invitations(owner, repo).await? {
+            let username = match invitation.invitee.and_then(|invitee| invitee.login) {
+                Some(username) => username,
+                None => continue,
+            };
+            if collaborators
+                .iter()
+                .any(|collaborator| collaborator.username.eq_ignore_ascii_case(&username))
+            {
+                continue;
+            }
+            collaborators.push(Collaborator {
+                username,
+                permission: invitation_permission(&invitation.permissions)?,
+            });
+        }
+        Ok(collaborators)
+    }
+
+    /// Get the teams with access, custom repository roles cannot be configured so are an error.
+    async fn teams(&self, owner: &str, repo: &str) -> Result<Vec<Team>> {
+        self.client
+            .teams(owner, repo)
+            .await?
+            .into_iter()
+            .map(|team| {
+                let permission = team.permission.parse().map_err(|_| {
+                    anyhow!(
+                        "Team [{}] has the custom role [{}], which cannot be configured",
+                        team.slug,
+                        team.permission
+                    )
+                })?;
+                Ok(Team {
+                    slug: team.slug,
+                    permission,
+                })
+            })
+            .collect()
+    }
+
+    /// Get the webhooks which have a url, their secrets are never returned.
+    async fn webhooks(&self, owner: &str, repo: &str) -> Result<Vec<Webhook>> {
+        let hooks = self.client.hooks(owner, repo).await?;
+        Ok(hooks
+            .into_iter()
+            .filter_map(|hook| {
+                // Hooks without a url, e.g. for a github app, cannot be configured.
+                let url = hook.config.url?;
+                Some(Webhook {
+                    url,
+                    content_type: hook
+                        .config
+                        .content_type
+                        .and_then(|content_type| content_type.parse().ok()),
+                    events: Some(hook.events),
+                    active: Some(hook.active),
+                    secret_env: None,
+                    id: Some(hook.id),
+                    has_secret: hook.config.secret.is_some(),
+                })
+            })
+            .collect())
+    }
+
+    /// Build the actions settings from the actions permissions, the token
+    /// permissions of workflows and the actions variables.
+    ///
+    /// Github only has selected actions when `allowed-actions` is selected,
+    /// and no allowed actions when actions are disabled.
+    async fn actions(&self, owner: &str, repo: &str) -> Result<Actions> {
+        let permissions = self.client.actions_permissions(owner, repo).await?;
+        let allowed_actions = match permissions.allowed_actions.as_deref().map(str::parse) {
+            Some(allowed_actions) => Some(allowed_actions?),
+            None => None,
+        };
+        let selected = match allowed_actions {
+            Some(AllowedActions::Selected) => self.client.selected_actions(owner, repo).await?,
+            _ => github::SelectedActions::default(),
+        };
+        let workflow = self.client.workflow_permissions(owner, repo).await?;
+        let default_workflow_permissions = match workflow.default_workflow_permissions {
+            Some(permission) => Some(permission.parse()?),
+            None => None,
+        };
+        let variables = self
+            .client
+            .actions_variables(owner, repo)
+            .await?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value))
+            .collect();
+        Ok(Actions {
+            enabled: Some(permissions.enabled),
+            allowed_actions,
+            github_owned_allowed: selected.github_owned_allowed,
+            verified_allowed: selected.verified_allowed,
+            patterns_allowed: selected.patterns_allowed,
+            default_workflow_permissions,
+            can_approve_pull_requests: workflow.can_approve_pull_request_reviews,
+            variables: Some(variables),
+        })
+    }
+
+    /// Get the names of the secrets, github never returns their values so they have no source.
+    async fn secrets(&self, owner: &str, repo: &str) -> Result<BTreeMap<String, Secret>> {
+        let secrets = self.client.secrets(owner, repo).await?;
+        Ok(secrets
+            .into_iter()
+            .map(|secret| (secret.name, Secret::default()))
+            .collect())
+    }
+
+    /// Get every environment with its branch policies and variables.
+    async fn environments(&self, owner: &str, repo: &str) -> Result<Vec<Environment>> {
+        let mut environments = Vec::new();
+        for environment in self.client.environments(owner, repo).await? {
+            environments.push(self.environment(owner, repo, environment).await?);
+        }
+        Ok(environments)
+    }
+
+    /// Build an environment from its protection rules, branch policies and variables.
+    ///
+    /// Github leaves out rules which are turned off, these are filled in with
+    /// their off values so they can be compared with settings.
+    async fn environment(
+        &self,
+        owner: &str,
+        repo: &str,
+        environment: github::Environment,
+    ) -> Result<Environment> {
+        let mut wait_timer = 0;
+        let mut reviewers = Vec::new();
+        let mut reviewer_teams = Vec::new();
+        for rule in environment.protection_rules {
+            match rule {
+                ProtectionRule::WaitTimer { wait_timer: timer } => wait_timer = timer,
+                ProtectionRule::RequiredReviewers {
+                    reviewers: required,
+                } => {
+                    for reviewer in required {
+                        match reviewer {
+                            Reviewer::User(user) => reviewers.extend(user.login),
+                            Reviewer::Team(team) => reviewer_teams.extend(team.slug),
+                        }
+                    }
+                }
+                ProtectionRule::Other => {}
+            }
+        }
+        let deployment_branches = match environment.deployment_branch_policy {
+            None => DeploymentBranches::All,
+            Some(policy) if policy.custom_branch_policies => DeploymentBranches::Custom,
+            Some(_) => DeploymentBranches::Protected,
+        };
+        let branch_patterns = match deployment_branches {
+            DeploymentBranches::Custom => self
+                .client
+                .deployment_branch_policies(owner, repo, &environment.name)
+                .await?
+                .into_iter()
+                .filter(|policy| policy.kind.as_deref() != Some("tag"))
+                .map(|policy| policy.name)
+                .collect(),
+            _ => Vec::new(),
+        };
+        let variables = self
+            .client
+            .environment_variables(owner, repo, &environment.name)
+            .await?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value))
+            .collect();
+        Ok(Environment {
+            name: environment.name,
+            wait_timer: Some(wait_timer),
+            reviewers: Some(reviewers),
+            reviewer_teams: Some(reviewer_teams),
+            deployment_branches: Some(deployment_branches),
+            branch_patterns: Some(branch_patterns),
+            variables: Some(variables),
+        })
+    }
 }
 
 #[async_trait]
@@ -28,69 +334,170 @@ impl<C> Retrieve for RetrieveSettings<'_, C>
 where
     C: GithubClient + Send + Sync,
 {
-    async fn retrieve(&self, owner: &str, repo: &str) -> Result<GramSettings> {
+    async fn retrieve(&self, owner: &str, repo: &str, sections: Sections) -> Result<GramSettings> {
         let repository = self.client.repository(owner, repo).await?;
-        let protected_branches = self.client.protected_branches(owner, repo).await?;
-        let protected = protected_branches
-            .into_iter()
-            .map(|branch| ProtectedBranch { name: branch.name })
-            .collect::<Vec<ProtectedBranch>>();
-        let protected = if protected.is_empty() {
-            None
-        } else {
-            Some(protected)
+        let topics = match sections.topics {
+            true => Some(self.client.topics(owner, repo).await?),
+            false => None,
+        };
+        let protected = match sections.protected {
+            true => self.protected(owner, repo).await?,
+            false => None,
+        };
+        let labels = match sections.labels {
+            true => Some(self.labels(owner, repo).await?.into()),
+            false => None,
+        };
+        let collaborators = match sections.collaborators {
+            true => Some(self.collaborators(owner, repo).await?),
+            false => None,
+        };
+        let teams = match sections.teams {
+            true => Some(self.teams(owner, repo).await?),
+            false => None,
+        };
+        let webhooks = match sections.webhooks {
+            true => Some(self.webhooks(owner, repo).await?),
+            false => None,
+        };
+        let environments = match sections.environments {
+            true => Some(self.environments(owner, repo).await?),
+            false => None,
+        };
+        let actions = match sections.actions {
+            true => Some(self.actions(owner, repo).await?),
+            false => None,
+        };
+        let secrets = match sections.secrets {
+            true => Some(self.secrets(owner, repo).await?),
+            false => None,
+        };
+        let visibility = match repository.visibility.as_deref().map(str::parse) {
+            Some(visibility) => Some(visibility?),
+            None => repository.private.map(|private| match private {
+                true => Visibility::Private,
+                false => Visibility::Public,
+            }),
         };
         Ok(GramSettings {
+            extends: None,
             description: repository.description,
+            homepage: repository.homepage,
+            topics,
             options: Some(Options {
-                allow_squash_merge: Some(repository.allow_squash_merge),
-                allow_merge_commit: Some(repository.allow_merge_commit),
-                allow_rebase_merge: Some(repository.allow_rebase_merge),
-                delete_branch_on_merge: Some(repository.delete_branch_on_merge),
+                visibility,
+                default_branch: repository.default_branch,
+                has_issues: repository.has_issues,
+                has_projects: repository.has_projects,
+                has_wiki: repository.has_wiki,
+                has_discussions: repository.has_discussions,
+                is_template: repository.is_template,
+                allow_forking: repository.allow_forking,
+                web_commit_signoff_required: repository.web_commit_signoff_required,
+                allow_squash_merge: repository.allow_squash_merge,
+                allow_merge_commit: repository.allow_merge_commit,
+                allow_rebase_merge: repository.allow_rebase_merge,
+                allow_auto_merge: repository.allow_auto_merge,
+                allow_update_branch: repository.allow_update_branch,
+                delete_branch_on_merge: repository.delete_branch_on_merge,
+                squash_merge_commit_title: repository.squash_merge_commit_title,
+                squash_merge_commit_message: repository.squash_merge_commit_message,
+                merge_commit_title: repository.merge_commit_title,
+                merge_commit_message: repository.merge_commit_message,
             }),
             protected,
+            labels,
+            collaborators,
+            teams,
+            webhooks,
+            environments,
+            secrets,
+            actions,
         })
     }
 }
 
-#[cfg(test)]
-mod test {
-    use super::{Retrieve, RetrieveSettings};
-    use crate::{
-        commands::settings::GramSettings,
-        github::{Branch, GithubClient, Repository},
-    };
-    use anyhow::{anyhow, Result};
-    use async_trait::async_trait;
+/// Invitations name the pull and push permissions `read` and `write`.
+fn invitation_permission(permissions: &str) -> Result<Permission> {
+    match permissions {
+        "read" => Ok(Permission::Pull),
+        "write" => Ok(Permission::Push),
+        permissions => permissions.parse(),
+    }
+}
 
-    struct FakeClient {
-        protected_branches: Option<Vec<Branch>>,
-        repository: Option<Repository>,
+/// Github lists every permission a collaborator has, get the highest one.
+fn highest_permission(permissions: Permissions) -> Permission {
+    let Permissions {
+        admin,
+        maintain,
+        push,
+        triage,
+        ..
+    } = permissions;
+    match (admin, maintain, push, triage) {
+        (true, _, _, _) => Permission::Admin,
+        (_, true, _, _) => Permission::Maintain,
+        (_, _, true, _) => Permission::Push,
+        (_, _, _, true) => Permission::Triage,
+        _ => Permission::Pull,
     }
+}
 
-    #[async_trait]
-    impl GithubClient for FakeClient {
-        async fn repository(&self, _: &str, _: &str) -> Result<Repository> {
-            match &self.repository {
-                Some(repo) => Ok(repo.clone()),
-                None => Err(anyhow!("error")),
-            }
-        }
-        async fn protected_branches(&self, _: &str, _: &str) -> Result<Vec<Branch>> {
-            match &self.protected_branches {
-                Some(branches) => Ok(branches.clone()),
-                None => Err(anyhow!("error")),
-            }
-        }
+/// Build a protected branch from its protection on github.
+///
+/// Github leaves out any part of the protection which is turned off, these
+/// are filled in with their off values so they can be compared with settings.
+fn protected_branch(name: String, protection: BranchProtection) -> ProtectedBranch {
+    let BranchProtection {
+        required_status_checks,
+        enforce_admins,
+        required_pull_request_reviews,
+        required_linear_history,
+        allow_force_pushes,
+        allow_deletions,
+        ..
+    } = protection;
+    let status_checks = required_status_checks.unwrap_or_default();
+    let reviews = required_pull_request_reviews.unwrap_or_default();
+    ProtectedBranch {
+        name,
+        required_approving_review_count: Some(reviews.required_approving_review_count),
+        dismiss_stale_reviews: Some(reviews.dismiss_stale_reviews),
+        require_code_owner_reviews: Some(reviews.require_code_owner_reviews),
+        required_status_checks: Some(status_checks.contexts),
+        strict_status_checks: Some(status_checks.strict),
+        enforce_admins: Some(enforce_admins.unwrap_or_default().enabled),
+        required_linear_history: Some(required_linear_history.unwrap_or_default().enabled),
+        allow_force_pushes: Some(allow_force_pushes.unwrap_or_default().enabled),
+        allow_deletions: Some(allow_deletions.unwrap_or_default().enabled),
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Retrieve, RetrieveSettings, Sections};
+    use crate::{
+        commands::settings::{
+            Actions, AllowedActions, ContentType, DeploymentBranches, Environment, GramSettings,
+            Permission, Secret, Visibility, Webhook, WorkflowPermission,
+        },
+        github::{
+            self, fake::FakeGithub, Account, Branch, BranchPolicy, BranchProtection, Collaborator,
+            DeploymentBranchPolicy, Enabled, Hook, HookConfig, Invitation, Label, Permissions,
+            ProtectionRule, Repository, RequiredPullRequestReviews, Reviewer, Team, Variable,
+        },
+    };
+    use anyhow::Result;
 
     fn default_repository() -> Repository {
         Repository {
             description: Some("description".to_owned()),
-            allow_squash_merge: true,
-            allow_merge_commit: false,
-            allow_rebase_merge: true,
-            delete_branch_on_merge: false,
+            allow_squash_merge: Some(true),
+            allow_merge_commit: Some(false),
+            allow_rebase_merge: Some(true),
+            delete_branch_on_merge: Some(false),
+            ..Repository::default()
         }
     }
 
@@ -101,12 +508,14 @@ mod test {
     }
 
     async fn actual_settings(repo: Repository, branches: Vec<Branch>) -> Result<GramSettings> {
-        let client = FakeClient {
+        let client = FakeGithub {
             repository: Some(repo),
             protected_branches: Some(branches),
+            branch_protection: Some(BranchProtection::default()),
+            ..FakeGithub::default()
         };
         let retrieve = RetrieveSettings { client: &client };
-        retrieve.retrieve("", "").await
+        retrieve.retrieve("", "", Sections::all()).await
     }
 
     #[tokio::test]
@@ -157,14 +566,16 @@ mod test {
     #[tokio::test]
     async fn should_return_error_if_protected_branch_fetch_fails() {
         // arrange
-        let client = FakeClient {
+        let client = FakeGithub {
             repository: Some(Repository::default()),
             protected_branches: None,
+            branch_protection: Some(BranchProtection::default()),
+            ..FakeGithub::default()
         };
         let retriever = RetrieveSettings { client: &client };
 
         // act
-        let opt_settings = retriever.retrieve("", "").await;
+        let opt_settings = retriever.retrieve("", "", Sections::all()).await;
 
         // assert
         assert!(opt_settings.is_err());
@@ -173,14 +584,16 @@ mod test {
     #[tokio::test]
     async fn should_return_settings() {
         // arrange
-        let client = FakeClient {
+        let client = FakeGithub {
             repository: Some(Repository::default()),
             protected_branches: Some(vec![Branch::default()]),
+            branch_protection: Some(BranchProtection::default()),
+            ..FakeGithub::default()
         };
         let retriever = RetrieveSettings { client: &client };
 
         // act
-        let opt_settings = retriever.retrieve("", "").await;
+        let opt_settings = retriever.retrieve("", "", Sections::all()).await;
 
         // assert
         assert!(opt_settings.is_ok());
@@ -189,16 +602,534 @@ mod test {
     #[tokio::test]
     async fn should_return_error_if_repo_fetch_fails() {
         // arrange
-        let client = FakeClient {
+        let client = FakeGithub {
             repository: None,
             protected_branches: Some(vec![Branch::default()]),
+            branch_protection: Some(BranchProtection::default()),
+            ..FakeGithub::default()
         };
         let retriever = RetrieveSettings { client: &client };
 
         // act
-        let opt_settings = retriever.retrieve("", "").await;
+        let opt_settings = retriever.retrieve("", "", Sections::all()).await;
 
         // assert
         assert!(opt_settings.is_err());
     }
+
+    #[tokio::test]
+    async fn should_return_error_if_branch_protection_fetch_fails() {
+        // arrange
+        let client = FakeGithub {
+            repository: Some(Repository::default()),
+            protected_branches: Some(vec![Branch::default()]),
+            branch_protection: None,
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let opt_settings = retriever.retrieve("", "", Sections::all()).await;
+
+        // assert
+        assert!(opt_settings.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_return_protection_rules_filling_in_missing_parts_as_off() {
+        // arrange
+        let client = FakeGithub {
+            repository: Some(Repository::default()),
+            protected_branches: Some(default_protected_branches()),
+            branch_protection: Some(BranchProtection {
+                required_pull_request_reviews: Some(RequiredPullRequestReviews {
+                    dismiss_stale_reviews: true,
+                    require_code_owner_reviews: false,
+                    required_approving_review_count: 2,
+                    ..RequiredPullRequestReviews::default()
+                }),
+                enforce_admins: Some(Enabled { enabled: true }),
+                ..BranchProtection::default()
+            }),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let branch = &settings.protected.unwrap()[0];
+        assert_eq!(Some(2), branch.required_approving_review_count);
+        assert_eq!(Some(true), branch.dismiss_stale_reviews);
+        assert_eq!(Some(false), branch.require_code_owner_reviews);
+        assert_eq!(Some(Vec::new()), branch.required_status_checks);
+        assert_eq!(Some(false), branch.strict_status_checks);
+        assert_eq!(Some(true), branch.enforce_admins);
+        assert_eq!(Some(false), branch.required_linear_history);
+        assert_eq!(Some(false), branch.allow_force_pushes);
+        assert_eq!(Some(false), branch.allow_deletions);
+    }
+
+    #[tokio::test]
+    async fn should_return_labels_with_lowercase_colors() {
+        // arrange
+        let client = FakeGithub {
+            labels: Some(vec![Label {
+                name: "bug".to_owned(),
+                color: "D73A4A".to_owned(),
+                description: None,
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let labels = settings.labels.unwrap();
+        assert_eq!(None, labels.prune);
+        assert_eq!("bug", labels.items[0].name);
+        assert_eq!(Some("d73a4a".to_owned()), labels.items[0].color);
+        assert_eq!(None, labels.items[0].description);
+    }
+
+    #[tokio::test]
+    async fn should_return_error_if_labels_fetch_fails() {
+        // arrange
+        let client = FakeGithub {
+            labels: None,
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let opt_settings = retriever.retrieve("", "", Sections::all()).await;
+
+        // assert
+        assert!(opt_settings.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_return_the_highest_permission_of_each_collaborator() {
+        // arrange
+        let client = FakeGithub {
+            collaborators: Some(vec![Collaborator {
+                login: "alice".to_owned(),
+                permissions: Permissions {
+                    admin: false,
+                    maintain: true,
+                    push: true,
+                    triage: true,
+                    pull: true,
+                },
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let collaborators = settings.collaborators.unwrap();
+        assert_eq!("alice", collaborators[0].username);
+        assert_eq!(Permission::Maintain, collaborators[0].permission);
+    }
+
+    #[tokio::test]
+    async fn should_return_invited_users_as_collaborators() {
+        // arrange
+        let invitee = |login: &str| Account {
+            login: Some(login.to_owned()),
+            ..Account::default()
+        };
+        let client = FakeGithub {
+            collaborators: Some(vec![Collaborator {
+                login: "alice".to_owned(),
+                permissions: Permissions::default(),
+            }]),
+            invitations: Some(vec![
+                Invitation {
+                    id: 1,
+                    invitee: Some(invitee("bob")),
+                    permissions: "write".to_owned(),
+                },
+                Invitation {
+                    id: 2,
+                    invitee: Some(invitee("Alice")),
+                    permissions: "admin".to_owned(),
+                },
+                Invitation {
+                    id: 3,
+                    invitee: None,
+                    permissions: "read".to_owned(),
+                },
+            ]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let collaborators = settings
+            .collaborators
+            .unwrap()
+            .into_iter()
+            .map(|collaborator| (collaborator.username, collaborator.permission))
+            .collect::<Vec<(String, Permission)>>();
+        assert_eq!(
+            vec![
+                ("alice".to_owned(), Permission::Pull),
+                ("bob".to_owned(), Permission::Push),
+            ],
+            collaborators
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_error_if_a_team_has_an_unknown_permission() {
+        // arrange
+        let client = FakeGithub {
+            teams: Some(vec![Team {
+                slug: "core".to_owned(),
+                permission: "custom".to_owned(),
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let opt_settings = retriever.retrieve("", "", Sections::all()).await;
+
+        // assert
+        assert_eq!(
+            "Team [core] has the custom role [custom], which cannot be configured",
+            opt_settings.err().unwrap().to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn should_ignore_custom_team_roles_unless_teams_are_configured() {
+        // arrange
+        let client = FakeGithub {
+            teams: Some(vec![Team {
+                slug: "core".to_owned(),
+                permission: "custom".to_owned(),
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+        let configured = GramSettings {
+            topics: Some(vec!["rust".to_owned()]),
+            ..GramSettings::default()
+        };
+
+        // act
+        let opt_settings = retriever
+            .retrieve("", "", Sections::configured(&configured))
+            .await;
+
+        // assert
+        assert_eq!(None, opt_settings.unwrap().teams);
+    }
+
+    #[tokio::test]
+    async fn should_return_webhooks_with_a_url_without_their_secret() {
+        // arrange
+        let client = FakeGithub {
+            hooks: Some(vec![
+                Hook {
+                    id: 1,
+                    active: true,
+                    events: vec!["push".to_owned()],
+                    config: HookConfig {
+                        url: Some("https://ci.example.com/hook".to_owned()),
+                        content_type: Some("json".to_owned()),
+                        secret: Some("********".to_owned()),
+                    },
+                },
+                Hook {
+                    id: 2,
+                    ..Hook::default()
+                },
+            ]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let expected = Webhook {
+            url: "https://ci.example.com/hook".to_owned(),
+            content_type: Some(ContentType::Json),
+            events: Some(vec!["push".to_owned()]),
+            active: Some(true),
+            secret_env: None,
+            id: Some(1),
+            has_secret: true,
+        };
+        assert_eq!(Some(vec![expected]), settings.webhooks);
+    }
+
+    #[tokio::test]
+    async fn should_return_actions_permissions_selected_actions_and_variables() {
+        // arrange
+        let client = FakeGithub {
+            actions_permissions: Some(github::ActionsPermissions {
+                enabled: true,
+                allowed_actions: Some("selected".to_owned()),
+            }),
+            selected_actions: Some(github::SelectedActions {
+                github_owned_allowed: Some(true),
+                verified_allowed: Some(false),
+                patterns_allowed: Some(vec!["docker/*".to_owned()]),
+            }),
+            workflow_permissions: Some(github::WorkflowPermissions {
+                default_workflow_permissions: Some("read".to_owned()),
+                can_approve_pull_request_reviews: Some(false),
+            }),
+            actions_variables: Some(vec![Variable {
+                name: "REGION".to_owned(),
+                value: "eu-west-1".to_owned(),
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let expected = Actions {
+            enabled: Some(true),
+            allowed_actions: Some(AllowedActions::Selected),
+            github_owned_allowed: Some(true),
+            verified_allowed: Some(false),
+            patterns_allowed: Some(vec!["docker/*".to_owned()]),
+            default_workflow_permissions: Some(WorkflowPermission::Read),
+            can_approve_pull_requests: Some(false),
+            variables: Some(
+                vec![("REGION".to_owned(), "eu-west-1".to_owned())]
+                    .into_iter()
+                    .collect(),
+            ),
+        };
+        assert_eq!(Some(expected), settings.actions);
+    }
+
+    #[tokio::test]
+    async fn should_not_get_selected_actions_unless_they_are_allowed() {
+        // arrange
+        let client = FakeGithub {
+            actions_permissions: Some(github::ActionsPermissions {
+                enabled: false,
+                allowed_actions: None,
+            }),
+            // Github refuses to return selected actions in this case.
+            selected_actions: None,
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let actions = settings.actions.unwrap();
+        assert_eq!(Some(false), actions.enabled);
+        assert_eq!(None, actions.allowed_actions);
+        assert_eq!(None, actions.patterns_allowed);
+    }
+
+    #[tokio::test]
+    async fn should_only_call_github_for_the_sections_asked_for() {
+        // arrange
+        let client = FakeGithub {
+            topics: None,
+            protected_branches: None,
+            labels: None,
+            collaborators: None,
+            teams: None,
+            hooks: None,
+            environments: None,
+            secrets: None,
+            actions_permissions: None,
+            actions_variables: None,
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+        let configured = GramSettings {
+            description: Some("description".to_owned()),
+            ..GramSettings::default()
+        };
+
+        // act
+        let settings = retriever
+            .retrieve("", "", Sections::configured(&configured))
+            .await;
+
+        // assert
+        let settings = settings.unwrap();
+        assert!(settings.options.is_some());
+        assert_eq!(None, settings.labels);
+        assert_eq!(None, settings.webhooks);
+        assert_eq!(None, settings.environments);
+        assert_eq!(None, settings.secrets);
+        assert_eq!(None, settings.actions);
+    }
+
+    #[test]
+    fn sections_should_hold_the_sections_of_each_key() {
+        // act
+        let sections = Sections::holding(vec![
+            "description",
+            "labels.bug.color",
+            "webhooks.https://ci.example.com/hook.active",
+            "environments.production.wait-timer",
+            "secrets.DEPLOY_TOKEN",
+            "actions.variables.REGION",
+        ]);
+
+        // assert
+        assert_eq!(
+            Sections {
+                labels: true,
+                webhooks: true,
+                environments: true,
+                secrets: true,
+                actions: true,
+                ..Sections::default()
+            },
+            sections
+        );
+    }
+
+    #[tokio::test]
+    async fn should_return_secret_names_without_a_source() {
+        // arrange
+        let client = FakeGithub {
+            secrets: Some(vec![github::Secret {
+                name: "DEPLOY_TOKEN".to_owned(),
+                updated_at: "2020-01-10T14:59:22Z".to_owned(),
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let secrets = settings.secrets.unwrap();
+        assert_eq!(vec!["DEPLOY_TOKEN"], secrets.keys().collect::<Vec<_>>());
+        assert_eq!(Secret::default(), secrets["DEPLOY_TOKEN"]);
+    }
+
+    #[tokio::test]
+    async fn should_return_topics_and_leave_out_options_github_does_not_return() {
+        // arrange
+        let client = FakeGithub {
+            repository: Some(Repository {
+                private: Some(true),
+                default_branch: Some("main".to_owned()),
+                has_issues: Some(true),
+                ..Repository::default()
+            }),
+            topics: Some(vec!["cli".to_owned()]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        assert_eq!(Some(vec!["cli".to_owned()]), settings.topics);
+        let options = settings.options.unwrap();
+        assert_eq!(Some(Visibility::Private), options.visibility);
+        assert_eq!(Some("main".to_owned()), options.default_branch);
+        assert_eq!(Some(true), options.has_issues);
+        assert_eq!(None, options.has_wiki);
+        assert_eq!(None, options.merge_commit_title);
+    }
+
+    #[tokio::test]
+    async fn should_return_environments_filling_in_missing_rules_as_off() {
+        // arrange
+        let client = FakeGithub {
+            environments: Some(vec![
+                github::Environment {
+                    name: "production".to_owned(),
+                    protection_rules: vec![
+                        ProtectionRule::RequiredReviewers {
+                            reviewers: vec![Reviewer::Team(Account {
+                                slug: Some("core".to_owned()),
+                                ..Account::default()
+                            })],
+                        },
+                        ProtectionRule::Other,
+                    ],
+                    deployment_branch_policy: Some(DeploymentBranchPolicy {
+                        protected_branches: false,
+                        custom_branch_policies: true,
+                    }),
+                },
+                github::Environment {
+                    name: "staging".to_owned(),
+                    ..github::Environment::default()
+                },
+            ]),
+            deployment_branch_policies: Some(vec![
+                BranchPolicy {
+                    id: 1,
+                    name: "release/*".to_owned(),
+                    kind: Some("branch".to_owned()),
+                },
+                BranchPolicy {
+                    id: 2,
+                    name: "v*".to_owned(),
+                    kind: Some("tag".to_owned()),
+                },
+            ]),
+            environment_variables: Some(vec![Variable {
+                name: "REGION".to_owned(),
+                value: "eu-west-1".to_owned(),
+            }]),
+            ..FakeGithub::default()
+        };
+        let retriever = RetrieveSettings { client: &client };
+
+        // act
+        let settings = retriever.retrieve("", "", Sections::all()).await.unwrap();
+
+        // assert
+        let environments = settings.environments.unwrap();
+        assert_eq!(
+            Environment {
+                name: "production".to_owned(),
+                wait_timer: Some(0),
+                reviewers: Some(Vec::new()),
+                reviewer_teams: Some(vec!["core".to_owned()]),
+                deployment_branches: Some(DeploymentBranches::Custom),
+                branch_patterns: Some(vec!["release/*".to_owned()]),
+                variables: Some(
+                    vec![("REGION".to_owned(), "eu-west-1".to_owned())]
+                        .into_iter()
+                        .collect()
+                ),
+            },
+            environments[0]
+        );
+        assert_eq!(
+            Some(DeploymentBranches::All),
+            environments[1].deployment_branches
+        );
+        assert_eq!(Some(Vec::new()), environments[1].branch_patterns);
+    }
 }
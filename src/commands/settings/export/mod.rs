@@ -0,0 +1,152 @@
+use super::{
+    diff::retrieve::{Retrieve, Sections},
+    format::FileFormat,
+    GramSettings,
+};
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Export the actual settings of a repository as a settings file.
+///
+/// The exported file can be used as-is with the other settings commands,
+/// making it a starting point when adding gram to a repository. It is
+/// written as toml unless `--file-format` is given or `--out` has a yaml
+/// or json extension.
+#[derive(Debug, StructOpt)]
+pub struct Export {
+    /// The owner of the repository.
+    #[structopt(short, long)]
+    pub owner: String,
+
+    /// The name of the repository.
+    #[structopt(short, long)]
+    pub repo: String,
+
+    /// Path to write the settings to. Printed to stdout if not given.
+    #[structopt(long)]
+    pub out: Option<PathBuf>,
+}
+
+impl Export {
+    pub async fn handle<R>(self, retriever: R, format: Option<FileFormat>) -> Result<()>
+    where
+        R: Retrieve,
+    {
+        let settings = retriever
+            .retrieve(&self.owner, &self.repo, Sections::all())
+            .await?;
+        let format = match (format, &self.out) {
+            (Some(format), _) => format,
+            (None, Some(out)) => FileFormat::from_path(out),
+            (None, None) => FileFormat::Toml,
+        };
+        let settings_str = Export::to_string(&settings, format)?;
+        match &self.out {
+            Some(out) => fs::write(out, settings_str)?,
+            None => print!("{}", settings_str),
+        }
+        Ok(())
+    }
+
+    /// Serialize settings in the same layout they are read from.
+    pub(crate) fn to_string(settings: &GramSettings, format: FileFormat) -> Result<String> {
+        format.serialize(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Export, FileFormat};
+    use crate::commands::settings::{FileReader, GramSettings, Label, Options, ProtectedBranch};
+    use std::path::{Path, PathBuf};
+
+    struct StringReader {
+        contents: String,
+    }
+
+    impl FileReader for StringReader {
+        fn read_to_string<P: AsRef<Path>>(&self, _: P) -> Result<String, std::io::Error> {
+            Ok(self.contents.clone())
+        }
+    }
+
+    fn settings() -> GramSettings {
+        GramSettings {
+            description: Some("description".to_owned()),
+            options: Some(Options {
+                allow_squash_merge: Some(false),
+                allow_merge_commit: Some(true),
+                allow_rebase_merge: Some(true),
+                delete_branch_on_merge: Some(false),
+                ..Options::default()
+            }),
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                required_approving_review_count: Some(1),
+                ..ProtectedBranch::default()
+            }]),
+            labels: Some(
+                vec![Label {
+                    name: "bug".to_owned(),
+                    color: Some("d73a4a".to_owned()),
+                    description: None,
+                }]
+                .into(),
+            ),
+            ..GramSettings::default()
+        }
+    }
+
+    #[test]
+    fn to_string_should_use_the_settings_file_layout() {
+        // act
+        let settings_str = Export::to_string(&settings(), FileFormat::Toml).unwrap();
+
+        // assert
+        assert_eq!(
+            r#"description = "description"
+
+[options]
+allow-squash-merge = false
+allow-merge-commit = true
+allow-rebase-merge = true
+delete-branch-on-merge = false
+
+[[protected]]
+name = "master"
+required-approving-review-count = 1
+
+[[labels]]
+name = "bug"
+color = "d73a4a"
+"#,
+            settings_str
+        );
+    }
+
+    #[test]
+    fn to_string_should_round_trip_through_read_settings() {
+        // arrange
+        let settings = settings();
+        let reader = StringReader {
+            contents: Export::to_string(&settings, FileFormat::Toml).unwrap(),
+        };
+
+        // act
+        let (read, _) = reader.read_settings(&PathBuf::new()).unwrap();
+
+        // assert
+        assert_eq!(settings, read);
+    }
+
+    #[test]
+    fn to_string_should_leave_out_settings_which_are_not_set() {
+        // act
+        let settings_str = Export::to_string(&GramSettings::default(), FileFormat::Toml).unwrap();
+
+        // assert
+        assert_eq!("", settings_str);
+    }
+}
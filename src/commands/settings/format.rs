@@ -0,0 +1,197 @@
+//! The file formats settings can be written in.
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The format of a settings file, all of which hold the same settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FileFormat {
+    #[default]
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl FileFormat {
+    /// The format of a file going by its extension, toml if it is not known.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => FileFormat::Yaml,
+            Some("json") => FileFormat::Json,
+            _ => FileFormat::Toml,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, ParseError> {
+        match self {
+            FileFormat::Toml => toml::from_str(contents).map_err(|e| {
+                let position = e.line_col().map(|(line, column)| (line + 1, column + 1));
+                ParseError::new(e.to_string(), position)
+            }),
+            FileFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| {
+                let position = e.location().map(|at| (at.line(), at.column()));
+                ParseError::new(e.to_string(), position)
+            }),
+            FileFormat::Json => serde_json::from_str(contents).map_err(|e| {
+                let position = match e.line() {
+                    0 => None,
+                    line => Some((line, e.column())),
+                };
+                ParseError::new(e.to_string(), position)
+            }),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String> {
+        match self {
+            FileFormat::Toml => Ok(toml::to_string(value)?),
+            FileFormat::Yaml => Ok(serde_yaml::to_string(value)?),
+            FileFormat::Json => Ok(serde_json::to_string_pretty(value)? + "\n"),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = match self {
+            FileFormat::Toml => "toml",
+            FileFormat::Yaml => "yaml",
+            FileFormat::Json => "json",
+        };
+        write!(f, "{}", format)
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "toml" => Ok(FileFormat::Toml),
+            "yaml" | "yml" => Ok(FileFormat::Yaml),
+            "json" => Ok(FileFormat::Json),
+            _ => Err(anyhow!(
+                "Unknown file format [{}], expected one of toml, yaml or json",
+                format
+            )),
+        }
+    }
+}
+
+/// A settings file which could not be parsed, and where.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// The line and column of the problem, if the parser knows it.
+    pub position: Option<(usize, usize)>,
+}
+
+impl ParseError {
+    fn new(message: String, position: Option<(usize, usize)>) -> Self {
+        // Each parser puts the position at the end of its message.
+        let message = match message.rfind(" at line ") {
+            Some(at) if position.is_some() => message[..at].to_owned(),
+            _ => message,
+        };
+        Self { message, position }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => {
+                write!(f, "{} at line {} column {}", self.message, line, column)
+            }
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod test {
+    use super::{FileFormat, ParseError};
+    use crate::commands::settings::{GramSettings, Label, Labels, Options};
+    use std::path::Path;
+
+    #[test]
+    fn from_path_should_go_by_extension_and_default_to_toml() {
+        // assert
+        assert_eq!(FileFormat::Yaml, FileFormat::from_path(Path::new("a.yaml")));
+        assert_eq!(FileFormat::Yaml, FileFormat::from_path(Path::new("a.yml")));
+        assert_eq!(FileFormat::Json, FileFormat::from_path(Path::new("a.json")));
+        assert_eq!(FileFormat::Toml, FileFormat::from_path(Path::new("a.toml")));
+        assert_eq!(
+            FileFormat::Toml,
+            FileFormat::from_path(Path::new("settings"))
+        );
+    }
+
+    #[test]
+    fn parse_should_read_the_same_settings_from_every_format() {
+        // arrange
+        let expected = GramSettings {
+            description: Some("gram".to_owned()),
+            options: Some(Options {
+                allow_squash_merge: Some(false),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        };
+
+        // act
+        let toml = FileFormat::Toml.parse::<GramSettings>(
+            "description = \"gram\"\n[options]\nallow-squash-merge = false\n",
+        );
+        let yaml = FileFormat::Yaml
+            .parse::<GramSettings>("description: gram\noptions:\n  allow-squash-merge: false\n");
+        let json = FileFormat::Json.parse::<GramSettings>(
+            r#"{"description": "gram", "options": {"allow-squash-merge": false}}"#,
+        );
+
+        // assert
+        assert_eq!(expected, toml.unwrap());
+        assert_eq!(expected, yaml.unwrap());
+        assert_eq!(expected, json.unwrap());
+    }
+
+    #[test]
+    fn parse_should_give_the_position_of_an_error() {
+        // act
+        let toml = FileFormat::Toml.parse::<GramSettings>("description = 1\n");
+        let yaml = FileFormat::Yaml.parse::<GramSettings>("options:\n  allow-squash-merge: 1\n");
+        let json = FileFormat::Json.parse::<GramSettings>("{\n  \"description\": true\n}");
+
+        // assert
+        let position = |error: Result<GramSettings, ParseError>| error.err().unwrap().position;
+        assert_eq!(Some((1, 15)), position(toml));
+        assert_eq!(Some((2, 23)), position(yaml));
+        assert_eq!(Some((2, 21)), position(json));
+    }
+
+    #[test]
+    fn serialize_should_round_trip_through_parse() {
+        // arrange
+        let settings = GramSettings {
+            description: Some("gram".to_owned()),
+            labels: Some(Labels {
+                prune: Some(true),
+                items: vec![Label {
+                    name: "bug".to_owned(),
+                    ..Label::default()
+                }],
+            }),
+            ..GramSettings::default()
+        };
+
+        // assert
+        for format in &[FileFormat::Toml, FileFormat::Yaml, FileFormat::Json] {
+            let settings_str = format.serialize(&settings).unwrap();
+            assert_eq!(settings, format.parse(&settings_str).unwrap(), "{}", format);
+        }
+    }
+}
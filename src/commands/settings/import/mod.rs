@@ -0,0 +1,92 @@
+pub mod probot;
+use super::{export::Export, format::FileFormat, FileReader};
+use anyhow::{anyhow, Result};
+use probot::{Translation, PROBOT_SETTINGS_FILE};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+/// Import the settings file of another tool as a gram settings file.
+///
+/// Settings gram does not manage are listed on stderr rather than dropped.
+/// The settings file is written as toml unless `--file-format` is given or
+/// `--out` has a yaml or json extension.
+#[derive(Debug, StructOpt)]
+pub struct Import {
+    /// The tool the settings file is from, only probot is supported.
+    #[structopt(long)]
+    pub from: Source,
+
+    /// Path to the settings file to import, defaults to .github/settings.yml
+    /// for probot.
+    #[structopt(name = "file", short, long)]
+    pub settings_file: Option<PathBuf>,
+
+    /// Path to write the gram settings to. Printed to stdout if not given.
+    #[structopt(long)]
+    pub out: Option<PathBuf>,
+}
+
+/// Tools settings can be imported from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    /// https://github.com/probot/settings
+    Probot,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Probot => write!(f, "probot"),
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = anyhow::Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        match source {
+            "probot" => Ok(Source::Probot),
+            _ => Err(anyhow!("Unknown source [{}], expected probot", source)),
+        }
+    }
+}
+
+impl Import {
+    pub fn handle<F: FileReader>(self, reader: F) -> Result<()> {
+        let translation = self.translate(&reader)?;
+        for skipped in &translation.skipped {
+            eprintln!("Not imported {}", skipped);
+        }
+        let format = match (reader.file_format(), &self.out) {
+            (Some(format), _) => format,
+            (None, Some(out)) => FileFormat::from_path(out),
+            (None, None) => FileFormat::Toml,
+        };
+        let settings_str = Export::to_string(&translation.settings, format)?;
+        match &self.out {
+            Some(out) => fs::write(out, settings_str)?,
+            None => print!("{}", settings_str),
+        }
+        Ok(())
+    }
+
+    fn translate<F: FileReader>(&self, reader: &F) -> Result<Translation> {
+        match self.from {
+            Source::Probot => {
+                let file = self
+                    .settings_file
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(PROBOT_SETTINGS_FILE));
+                let probot_str = reader.read_to_string(&file)?;
+                let probot = serde_yaml::from_str(&probot_str).map_err(|e| {
+                    anyhow!("Failed to read probot settings {}: {}", file.display(), e)
+                })?;
+                Ok(Translation::from_probot(&probot))
+            }
+        }
+    }
+}
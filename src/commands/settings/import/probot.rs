@@ -0,0 +1,509 @@
+//! Translating probot/settings `.github/settings.yml` files.
+//!
+//! See https://github.com/probot/settings for the format.
+use super::super::{
+    normalize_color, Collaborator, GramSettings, Label, Options, Permission, ProtectedBranch, Team,
+    Visibility,
+};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+/// Where probot keeps its settings.
+pub static PROBOT_SETTINGS_FILE: &str = ".github/settings.yml";
+
+/// Settings translated from probot, along with everything which could not be.
+#[derive(Debug, Default, PartialEq)]
+pub struct Translation {
+    pub settings: GramSettings,
+    /// A message for each probot setting which was not translated.
+    pub skipped: Vec<String>,
+}
+
+impl Translation {
+    pub fn from_probot(probot: &Value) -> Self {
+        let mut translation = Translation::default();
+        let probot = match probot.as_object() {
+            Some(probot) => probot,
+            None => {
+                translation.skip("", "expected a mapping of settings");
+                return translation;
+            }
+        };
+        for (key, value) in probot {
+            match key.as_str() {
+                "repository" => translation.repository(value),
+                "labels" => translation.labels(value),
+                "collaborators" => translation.collaborators(value),
+                "teams" => translation.teams(value),
+                "branches" => translation.branches(value),
+                _ => translation.skip(key, "gram does not manage it"),
+            }
+        }
+        translation
+    }
+
+    fn skip(&mut self, key: &str, reason: &str) {
+        self.skipped.push(format!("[{}]: {}", key, reason));
+    }
+
+    /// Skip a value which is not of the type gram expects.
+    fn invalid(&mut self, key: &str, expected: &str) {
+        self.skip(key, &format!("expected {}", expected));
+    }
+
+    fn bool(&mut self, key: &str, value: &Value) -> Option<bool> {
+        let bool = value.as_bool();
+        if bool.is_none() {
+            self.invalid(key, "true or false");
+        }
+        bool
+    }
+
+    fn string(&mut self, key: &str, value: &Value) -> Option<String> {
+        let string = value.as_str().map(str::to_owned);
+        if string.is_none() {
+            self.invalid(key, "a string");
+        }
+        string
+    }
+
+    /// Probot takes topics as a list or as a comma separated string.
+    fn topics(&mut self, key: &str, value: &Value) -> Option<Vec<String>> {
+        let topics = match value {
+            Value::String(topics) => Some(topics.split(',').map(str::trim).collect::<Vec<&str>>()),
+            Value::Array(topics) => topics.iter().map(Value::as_str).collect(),
+            _ => None,
+        };
+        let topics = topics.map(|topics| {
+            topics
+                .into_iter()
+                .filter(|topic| !topic.is_empty())
+                .map(str::to_owned)
+                .collect()
+        });
+        if topics.is_none() {
+            self.invalid(key, "a list of topics");
+        }
+        topics
+    }
+
+    fn permission(&mut self, key: &str, value: &Value) -> Option<Permission> {
+        let permission = value.as_str().and_then(|p| Permission::from_str(p).ok());
+        if permission.is_none() {
+            self.invalid(key, "one of pull, triage, push, maintain or admin");
+        }
+        permission
+    }
+
+    /// The members of a list, along with the key of each.
+    fn members<'a>(
+        &mut self,
+        key: &str,
+        value: &'a Value,
+        name: &str,
+    ) -> Vec<(String, &'a Map<String, Value>)> {
+        let members = match value.as_array() {
+            Some(members) => members,
+            None => {
+                self.invalid(key, "a list");
+                return Vec::new();
+            }
+        };
+        let mut named = Vec::new();
+        for (index, member) in members.iter().enumerate() {
+            let member_name = member.get(name).and_then(Value::as_str);
+            match (member.as_object(), member_name) {
+                (Some(member), Some(member_name)) => {
+                    named.push((format!("{}.{}", key, member_name), member))
+                }
+                _ => self.invalid(
+                    &format!("{}.{}", key, index),
+                    &format!("a mapping with a {}", name),
+                ),
+            }
+        }
+        named
+    }
+
+    fn repository(&mut self, repository: &Value) {
+        let repository = match repository.as_object() {
+            Some(repository) => repository,
+            None => return self.invalid("repository", "a mapping"),
+        };
+        let mut options = Options::default();
+        for (field, value) in repository {
+            let key = format!("repository.{}", field);
+            match field.as_str() {
+                "description" => self.settings.description = self.string(&key, value),
+                "homepage" => self.settings.homepage = self.string(&key, value),
+                "topics" => self.settings.topics = self.topics(&key, value),
+                "private" if repository.contains_key("visibility") => {
+                    self.skip(&key, "visibility is imported instead")
+                }
+                "private" => {
+                    options.visibility = self.bool(&key, value).map(|private| match private {
+                        true => Visibility::Private,
+                        false => Visibility::Public,
+                    })
+                }
+                "visibility" => {
+                    options.visibility = value.as_str().and_then(|v| Visibility::from_str(v).ok());
+                    if options.visibility.is_none() {
+                        self.invalid(&key, "one of public, private or internal");
+                    }
+                }
+                "default_branch" => options.default_branch = self.string(&key, value),
+                "has_issues" => options.has_issues = self.bool(&key, value),
+                "has_projects" => options.has_projects = self.bool(&key, value),
+                "has_wiki" => options.has_wiki = self.bool(&key, value),
+                "has_discussions" => options.has_discussions = self.bool(&key, value),
+                "is_template" => options.is_template = self.bool(&key, value),
+                "allow_forking" => options.allow_forking = self.bool(&key, value),
+                "web_commit_signoff_required" => {
+                    options.web_commit_signoff_required = self.bool(&key, value)
+                }
+                "allow_squash_merge" => options.allow_squash_merge = self.bool(&key, value),
+                "allow_merge_commit" => options.allow_merge_commit = self.bool(&key, value),
+                "allow_rebase_merge" => options.allow_rebase_merge = self.bool(&key, value),
+                "allow_auto_merge" => options.allow_auto_merge = self.bool(&key, value),
+                "allow_update_branch" => options.allow_update_branch = self.bool(&key, value),
+                "delete_branch_on_merge" => options.delete_branch_on_merge = self.bool(&key, value),
+                "squash_merge_commit_title" => {
+                    options.squash_merge_commit_title = self.string(&key, value)
+                }
+                "squash_merge_commit_message" => {
+                    options.squash_merge_commit_message = self.string(&key, value)
+                }
+                "merge_commit_title" => options.merge_commit_title = self.string(&key, value),
+                "merge_commit_message" => options.merge_commit_message = self.string(&key, value),
+                _ => self.skip(&key, "gram does not manage it"),
+            }
+        }
+        if options != Options::default() {
+            self.settings.options = Some(options);
+        }
+    }
+
+    fn labels(&mut self, labels: &Value) {
+        let mut translated = Vec::new();
+        for (key, label) in self.members("labels", labels, "name") {
+            let mut translated_label = Label::default();
+            for (field, value) in label {
+                let field_key = format!("{}.{}", key, field);
+                match field.as_str() {
+                    "name" => {
+                        translated_label.name = self.string(&field_key, value).unwrap_or_default()
+                    }
+                    "color" => {
+                        translated_label.color = self
+                            .string(&field_key, value)
+                            .map(|color| normalize_color(&color))
+                    }
+                    "description" => translated_label.description = self.string(&field_key, value),
+                    _ => self.skip(&field_key, "gram does not manage it"),
+                }
+            }
+            translated.push(translated_label);
+        }
+        self.settings.labels = Some(translated.into());
+    }
+
+    fn collaborators(&mut self, collaborators: &Value) {
+        let mut translated = Vec::new();
+        for (key, collaborator) in self.members("collaborators", collaborators, "username") {
+            let mut translated_collaborator = Collaborator::default();
+            let mut permission = None;
+            for (field, value) in collaborator {
+                let field_key = format!("{}.{}", key, field);
+                match field.as_str() {
+                    "username" => {
+                        translated_collaborator.username =
+                            self.string(&field_key, value).unwrap_or_default()
+                    }
+                    "permission" => permission = self.permission(&field_key, value),
+                    _ => self.skip(&field_key, "gram does not manage it"),
+                }
+            }
+            match permission {
+                Some(permission) => {
+                    translated_collaborator.permission = permission;
+                    translated.push(translated_collaborator);
+                }
+                None => self.skip(&key, "a permission is required"),
+            }
+        }
+        self.settings.collaborators = Some(translated);
+    }
+
+    fn teams(&mut self, teams: &Value) {
+        let mut translated = Vec::new();
+        for (key, team) in self.members("teams", teams, "name") {
+            let mut translated_team = Team::default();
+            let mut permission = None;
+            for (field, value) in team {
+                let field_key = format!("{}.{}", key, field);
+                match field.as_str() {
+                    // Probot looks teams up by their slug, which it calls the name.
+                    "name" => {
+                        translated_team.slug = self.string(&field_key, value).unwrap_or_default()
+                    }
+                    "permission" => permission = self.permission(&field_key, value),
+                    _ => self.skip(&field_key, "gram does not manage it"),
+                }
+            }
+            match permission {
+                Some(permission) => {
+                    translated_team.permission = permission;
+                    translated.push(translated_team);
+                }
+                None => self.skip(&key, "a permission is required"),
+            }
+        }
+        self.settings.teams = Some(translated);
+    }
+
+    fn branches(&mut self, branches: &Value) {
+        let mut translated = Vec::new();
+        for (key, branch) in self.members("branches", branches, "name") {
+            let mut protected = ProtectedBranch::default();
+            for (field, value) in branch {
+                let field_key = format!("{}.{}", key, field);
+                match field.as_str() {
+                    "name" => protected.name = self.string(&field_key, value).unwrap_or_default(),
+                    "protection" => match value {
+                        Value::Object(protection) => {
+                            self.protection(&field_key, protection, &mut protected)
+                        }
+                        _ => self.skip(
+                            &field_key,
+                            "gram cannot remove the protection from a branch",
+                        ),
+                    },
+                    _ => self.skip(&field_key, "gram does not manage it"),
+                }
+            }
+            translated.push(protected);
+        }
+        self.settings.protected = Some(translated);
+    }
+
+    /// Translate the body of a `PUT /repos/{owner}/{repo}/branches/{branch}/protection`
+    /// call, which is what probot holds in `protection`.
+    fn protection(
+        &mut self,
+        key: &str,
+        protection: &Map<String, Value>,
+        protected: &mut ProtectedBranch,
+    ) {
+        for (field, value) in protection {
+            let key = format!("{}.{}", key, field);
+            match (field.as_str(), value) {
+                ("required_pull_request_reviews", Value::Object(reviews)) => {
+                    for (field, value) in reviews {
+                        let key = format!("{}.{}", key, field);
+                        match field.as_str() {
+                            "required_approving_review_count" => {
+                                protected.required_approving_review_count = match value.as_u64() {
+                                    Some(count) => Some(count as u32),
+                                    None => {
+                                        self.invalid(&key, "a whole number");
+                                        None
+                                    }
+                                }
+                            }
+                            "dismiss_stale_reviews" => {
+                                protected.dismiss_stale_reviews = self.bool(&key, value)
+                            }
+                            "require_code_owner_reviews" => {
+                                protected.require_code_owner_reviews = self.bool(&key, value)
+                            }
+                            _ => self.skip(&key, "gram does not manage it"),
+                        }
+                    }
+                }
+                ("required_status_checks", Value::Object(checks)) => {
+                    for (field, value) in checks {
+                        let key = format!("{}.{}", key, field);
+                        match field.as_str() {
+                            "strict" => protected.strict_status_checks = self.bool(&key, value),
+                            "contexts" => {
+                                protected.required_status_checks =
+                                    match value.as_array().and_then(|contexts| {
+                                        contexts
+                                            .iter()
+                                            .map(|context| context.as_str().map(str::to_owned))
+                                            .collect::<Option<Vec<String>>>()
+                                    }) {
+                                        Some(contexts) => Some(contexts),
+                                        None => {
+                                            self.invalid(&key, "a list of strings");
+                                            None
+                                        }
+                                    }
+                            }
+                            _ => self.skip(&key, "gram does not manage it"),
+                        }
+                    }
+                }
+                ("enforce_admins", value) => protected.enforce_admins = self.bool(&key, value),
+                ("required_linear_history", value) => {
+                    protected.required_linear_history = self.bool(&key, value)
+                }
+                ("allow_force_pushes", value) => {
+                    protected.allow_force_pushes = self.bool(&key, value)
+                }
+                ("allow_deletions", value) => protected.allow_deletions = self.bool(&key, value),
+                (_, Value::Null) => self.skip(&key, "gram cannot check that it is turned off"),
+                _ => self.skip(&key, "gram does not manage it"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Translation;
+    use crate::commands::settings::{
+        Collaborator, GramSettings, Label, Options, Permission, ProtectedBranch, Team, Visibility,
+    };
+
+    static PROBOT: &str = r##"
+repository:
+  name: gram
+  description: Cli for automating interactions with repositories
+  homepage: https://example.com
+  topics: github, probot
+  private: true
+  has_wiki: false
+  allow_squash_merge: false
+  allow_rebase_merge: true
+  delete_branch_on_merge: true
+
+labels:
+  - name: bug
+    color: "#d73a4a"
+    description: Something isn't working
+  - name: docs
+    new_name: documentation
+
+milestones:
+  - title: v1.0
+
+collaborators:
+  - username: alice
+    permission: push
+  - username: bob
+    permission: write
+
+teams:
+  - name: core
+    permission: admin
+
+branches:
+  - name: master
+    protection:
+      required_pull_request_reviews:
+        required_approving_review_count: 2
+        dismiss_stale_reviews: true
+        dismissal_restrictions: {}
+      required_status_checks:
+        strict: true
+        contexts: ["build"]
+      enforce_admins: true
+      restrictions: null
+  - name: old
+    protection: null
+"##;
+
+    #[test]
+    fn from_probot_should_translate_the_settings_gram_manages() {
+        // arrange
+        let probot = serde_yaml::from_str(PROBOT).unwrap();
+
+        // act
+        let translation = Translation::from_probot(&probot);
+
+        // assert
+        assert_eq!(
+            GramSettings {
+                description: Some("Cli for automating interactions with repositories".to_owned()),
+                homepage: Some("https://example.com".to_owned()),
+                topics: Some(vec!["github".to_owned(), "probot".to_owned()]),
+                options: Some(Options {
+                    visibility: Some(Visibility::Private),
+                    has_wiki: Some(false),
+                    allow_squash_merge: Some(false),
+                    allow_rebase_merge: Some(true),
+                    delete_branch_on_merge: Some(true),
+                    ..Options::default()
+                }),
+                protected: Some(vec![
+                    ProtectedBranch {
+                        name: "master".to_owned(),
+                        required_approving_review_count: Some(2),
+                        dismiss_stale_reviews: Some(true),
+                        required_status_checks: Some(vec!["build".to_owned()]),
+                        strict_status_checks: Some(true),
+                        enforce_admins: Some(true),
+                        ..ProtectedBranch::default()
+                    },
+                    ProtectedBranch {
+                        name: "old".to_owned(),
+                        ..ProtectedBranch::default()
+                    }
+                ]),
+                labels: Some(
+                    vec![
+                        Label {
+                            name: "bug".to_owned(),
+                            color: Some("d73a4a".to_owned()),
+                            description: Some("Something isn't working".to_owned()),
+                        },
+                        Label {
+                            name: "docs".to_owned(),
+                            ..Label::default()
+                        }
+                    ]
+                    .into()
+                ),
+                collaborators: Some(vec![Collaborator {
+                    username: "alice".to_owned(),
+                    permission: Permission::Push,
+                }]),
+                teams: Some(vec![Team {
+                    slug: "core".to_owned(),
+                    permission: Permission::Admin,
+                }]),
+                ..GramSettings::default()
+            },
+            translation.settings
+        );
+    }
+
+    #[test]
+    fn from_probot_should_report_everything_it_cannot_translate() {
+        // arrange
+        let probot = serde_yaml::from_str(PROBOT).unwrap();
+
+        // act
+        let mut skipped = Translation::from_probot(&probot).skipped;
+
+        // assert
+        skipped.sort();
+        assert_eq!(
+            vec![
+                "[branches.master.protection.required_pull_request_reviews.dismissal_restrictions]: \
+                 gram does not manage it",
+                "[branches.master.protection.restrictions]: gram cannot check that it is turned off",
+                "[branches.old.protection]: gram cannot remove the protection from a branch",
+                "[collaborators.bob.permission]: \
+                 expected one of pull, triage, push, maintain or admin",
+                "[collaborators.bob]: a permission is required",
+                "[labels.docs.new_name]: gram does not manage it",
+                "[milestones]: gram does not manage it",
+                "[repository.name]: gram does not manage it",
+            ],
+            skipped
+        );
+    }
+}
@@ -1,63 +1,668 @@
+pub mod apply;
 pub mod diff;
-use anyhow::Result;
+pub mod export;
+pub mod format;
+pub mod import;
+pub mod plan;
+pub mod remote;
+pub mod resolve;
+pub mod schema;
+pub mod spans;
+pub mod validate;
+use anyhow::{anyhow, Result};
+pub use apply::Apply;
 pub use diff::Diff;
-use serde::Deserialize;
+pub use export::Export;
+use format::FileFormat;
+pub use import::Import;
+pub use plan::Plan;
+pub use resolve::Resolve;
+pub use schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use spans::Spans;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
+pub use validate::Validate;
 
 /// Supported settings subcommands.
 #[derive(Debug, StructOpt)]
 pub enum SettingsCmd {
     Diff(Diff),
+    Plan(Plan),
+    Apply(Apply),
+    Export(Export),
+    Resolve(Resolve),
+    Validate(Validate),
+    Schema(Schema),
+    Import(Import),
 }
 
 /// Repository settings that `gram` is able to see.
 ///
-/// Any settings that are not defined here will be ignored in all
-/// `gram` commands.
-#[derive(Debug, Default, Clone, Deserialize)]
+/// Any key that is not defined here is an error in all `gram` commands.
+#[derive(Debug, Default, Clone, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct GramSettings {
+    /// Settings files to merge in before this one, relative to this file.
+    /// Later files win.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Vec<String>>,
+    /// The short description of the repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    /// The url shown next to the description of the repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    /// Topics the repository is tagged with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topics: Option<Vec<String>>,
+    /// Settings under the Options section of the repository settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<Options>,
+    /// Branches which should be protected.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub protected: Option<Vec<ProtectedBranch>>,
+    /// Labels which should exist on the repository, and whether any other
+    /// label counts as drift.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<LabelsFormat>")]
+    pub labels: Option<Labels>,
+    /// Users with direct access to the repository. Any other user with direct
+    /// access counts as drift.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collaborators: Option<Vec<Collaborator>>,
+    /// Teams with access to the repository. Any other team with access counts as drift.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub teams: Option<Vec<Team>>,
+    /// Webhooks which should exist on the repository. Other webhooks on the
+    /// repository are left alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhooks: Option<Vec<Webhook>>,
+    /// Deployment environments which should exist on the repository. Other
+    /// environments on the repository are left alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environments: Option<Vec<Environment>>,
+    /// Actions secrets by name and where to read their values from, values
+    /// never go in the settings file. Any other secret on the repository
+    /// counts as drift.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<BTreeMap<String, Secret>>,
+    /// Which actions can run on the repository, what their token can do and
+    /// the variables workflows can read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Actions>,
 }
 
 /// Represents settings that appear under a repositories Settings -> Options section.
-#[derive(Clone, Debug, Default, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
 pub struct Options {
-    #[serde(rename = "allow-squash-merge")]
+    /// Who can see the repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// The branch pull requests target and the repository is checked out on by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+    /// Whether the repository has issues.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_issues: Option<bool>,
+    /// Whether the repository has projects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_projects: Option<bool>,
+    /// Whether the repository has a wiki.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_wiki: Option<bool>,
+    /// Whether the repository has discussions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_discussions: Option<bool>,
+    /// Whether new repositories can be generated from this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_template: Option<bool>,
+    /// Whether the repository can be forked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_forking: Option<bool>,
+    /// Whether commits made on the web must be signed off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_commit_signoff_required: Option<bool>,
+    /// Whether pull requests can be squash merged.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_squash_merge: Option<bool>,
-    #[serde(rename = "allow-merge-commit")]
+    /// Whether pull requests can be merged with a merge commit.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_merge_commit: Option<bool>,
-    #[serde(rename = "allow-rebase-merge")]
+    /// Whether pull requests can be rebase merged.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_rebase_merge: Option<bool>,
-    #[serde(rename = "delete-branch-on-merge")]
+    /// Whether pull requests can be set to merge once their checks pass.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_auto_merge: Option<bool>,
+    /// Whether pull requests which are behind their base branch can be updated from it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_update_branch: Option<bool>,
+    /// Whether head branches are deleted once their pull request is merged.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub delete_branch_on_merge: Option<bool>,
+    /// The title of squash merge commits, PR_TITLE or COMMIT_OR_PR_TITLE.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^(PR_TITLE|COMMIT_OR_PR_TITLE)$"))]
+    pub squash_merge_commit_title: Option<String>,
+    /// The message of squash merge commits, PR_BODY, COMMIT_MESSAGES or BLANK.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^(PR_BODY|COMMIT_MESSAGES|BLANK)$"))]
+    pub squash_merge_commit_message: Option<String>,
+    /// The title of merge commits, PR_TITLE or MERGE_MESSAGE.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^(PR_TITLE|MERGE_MESSAGE)$"))]
+    pub merge_commit_title: Option<String>,
+    /// The message of merge commits, PR_BODY, PR_TITLE or BLANK.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^(PR_BODY|PR_TITLE|BLANK)$"))]
+    pub merge_commit_message: Option<String>,
 }
 
-impl Copy for Options {}
+/// Who can see a repository. Internal repositories are visible to members of
+/// the enterprise the repository belongs to.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    Private,
+    Internal,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let visibility = match self {
+            Visibility::Public => "public",
+            Visibility::Private => "private",
+            Visibility::Internal => "internal",
+        };
+        write!(f, "{}", visibility)
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = anyhow::Error;
+
+    fn from_str(visibility: &str) -> Result<Self> {
+        match visibility {
+            "public" => Ok(Visibility::Public),
+            "private" => Ok(Visibility::Private),
+            "internal" => Ok(Visibility::Internal),
+            _ => Err(anyhow!("Unknown visibility [{}]", visibility)),
+        }
+    }
+}
+
+/// Represents settings that appear under a repositories Settings -> Actions section.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct Actions {
+    /// Whether actions can run at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Which actions workflows can use.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_actions: Option<AllowedActions>,
+    /// Whether actions made by github can be used when `allowed-actions` is selected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_owned_allowed: Option<bool>,
+    /// Whether actions by verified creators can be used when `allowed-actions`
+    /// is selected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_allowed: Option<bool>,
+    /// Patterns of other actions which can be used when `allowed-actions` is
+    /// selected, e.g. `docker/login-action@*`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patterns_allowed: Option<Vec<String>>,
+    /// What the `GITHUB_TOKEN` of a workflow can do by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_workflow_permissions: Option<WorkflowPermission>,
+    /// Whether workflows can approve pull requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub can_approve_pull_requests: Option<bool>,
+    /// Variables available to every workflow. Other variables on the
+    /// repository are left alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables: Option<BTreeMap<String, String>>,
+}
+
+/// The actions workflows on a repository can use.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowedActions {
+    /// Any action.
+    All,
+    /// Only actions in the same repository or organization.
+    LocalOnly,
+    /// Only local actions and those allowed by `github-owned-allowed`,
+    /// `verified-allowed` and `patterns-allowed`.
+    Selected,
+}
+
+impl fmt::Display for AllowedActions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let allowed = match self {
+            AllowedActions::All => "all",
+            AllowedActions::LocalOnly => "local_only",
+            AllowedActions::Selected => "selected",
+        };
+        write!(f, "{}", allowed)
+    }
+}
 
-#[derive(Clone, Debug, Default, Deserialize)]
+impl FromStr for AllowedActions {
+    type Err = anyhow::Error;
+
+    fn from_str(allowed: &str) -> Result<Self> {
+        match allowed {
+            "all" => Ok(AllowedActions::All),
+            "local_only" => Ok(AllowedActions::LocalOnly),
+            "selected" => Ok(AllowedActions::Selected),
+            _ => Err(anyhow!("Unknown allowed actions [{}]", allowed)),
+        }
+    }
+}
+
+/// What the `GITHUB_TOKEN` of a workflow can do with the repository contents.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowPermission {
+    Read,
+    Write,
+}
+
+impl fmt::Display for WorkflowPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let permission = match self {
+            WorkflowPermission::Read => "read",
+            WorkflowPermission::Write => "write",
+        };
+        write!(f, "{}", permission)
+    }
+}
+
+impl FromStr for WorkflowPermission {
+    type Err = anyhow::Error;
+
+    fn from_str(permission: &str) -> Result<Self> {
+        match permission {
+            "read" => Ok(WorkflowPermission::Read),
+            "write" => Ok(WorkflowPermission::Write),
+            _ => Err(anyhow!("Unknown workflow permission [{}]", permission)),
+        }
+    }
+}
+
+/// A protected branch and the rules protecting it.
+///
+/// Only the name is required, any rule which is not set is not checked.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
 pub struct ProtectedBranch {
+    /// The name of the branch.
     pub name: String,
+    /// How many approving reviews a pull request needs before merging.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 6))]
+    pub required_approving_review_count: Option<u32>,
+    /// Whether approvals are dismissed when new commits are pushed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dismiss_stale_reviews: Option<bool>,
+    /// Whether a code owner must approve pull requests which change their code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_code_owner_reviews: Option<bool>,
+    /// Status check contexts which must pass before merging.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_status_checks: Option<Vec<String>>,
+    /// Whether the branch must be up to date before merging.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict_status_checks: Option<bool>,
+    /// Whether the rules apply to administrators too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enforce_admins: Option<bool>,
+    /// Whether merge commits are prevented from being pushed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_linear_history: Option<bool>,
+    /// Whether anyone with push access can force push.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_force_pushes: Option<bool>,
+    /// Whether anyone with push access can delete the branch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_deletions: Option<bool>,
 }
 
-pub struct SettingsReader;
+/// Labels which should exist on the repository.
+///
+/// These are either a list of labels, `[[labels]]`, or a table which can
+/// also say whether to prune other labels, `[labels]` with `prune` and the
+/// list as `[[labels.items]]`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(from = "LabelsFormat", into = "LabelsFormat")]
+pub struct Labels {
+    /// Whether labels on the repository which are not in `items` count as drift.
+    pub prune: Option<bool>,
+    pub items: Vec<Label>,
+}
+
+impl From<Vec<Label>> for Labels {
+    fn from(items: Vec<Label>) -> Self {
+        Labels { prune: None, items }
+    }
+}
+
+impl<'a> IntoIterator for &'a Labels {
+    type Item = &'a Label;
+    type IntoIter = std::slice::Iter<'a, Label>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// A list of labels, or a table with `prune` and the list as `items`.
+#[derive(Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged)]
+#[schemars(rename = "Labels")]
+enum LabelsFormat {
+    List(Vec<Label>),
+    Table(LabelsTable),
+}
+
+#[derive(Clone, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+struct LabelsTable {
+    /// Whether labels on the repository which are not in `items` count as drift.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prune: Option<bool>,
+    /// Labels which should exist on the repository.
+    #[serde(default)]
+    items: Vec<Label>,
+}
+
+impl From<LabelsFormat> for Labels {
+    fn from(format: LabelsFormat) -> Self {
+        match format {
+            LabelsFormat::List(items) => Labels::from(items),
+            LabelsFormat::Table(LabelsTable { prune, items }) => Labels { prune, items },
+        }
+    }
+}
+
+impl From<Labels> for LabelsFormat {
+    /// Labels are only written as a table when they need to be.
+    fn from(labels: Labels) -> Self {
+        match labels.prune {
+            None => LabelsFormat::List(labels.items),
+            prune => LabelsFormat::Table(LabelsTable {
+                prune,
+                items: labels.items,
+            }),
+        }
+    }
+}
+
+/// A label which should exist on the repository.
+///
+/// Only the name is required, a color or description which is not set is not checked.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Label {
+    pub name: String,
+    /// Hex color of the label, the leading `#` is optional.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(regex(pattern = r"^#?[0-9a-fA-F]{6}$"))]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A label color the way github keeps it, without the leading `#` and in lowercase.
+///
+/// Github rejects colors with a `#`, so colors are normalized before they
+/// are validated, compared or sent.
+pub fn normalize_color(color: &str) -> String {
+    color.strip_prefix('#').unwrap_or(color).to_lowercase()
+}
+
+/// A user with direct access to the repository.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Collaborator {
+    pub username: String,
+    pub permission: Permission,
+}
+
+/// A team with access to the repository.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Team {
+    pub slug: String,
+    pub permission: Permission,
+}
+
+/// A webhook which should exist on the repository, matched by its url.
+///
+/// Only the url is required, settings which are not set are not checked.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<ContentType>,
+    /// Events which trigger the webhook, e.g. `push` or `pull_request`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+    /// Whether the webhook is delivered when its events happen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    /// Name of the environment variable holding the secret payloads are
+    /// signed with. It is only read when applying, github never returns the
+    /// secret so only whether one is set is checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_env: Option<String>,
+    /// The id github gave the webhook, only known for retrieved webhooks.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub id: Option<u64>,
+    /// Whether github has a secret for the webhook, only known for retrieved webhooks.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub has_secret: bool,
+}
+
+/// The format webhook payloads are delivered in.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentType {
+    Json,
+    Form,
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content_type = match self {
+            ContentType::Json => "json",
+            ContentType::Form => "form",
+        };
+        write!(f, "{}", content_type)
+    }
+}
+
+impl FromStr for ContentType {
+    type Err = anyhow::Error;
+
+    fn from_str(content_type: &str) -> Result<Self> {
+        match content_type {
+            "json" => Ok(ContentType::Json),
+            "form" => Ok(ContentType::Form),
+            _ => Err(anyhow!("Unknown content type [{}]", content_type)),
+        }
+    }
+}
+
+/// A deployment environment and the rules protecting it.
+///
+/// Only the name is required, rules which are not set are not checked.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    pub name: String,
+    /// Minutes to wait before a deployment goes ahead, at most 30 days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0, max = 43200))]
+    pub wait_timer: Option<u32>,
+    /// Usernames of the users who can approve deployments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviewers: Option<Vec<String>>,
+    /// Slugs of the teams who can approve deployments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviewer_teams: Option<Vec<String>>,
+    /// Which branches can deploy to the environment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployment_branches: Option<DeploymentBranches>,
+    /// Name patterns of the branches which can deploy, e.g. `release/*`, when
+    /// `deployment-branches` is custom.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_patterns: Option<Vec<String>>,
+    /// Variables available to workflows deploying to the environment. Other
+    /// variables on the environment are left alone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables: Option<BTreeMap<String, String>>,
+}
+
+/// Where to read the value of an actions secret from when applying, exactly
+/// one of `env` or `file` must be given. Secrets retrieved from github have
+/// neither as github never returns their values.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+pub struct Secret {
+    /// Name of the environment variable holding the value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+    /// Path to a file holding the value, relative to the current directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.env, &self.file) {
+            (Some(env), None) => write!(f, "env {}", env),
+            (None, Some(file)) => write!(f, "file {}", file.display()),
+            (Some(env), Some(file)) => write!(f, "env {} and file {}", env, file.display()),
+            (None, None) => write!(f, "no source"),
+        }
+    }
+}
+
+/// The branches which can deploy to an environment.
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeploymentBranches {
+    /// Any branch.
+    All,
+    /// Only branches with branch protection.
+    Protected,
+    /// Only branches matching `branch-patterns`.
+    Custom,
+}
+
+impl fmt::Display for DeploymentBranches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let branches = match self {
+            DeploymentBranches::All => "all",
+            DeploymentBranches::Protected => "protected",
+            DeploymentBranches::Custom => "custom",
+        };
+        write!(f, "{}", branches)
+    }
+}
+
+/// Permission levels a user or team can have on a repository, from least to most access.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    #[default]
+    Pull,
+    Triage,
+    Push,
+    Maintain,
+    Admin,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let permission = match self {
+            Permission::Pull => "pull",
+            Permission::Triage => "triage",
+            Permission::Push => "push",
+            Permission::Maintain => "maintain",
+            Permission::Admin => "admin",
+        };
+        write!(f, "{}", permission)
+    }
+}
+
+impl FromStr for Permission {
+    type Err = anyhow::Error;
+
+    fn from_str(permission: &str) -> Result<Self> {
+        match permission {
+            "pull" => Ok(Permission::Pull),
+            "triage" => Ok(Permission::Triage),
+            "push" => Ok(Permission::Push),
+            "maintain" => Ok(Permission::Maintain),
+            "admin" => Ok(Permission::Admin),
+            _ => Err(anyhow!("Unknown permission [{}]", permission)),
+        }
+    }
+}
+
+/// Reads settings files from disk.
+pub struct SettingsReader {
+    format: Option<FileFormat>,
+}
 
 impl SettingsReader {
-    pub fn new() -> Self {
-        Self {}
+    /// Read the settings files given on the command line as `format` if
+    /// given, whatever their extension.
+    pub fn new(format: Option<FileFormat>) -> Self {
+        Self { format }
     }
 }
 
 pub trait FileReader {
     fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, std::io::Error>;
 
-    fn read_settings(&self, settings_location: &PathBuf) -> Result<GramSettings> {
-        let settings_str = self.read_to_string(settings_location)?;
-        let settings = toml::from_str::<GramSettings>(&settings_str)?;
-        Ok(settings)
+    /// The format of the settings files given on the command line, if it
+    /// should not be worked out from their extension.
+    ///
+    /// Files they extend are always read by their extension.
+    fn file_format(&self) -> Option<FileFormat> {
+        None
+    }
+
+    /// Read settings, merged with any settings files they extend, along with
+    /// the line each of their keys is on.
+    ///
+    /// Lines are only known for keys in the settings file itself, not in the
+    /// files it extends.
+    fn read_settings(&self, settings_location: &PathBuf) -> Result<(GramSettings, Spans)> {
+        resolve::read_settings(self, settings_location)
     }
 }
 
@@ -65,4 +670,8 @@ impl FileReader for SettingsReader {
     fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, std::io::Error> {
         fs::read_to_string(path)
     }
+
+    fn file_format(&self) -> Option<FileFormat> {
+        self.format
+    }
 }
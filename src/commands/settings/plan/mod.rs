@@ -0,0 +1,332 @@
+use super::{
+    apply::{Apply, Change},
+    diff::{
+        model::Value,
+        retrieve::{Retrieve, Sections},
+        DiffableSettings,
+    },
+    GramSettings,
+};
+use crate::commands::FileReader;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+/// Save the changes needed to make a repository match a settings toml file.
+///
+/// The plan can be reviewed and later applied with `gram settings apply --plan`.
+/// Nothing is changed on the repository when making a plan.
+#[derive(Debug, StructOpt)]
+pub struct Plan {
+    /// The owner of the repository.
+    #[structopt(short, long)]
+    pub owner: String,
+
+    /// The name of the repository.
+    #[structopt(short, long)]
+    pub repo: String,
+
+    /// Path to the settings TOML file.
+    #[structopt(name = "file", short, long)]
+    pub settings_file: PathBuf,
+
+    /// Path to write the plan to.
+    #[structopt(long)]
+    pub out: PathBuf,
+}
+
+/// A saved list of changes for a single repository.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SettingsPlan {
+    pub owner: String,
+    pub repo: String,
+    pub changes: Vec<PlannedChange>,
+}
+
+/// A change along with the github call it maps to.
+///
+/// The call is only there for whoever reviews the plan, applying the plan
+/// uses the change itself.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PlannedChange {
+    pub call: String,
+    #[serde(flatten)]
+    pub change: Change,
+    /// The other actual values the change was made from, which must not have
+    /// changed when the plan is applied.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub reads: BTreeMap<String, Value>,
+}
+
+impl Plan {
+    pub async fn handle<F, R>(self, reader: F, retriever: R) -> Result<()>
+    where
+        F: FileReader,
+        R: Retrieve,
+    {
+        let plan = self.plan(reader, retriever).await?;
+        fs::write(&self.out, serde_json::to_string_pretty(&plan)?)?;
+        for planned in &plan.changes {
+            println!("{}: {}", planned.call, planned.change.action);
+        }
+        println!(
+            "Plan with {} change(s) written to {}",
+            plan.changes.len(),
+            self.out.display()
+        );
+        Ok(())
+    }
+
+    async fn plan<F, R>(&self, reader: F, retriever: R) -> Result<SettingsPlan>
+    where
+        F: FileReader,
+        R: Retrieve,
+    {
+        let (configured_settings, _) = reader.read_settings(&self.settings_file)?;
+        let sections = Sections::configured(&configured_settings);
+        let actual_settings = retriever
+            .retrieve(&self.owner, &self.repo, sections)
+            .await?;
+        let changes = Apply::changes(&configured_settings, &actual_settings)?
+            .into_iter()
+            .map(|change| PlannedChange {
+                call: change.action.call(&self.owner, &self.repo),
+                reads: change.action.reads(&actual_settings),
+                change,
+            })
+            .collect();
+        Ok(SettingsPlan {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            changes,
+        })
+    }
+}
+
+impl SettingsPlan {
+    /// Read a plan previously written by `gram settings plan`.
+    pub fn read<F: FileReader>(reader: &F, path: &Path) -> Result<Self> {
+        let plan_str = reader.read_to_string(path)?;
+        serde_json::from_str::<Self>(&plan_str)
+            .map_err(|e| anyhow!("Failed to read plan {}: {}", path.display(), e))
+    }
+
+    /// Check the actual settings still hold the values this plan was made
+    /// against, both of the keys it changes and of any others its changes
+    /// were made from.
+    pub fn verify(&self, actual: &GramSettings) -> Result<()> {
+        let current = HashMap::from(DiffableSettings(actual));
+        let mut stale = Vec::new();
+        let mut check = |key: &str, planned: Option<&Value>, now: Option<&Value>| {
+            let message = format!(
+                "[{}]: planned against [{}] but it is now [{}]",
+                key,
+                display(planned),
+                display(now)
+            );
+            if planned != now && !stale.contains(&message) {
+                stale.push(message);
+            }
+        };
+        for planned in &self.changes {
+            let key = planned.change.key.as_str();
+            check(key, planned.change.before.as_ref(), current.get(key));
+            let reads = planned.change.action.reads(actual);
+            let mut keys = planned.reads.keys().chain(reads.keys()).collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                check(key, planned.reads.get(key), reads.get(key));
+            }
+        }
+        match stale.as_slice() {
+            [] => Ok(()),
+            [..] => Err(anyhow!(
+                "Repository settings changed since the plan was made, refusing to apply it!\n{}",
+                stale.join("\n")
+            )),
+        }
+    }
+}
+
+fn display(value: Option<&Value>) -> String {
+    value.map(Value::to_string).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Plan, SettingsPlan};
+    use crate::commands::settings::{
+        apply::Action,
+        diff::{
+            model::Value,
+            retrieve::{Retrieve, Sections},
+        },
+        spans::Spans,
+        FileReader, GramSettings, Options, ProtectedBranch,
+    };
+    use crate::github::RepositoryUpdate;
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use std::path::{Path, PathBuf};
+
+    struct FakeFileReader {
+        settings: GramSettings,
+        contents: String,
+    }
+
+    impl FileReader for FakeFileReader {
+        fn read_to_string<P: AsRef<Path>>(&self, _: P) -> Result<String, std::io::Error> {
+            Ok(self.contents.clone())
+        }
+
+        fn read_settings(&self, _: &PathBuf) -> Result<(GramSettings, Spans)> {
+            Ok((self.settings.clone(), Spans::default()))
+        }
+    }
+
+    struct FakeRetriever {
+        settings: GramSettings,
+    }
+
+    #[async_trait]
+    impl Retrieve for FakeRetriever {
+        async fn retrieve(&self, _owner: &str, _repo: &str, _: Sections) -> Result<GramSettings> {
+            Ok(self.settings.clone())
+        }
+    }
+
+    fn settings(allow_squash_merge: bool) -> GramSettings {
+        GramSettings {
+            options: Some(Options {
+                allow_squash_merge: Some(allow_squash_merge),
+                ..Options::default()
+            }),
+            ..GramSettings::default()
+        }
+    }
+
+    async fn plan(configured: GramSettings, actual: GramSettings) -> SettingsPlan {
+        let command = Plan {
+            owner: "owner".to_owned(),
+            repo: "repo".to_owned(),
+            settings_file: PathBuf::new(),
+            out: PathBuf::new(),
+        };
+        let reader = FakeFileReader {
+            settings: configured,
+            contents: String::new(),
+        };
+        let retriever = FakeRetriever { settings: actual };
+        command.plan(reader, retriever).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn plan_should_record_before_and_after_values_and_the_call() {
+        // act
+        let plan = plan(settings(false), settings(true)).await;
+
+        // assert
+        assert_eq!(1, plan.changes.len());
+        let planned = &plan.changes[0];
+        assert_eq!("PATCH /repos/owner/repo", planned.call);
+        assert_eq!("options.allow-squash-merge", planned.change.key);
+        assert_eq!(Some(Value::Bool(true)), planned.change.before);
+        assert_eq!(Some(Value::Bool(false)), planned.change.after);
+        assert_eq!(
+            Action::UpdateRepository(RepositoryUpdate {
+                allow_squash_merge: Some(false),
+                ..RepositoryUpdate::default()
+            }),
+            planned.change.action
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_should_read_back_the_plan_it_serialized() {
+        // arrange
+        let plan = plan(settings(false), settings(true)).await;
+        let reader = FakeFileReader {
+            settings: GramSettings::default(),
+            contents: serde_json::to_string(&plan).unwrap(),
+        };
+
+        // act
+        let read = SettingsPlan::read(&reader, Path::new("plan.json"));
+
+        // assert
+        assert_eq!(plan, read.unwrap());
+    }
+
+    #[tokio::test]
+    async fn verify_should_succeed_if_settings_are_unchanged_since_the_plan() {
+        // arrange
+        let plan = plan(settings(false), settings(true)).await;
+
+        // act
+        let result = plan.verify(&settings(true));
+
+        // assert
+        assert!(result.is_ok(), "expected ok, got {:#?}", result);
+    }
+
+    #[tokio::test]
+    async fn verify_should_refuse_a_plan_if_settings_changed_since_the_plan() {
+        // arrange
+        let plan = plan(settings(false), settings(true)).await;
+
+        // act
+        let result = plan.verify(&GramSettings::default());
+
+        // assert
+        assert!(result.is_err());
+        assert_eq!(
+            "Repository settings changed since the plan was made, refusing to apply it!\n\
+             [options.allow-squash-merge]: planned against [true] but it is now []",
+            format!("{}", result.err().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_should_refuse_a_plan_if_a_value_a_change_was_made_from_changed() {
+        // arrange
+        let branch = |enforce_admins: bool, allow_deletions: bool| ProtectedBranch {
+            name: "master".to_owned(),
+            enforce_admins: Some(enforce_admins),
+            allow_deletions: Some(allow_deletions),
+            ..ProtectedBranch::default()
+        };
+        let configured = GramSettings {
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                enforce_admins: Some(true),
+                ..ProtectedBranch::default()
+            }]),
+            ..GramSettings::default()
+        };
+        let actual = |allow_deletions: bool| GramSettings {
+            protected: Some(vec![branch(false, allow_deletions)]),
+            ..GramSettings::default()
+        };
+        let plan = plan(configured, actual(false)).await;
+
+        // act
+        let result = plan.verify(&actual(true));
+
+        // assert
+        assert_eq!(
+            Some(&Value::Bool(false)),
+            plan.changes[0]
+                .reads
+                .get("protected.master.allow-deletions")
+        );
+        assert_eq!(
+            "Repository settings changed since the plan was made, refusing to apply it!\n\
+             [protected.master.allow-deletions]: planned against [false] but it is now [true]",
+            format!("{}", result.err().unwrap())
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Working out which repository to use from the local git checkout.
+use super::FileReader;
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+
+/// Where a checkout keeps its settings file when none is given.
+pub static DEFAULT_SETTINGS_FILE: &str = ".github/gram/settings.toml";
+
+/// The owner and name of the repository a remote of the checkout `dir` is
+/// in points at.
+pub fn remote_repository<F: FileReader + ?Sized>(
+    reader: &F,
+    dir: &Path,
+    remote: &str,
+) -> Result<(String, String)> {
+    let config_path = git_config(reader, dir).ok_or_else(|| {
+        anyhow!(
+            "No repository given and {} is not in a git checkout",
+            dir.display()
+        )
+    })?;
+    let config = reader.read_to_string(&config_path).map_err(|e| {
+        anyhow!(
+            "No repository given and failed to read {}: {}",
+            config_path.display(),
+            e
+        )
+    })?;
+    let url = remote_url(&config, remote).ok_or_else(|| {
+        anyhow!(
+            "No url for remote [{}] in {}",
+            remote,
+            config_path.display()
+        )
+    })?;
+    parse_repository(&url)
+}
+
+/// The git config of the checkout `dir` is in, looking in each parent
+/// directory until there is a `.git`.
+///
+/// In worktrees and submodules `.git` is a file pointing at the real git
+/// directory, and a worktree shares the config of its main checkout.
+fn git_config<F: FileReader + ?Sized>(reader: &F, dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        let dot_git = dir.join(".git");
+        let git_dir = match reader.read_to_string(&dot_git) {
+            Ok(file) => dir.join(file.trim().strip_prefix("gitdir:")?.trim()),
+            Err(_) => dot_git,
+        };
+        let common_dir = match reader.read_to_string(git_dir.join("commondir")) {
+            Ok(common_dir) => git_dir.join(common_dir.trim()),
+            Err(_) => git_dir,
+        };
+        let config = common_dir.join("config");
+        reader.read_to_string(&config).ok().map(|_| config)
+    })
+}
+
+/// The url of a remote in a git config file.
+fn remote_url(config: &str, remote: &str) -> Option<String> {
+    let section = format!("[remote \"{}\"]", remote);
+    let mut in_section = false;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == section;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let mut parts = line.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if key.trim().eq_ignore_ascii_case("url") => {
+                return Some(value.trim().trim_matches('"').to_owned())
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The owner and name of a repository given as `owner/repo`, a github url
+/// like `https://github.com/owner/repo`, or a git remote url in https, ssh or
+/// `git@host:owner/repo.git` form. The host is not checked, so Github
+/// Enterprise Server urls work too.
+pub fn parse_repository(repository: &str) -> Result<(String, String)> {
+    let error = || anyhow!("Expected [{}] to be owner/repo or a github url", repository);
+    let path = match repository.find("://") {
+        // scheme://[user@]host[:port]/owner/repo
+        Some(scheme_end) => {
+            let rest = &repository[scheme_end + 3..];
+            &rest[rest.find('/').ok_or_else(error)?..]
+        }
+        None => match (repository.find(':'), repository.find('/')) {
+            // [user@]host:owner/repo
+            (Some(colon), Some(slash)) if colon < slash => &repository[colon + 1..],
+            _ if repository.split('/').count() == 2 => repository,
+            _ => return Err(error()),
+        },
+    };
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+    match (segments.next(), segments.next()) {
+        (Some(owner), Some(repo)) => {
+            let repo = repo.trim_end_matches(".git");
+            match repo {
+                "" => Err(error()),
+                repo => Ok((owner.to_owned(), repo.to_owned())),
+            }
+        }
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_repository, remote_repository, remote_url};
+    use crate::commands::settings::FileReader;
+    use std::io::{Error, ErrorKind};
+    use std::path::Path;
+
+    static CONFIG: &str = r#"
+[core]
+	bare = false
+[remote "origin"]
+	url = git@github.com:wayofthepie/gram.git
+	fetch = +refs/heads/*:refs/remotes/origin/*
+[remote "upstream"]
+	url = https://github.example.com/platform/gram
+[branch "master"]
+	remote = origin
+"#;
+
+    /// A checkout in `/code/gram`, with a worktree in `/code/gram-fix` and a
+    /// submodule in `/code/gram/vendor/lib`. Reading `.git` as a file fails
+    /// where it is a directory.
+    struct CheckoutReader;
+
+    impl FileReader for CheckoutReader {
+        fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, Error> {
+            match path.as_ref().to_str() {
+                Some("/code/gram/.git/config")
+                | Some("/code/gram/.git/worktrees/gram-fix/../../config") => Ok(CONFIG.to_owned()),
+                Some("/code/gram-fix/.git") => {
+                    Ok("gitdir: /code/gram/.git/worktrees/gram-fix\n".to_owned())
+                }
+                Some("/code/gram/.git/worktrees/gram-fix/commondir") => Ok("../..\n".to_owned()),
+                Some("/code/gram/vendor/lib/.git") => {
+                    Ok("gitdir: ../../.git/modules/lib\n".to_owned())
+                }
+                Some("/code/gram/vendor/lib/../../.git/modules/lib/config") => {
+                    Ok("[remote \"origin\"]\n\turl = https://github.com/vendor/lib\n".to_owned())
+                }
+                _ => Err(Error::from(ErrorKind::NotFound)),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_repository_should_accept_slugs_and_remote_urls() {
+        // arrange
+        let expected = Some(("owner".to_owned(), "repo".to_owned()));
+
+        // assert
+        for repository in &[
+            "owner/repo",
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo.git",
+            "https://github.com/owner/repo/tree/master",
+            "https://user@github.example.com/owner/repo.git",
+            "ssh://git@github.com/owner/repo.git",
+            "ssh://git@github.example.com:2222/owner/repo",
+            "git@github.com:owner/repo.git",
+            "git@github.example.com:owner/repo",
+        ] {
+            assert_eq!(
+                expected,
+                parse_repository(repository).ok(),
+                "{}",
+                repository
+            );
+        }
+    }
+
+    #[test]
+    fn parse_repository_should_error_if_there_is_no_owner_and_repo() {
+        // assert
+        for repository in &[
+            "repo",
+            "owner/repo/extra",
+            "https://github.com/owner",
+            "git@github.com:owner/.git",
+        ] {
+            assert_eq!(
+                format!("Expected [{}] to be owner/repo or a github url", repository),
+                format!("{}", parse_repository(repository).err().unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn remote_url_should_only_read_the_url_of_the_named_remote() {
+        // arrange
+        let config = "[remote \"origin\"]\nfetch = x\n[branch \"master\"]\nurl = y\n";
+
+        // act
+        let origin = remote_url(config, "origin");
+
+        // assert
+        assert_eq!(None, origin);
+    }
+
+    #[test]
+    fn remote_repository_should_use_the_url_of_the_remote() {
+        // arrange
+        let dir = Path::new("/code/gram");
+
+        // act
+        let origin = remote_repository(&CheckoutReader, dir, "origin").unwrap();
+        let upstream = remote_repository(&CheckoutReader, dir, "upstream").unwrap();
+        let missing = remote_repository(&CheckoutReader, dir, "fork");
+
+        // assert
+        assert_eq!(("wayofthepie".to_owned(), "gram".to_owned()), origin);
+        assert_eq!(("platform".to_owned(), "gram".to_owned()), upstream);
+        assert_eq!(
+            "No url for remote [fork] in /code/gram/.git/config",
+            format!("{}", missing.err().unwrap())
+        );
+    }
+
+    #[test]
+    fn remote_repository_should_find_the_checkout_from_a_subdirectory_worktree_or_submodule() {
+        // act
+        let subdirectory = remote_repository(
+            &CheckoutReader,
+            Path::new("/code/gram/src/commands"),
+            "origin",
+        );
+        let worktree =
+            remote_repository(&CheckoutReader, Path::new("/code/gram-fix/src"), "origin");
+        let submodule = remote_repository(
+            &CheckoutReader,
+            Path::new("/code/gram/vendor/lib"),
+            "origin",
+        );
+        let outside = remote_repository(&CheckoutReader, Path::new("/tmp"), "origin");
+
+        // assert
+        assert_eq!(
+            ("wayofthepie".to_owned(), "gram".to_owned()),
+            subdirectory.unwrap()
+        );
+        assert_eq!(
+            ("wayofthepie".to_owned(), "gram".to_owned()),
+            worktree.unwrap()
+        );
+        assert_eq!(("vendor".to_owned(), "lib".to_owned()), submodule.unwrap());
+        assert_eq!(
+            "No repository given and /tmp is not in a git checkout",
+            format!("{}", outside.err().unwrap())
+        );
+    }
+}
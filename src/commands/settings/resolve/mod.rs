@@ -0,0 +1,476 @@
+use super::{export::Export, format::FileFormat, spans::Spans, FileReader, GramSettings};
+use anyhow::{anyhow, Result};
+use std::path::{Component, Path, PathBuf};
+use structopt::StructOpt;
+use toml::{value::Table, Value};
+
+/// Print a settings file with every file it extends merged in.
+///
+/// Files listed in `extends` are merged in order, then the file itself, so
+/// later files win. Tables are merged key by key, and lists of tables like
+/// `[[protected]]` or `[[labels]]` are merged by name.
+#[derive(Debug, StructOpt)]
+pub struct Resolve {
+    /// Path to the settings file.
+    #[structopt(name = "file", short, long)]
+    pub settings_file: PathBuf,
+}
+
+/// The key listing the files a settings file extends.
+static EXTENDS: &str = "extends";
+
+/// The key of the labels, which are either a list or a table holding the list.
+static LABELS: &str = "labels";
+
+/// The fields which name a member of a list of tables, to merge members by.
+static MEMBER_NAMES: [&str; 4] = ["name", "username", "slug", "url"];
+
+impl Resolve {
+    pub fn handle<F: FileReader>(self, reader: F) -> Result<()> {
+        let (settings, _) = reader.read_settings(&self.settings_file)?;
+        let format = reader
+            .file_format()
+            .unwrap_or_else(|| FileFormat::from_path(&self.settings_file));
+        print!("{}", Export::to_string(&settings, format)?);
+        Ok(())
+    }
+}
+
+/// Read a settings file and every file it extends into settings, along with
+/// the lines of the keys in the file itself.
+pub fn read_settings<F: FileReader + ?Sized, P: AsRef<Path>>(
+    reader: &F,
+    path: P,
+) -> Result<(GramSettings, Spans)> {
+    let path = path.as_ref();
+    let (settings, spans) = resolve(reader, path)?;
+    let settings = settings.try_into::<GramSettings>().map_err(|e| {
+        // Merged settings have no positions, but the file itself can say
+        // where the problem is if it is in that file.
+        let format = reader
+            .file_format()
+            .unwrap_or_else(|| FileFormat::from_path(path));
+        let located = reader
+            .read_to_string(path)
+            .ok()
+            .and_then(|settings_str| format.parse::<GramSettings>(&settings_str).err());
+        match located {
+            Some(located) => anyhow!("Failed to read settings {}: {}", path.display(), located),
+            None => anyhow!("Failed to read settings {}: {}", path.display(), e),
+        }
+    })?;
+    Ok((settings, spans))
+}
+
+/// Read a settings file and every file it extends, merged into one value,
+/// along with the lines of the keys in the file itself.
+pub fn resolve<F: FileReader + ?Sized, P: AsRef<Path>>(
+    reader: &F,
+    path: P,
+) -> Result<(Value, Spans)> {
+    resolve_chain(reader, &normalize(path.as_ref()), &mut Vec::new())
+}
+
+/// `chain` holds the files currently being resolved, to catch cycles. Only
+/// the file at the start of the chain has its lines found.
+fn resolve_chain<F: FileReader + ?Sized>(
+    reader: &F,
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+) -> Result<(Value, Spans)> {
+    if chain.iter().any(|extended| extended == path) {
+        let cycle = chain
+            .iter()
+            .chain(std::iter::once(&path.to_owned()))
+            .map(|path| path.display().to_string())
+            .collect::<Vec<String>>();
+        return Err(anyhow!(
+            "Settings files extend each other in a cycle: {}",
+            cycle.join(" -> ")
+        ));
+    }
+    let settings_str = match reader.read_to_string(path) {
+        Ok(settings_str) => settings_str,
+        Err(e) if chain.is_empty() => return Err(e.into()),
+        Err(e) => {
+            return Err(anyhow!(
+                "Failed to read extended settings {}: {}",
+                path.display(),
+                e
+            ))
+        }
+    };
+    // Only the files given on the command line can have an explicit format.
+    let format = match chain.as_slice() {
+        [] => reader.file_format(),
+        _ => None,
+    };
+    let format = format.unwrap_or_else(|| FileFormat::from_path(path));
+    let mut settings = format
+        .parse::<Value>(&settings_str)
+        .map_err(|e| anyhow!("Failed to read settings {}: {}", path.display(), e))?;
+    let spans = match chain.is_empty() {
+        true => Spans::parse_as(&settings_str, format),
+        false => Spans::default(),
+    };
+    let extends = match settings.as_table_mut().and_then(|t| t.remove(EXTENDS)) {
+        Some(Value::Array(extends)) => extends,
+        Some(_) => {
+            return Err(anyhow!(
+                "[{}] in {} must be a list of paths",
+                EXTENDS,
+                path.display()
+            ))
+        }
+        None => Vec::new(),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    chain.push(path.to_owned());
+    let mut merged = Value::Table(Table::new());
+    for extended in extends {
+        let extended = extended.as_str().ok_or_else(|| {
+            anyhow!(
+                "[{}] in {} must be a list of paths",
+                EXTENDS,
+                path.display()
+            )
+        })?;
+        let (extended, _) = resolve_chain(reader, &normalize(&dir.join(extended)), chain)?;
+        merge(&mut merged, extended);
+    }
+    chain.pop();
+    labels_table(&mut settings);
+    merge(&mut merged, settings);
+    Ok((merged, spans))
+}
+
+/// Turn a list of labels into a table holding the list as `items`, so one
+/// file can set `prune` on the labels another file lists.
+fn labels_table(settings: &mut Value) {
+    let labels = settings.as_table_mut().and_then(|t| t.get_mut(LABELS));
+    if let Some(labels) = labels.filter(|labels| labels.is_array()) {
+        let items = std::mem::replace(labels, Value::Table(Table::new()));
+        let mut table = Table::new();
+        table.insert("items".to_owned(), items);
+        *labels = Value::Table(table);
+    }
+}
+
+/// Merge `layer` into `base`, values in `layer` win.
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Table(base), Value::Table(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(layer))
+            if member_names(base).is_some() && member_names(&layer).is_some() =>
+        {
+            for member in layer {
+                let name = member_name(&member).map(str::to_owned);
+                match base
+                    .iter_mut()
+                    .find(|existing| member_name(existing) == name.as_deref())
+                {
+                    Some(existing) => merge(existing, member),
+                    None => base.push(member),
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// The names of the members of a list, if every member is a named table.
+fn member_names(values: &[Value]) -> Option<Vec<&str>> {
+    values.iter().map(member_name).collect()
+}
+
+/// The name of a member of a list, if it is a named table.
+pub(crate) fn member_name(value: &Value) -> Option<&str> {
+    let table = value.as_table()?;
+    MEMBER_NAMES
+        .iter()
+        .find_map(|field| table.get(*field).and_then(Value::as_str))
+}
+
+/// Remove `.` and `..` from a path without touching the filesystem, so the
+/// same file reached by different relative paths is seen as one file.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use crate::commands::settings::{
+        FileReader, GramSettings, Label, Labels, Options, ProtectedBranch,
+    };
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    struct FilesReader {
+        files: HashMap<&'static str, &'static str>,
+    }
+
+    impl FileReader for FilesReader {
+        fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, std::io::Error> {
+            let path = path.as_ref().to_str().unwrap();
+            self.files
+                .get(path)
+                .map(|contents| (*contents).to_owned())
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+        }
+    }
+
+    fn reader(files: Vec<(&'static str, &'static str)>) -> FilesReader {
+        FilesReader {
+            files: files.into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn read_settings_should_merge_extended_files_in_order_with_later_files_winning() {
+        // arrange
+        let reader = reader(vec![
+            (
+                "base.toml",
+                r#"
+description = "base"
+[options]
+allow-squash-merge = false
+allow-merge-commit = false
+[[protected]]
+name = "master"
+required-approving-review-count = 1
+dismiss-stale-reviews = true
+[[labels]]
+name = "bug"
+color = "d73a4a"
+"#,
+            ),
+            (
+                "repo/profiles/service.toml",
+                r#"
+[options]
+allow-merge-commit = true
+[[protected]]
+name = "master"
+required-approving-review-count = 2
+[[protected]]
+name = "release"
+"#,
+            ),
+            (
+                "repo/settings.toml",
+                r#"
+extends = ["../base.toml", "profiles/service.toml"]
+description = "service"
+[[labels]]
+name = "docs"
+"#,
+            ),
+        ]);
+
+        // act
+        let (settings, spans) = reader
+            .read_settings(&PathBuf::from("repo/settings.toml"))
+            .unwrap();
+
+        // assert
+        let branch = |name: &str| ProtectedBranch {
+            name: name.to_owned(),
+            ..ProtectedBranch::default()
+        };
+        let label = |name: &str, color: Option<&str>| Label {
+            name: name.to_owned(),
+            color: color.map(str::to_owned),
+            description: None,
+        };
+        assert_eq!(
+            GramSettings {
+                description: Some("service".to_owned()),
+                options: Some(Options {
+                    allow_squash_merge: Some(false),
+                    allow_merge_commit: Some(true),
+                    ..Options::default()
+                }),
+                protected: Some(vec![
+                    ProtectedBranch {
+                        required_approving_review_count: Some(2),
+                        dismiss_stale_reviews: Some(true),
+                        ..branch("master")
+                    },
+                    branch("release"),
+                ]),
+                labels: Some(vec![label("bug", Some("d73a4a")), label("docs", None)].into()),
+                ..GramSettings::default()
+            },
+            settings
+        );
+        // Lines are only known in the file itself.
+        assert_eq!(Some(3), spans.line("description"));
+        assert_eq!(Some(5), spans.line("labels.docs"));
+        assert_eq!(None, spans.line("protected.release"));
+    }
+
+    #[test]
+    fn read_settings_should_merge_a_list_of_labels_into_a_table_of_labels() {
+        // arrange
+        let reader = reader(vec![
+            (
+                "base.toml",
+                "[labels]\nprune = true\n\n[[labels.items]]\nname = \"bug\"\n",
+            ),
+            (
+                "settings.toml",
+                "extends = [\"base.toml\"]\n\n[[labels]]\nname = \"docs\"\n",
+            ),
+        ]);
+
+        // act
+        let settings = reader.read_settings(&PathBuf::from("settings.toml"));
+
+        // assert
+        let label = |name: &str| Label {
+            name: name.to_owned(),
+            ..Label::default()
+        };
+        assert_eq!(
+            Some(Labels {
+                prune: Some(true),
+                items: vec![label("bug"), label("docs")],
+            }),
+            settings.unwrap().0.labels
+        );
+    }
+
+    #[test]
+    fn read_settings_should_error_if_files_extend_each_other_in_a_cycle() {
+        // arrange
+        let reader = reader(vec![
+            ("a.toml", "extends = [\"dir/b.toml\"]"),
+            ("dir/b.toml", "extends = [\"../a.toml\"]"),
+        ]);
+
+        // act
+        let settings = reader.read_settings(&PathBuf::from("./a.toml"));
+
+        // assert
+        assert_eq!(
+            "Settings files extend each other in a cycle: a.toml -> dir/b.toml -> a.toml",
+            format!("{}", settings.err().unwrap())
+        );
+    }
+
+    #[test]
+    fn read_settings_should_name_an_extended_file_which_cannot_be_read() {
+        // arrange
+        let reader = reader(vec![("a.toml", "extends = [\"missing.toml\"]")]);
+
+        // act
+        let settings = reader.read_settings(&PathBuf::from("a.toml"));
+
+        // assert
+        assert!(format!("{}", settings.err().unwrap())
+            .starts_with("Failed to read extended settings missing.toml:"));
+    }
+
+    #[test]
+    fn read_settings_should_read_each_file_in_the_format_of_its_extension() {
+        // arrange
+        let reader = reader(vec![
+            (
+                "base.toml",
+                "description = \"base\"\n\n[labels]\nprune = true\n",
+            ),
+            (
+                "settings.yaml",
+                "extends:\n  - base.toml\ndescription: service\n",
+            ),
+        ]);
+
+        // act
+        let settings = reader.read_settings(&PathBuf::from("settings.yaml"));
+
+        // assert
+        assert_eq!(
+            GramSettings {
+                description: Some("service".to_owned()),
+                labels: Some(Labels {
+                    prune: Some(true),
+                    items: Vec::new(),
+                }),
+                ..GramSettings::default()
+            },
+            settings.unwrap().0
+        );
+    }
+
+    #[test]
+    fn read_settings_should_name_the_file_and_position_of_an_invalid_value() {
+        // arrange
+        let reader = reader(vec![(
+            "settings.json",
+            "{\n  \"options\": {\"allow-squash-merge\": \"yes\"}\n}",
+        )]);
+
+        // act
+        let settings = reader.read_settings(&PathBuf::from("settings.json"));
+
+        // assert
+        assert_eq!(
+            "Failed to read settings settings.json: invalid type: string \"yes\", \
+             expected a boolean at line 2 column 41",
+            format!("{}", settings.err().unwrap())
+        );
+    }
+
+    #[test]
+    fn read_settings_should_error_on_an_unknown_key_in_a_file_which_extends_another() {
+        // arrange
+        let reader = reader(vec![
+            ("base.toml", "description = \"base\"\n"),
+            (
+                "settings.toml",
+                "extends = [\"base.toml\"]\n[options]\nallow-sqaush-merge = false\n",
+            ),
+        ]);
+
+        // act
+        let settings = reader.read_settings(&PathBuf::from("settings.toml"));
+
+        // assert
+        let error = format!("{}", settings.err().unwrap());
+        assert!(
+            error.starts_with(
+                "Failed to read settings settings.toml: unknown field `allow-sqaush-merge`"
+            ),
+            "{}",
+            error
+        );
+        assert!(
+            error.ends_with("for key `options` at line 2 column 1"),
+            "{}",
+            error
+        );
+    }
+}
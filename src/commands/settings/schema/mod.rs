@@ -0,0 +1,113 @@
+use super::GramSettings;
+use anyhow::Result;
+use schemars::{
+    gen::SchemaSettings,
+    schema::{ObjectValidation, RootSchema, Schema as JsonSchema, SchemaObject},
+};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Print a JSON Schema of the settings toml file format.
+///
+/// Editors can use it to complete and check settings files, e.g. with
+/// Even Better TOML by adding `#:schema ./settings.schema.json` to the top
+/// of a settings file.
+#[derive(Debug, StructOpt)]
+pub struct Schema {
+    /// Path to write the schema to. Printed to stdout if not given.
+    #[structopt(long)]
+    pub out: Option<PathBuf>,
+}
+
+impl Schema {
+    pub fn handle(self) -> Result<()> {
+        let schema_str = serde_json::to_string_pretty(&Schema::schema())? + "\n";
+        match &self.out {
+            Some(out) => fs::write(out, schema_str)?,
+            None => print!("{}", schema_str),
+        }
+        Ok(())
+    }
+
+    /// The schema of a settings file, descriptions come from the doc comments
+    /// on the settings types.
+    pub(crate) fn schema() -> RootSchema {
+        // Toml has no null, unset settings are left out instead.
+        let mut schema = SchemaSettings::draft07()
+            .with(|settings| settings.option_add_null_type = false)
+            .into_generator()
+            .into_root_schema_for::<GramSettings>();
+        // Serde can't say a secret is read from exactly one place.
+        if let Some(JsonSchema::Object(secret)) = schema.definitions.get_mut("Secret") {
+            secret.subschemas().one_of = Some(vec![required("env"), required("file")]);
+        }
+        schema
+    }
+}
+
+/// A schema which only requires a key to be set.
+fn required(name: &str) -> JsonSchema {
+    JsonSchema::Object(SchemaObject {
+        object: Some(Box::new(ObjectValidation {
+            required: std::iter::once(name.to_owned()).collect(),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::Schema;
+    use serde_json::json;
+
+    #[test]
+    fn schema_should_use_the_settings_file_keys_and_doc_comments() {
+        // act
+        let schema = serde_json::to_value(Schema::schema()).unwrap();
+
+        // assert
+        let properties = &schema["properties"];
+        assert_eq!(false, schema["additionalProperties"]);
+        assert_eq!(json!("array"), properties["extends"]["type"]);
+        assert_eq!(
+            json!("The short description of the repository."),
+            properties["description"]["description"]
+        );
+        let labels = &schema["definitions"]["Labels"]["anyOf"];
+        assert_eq!(json!("array"), labels[0]["type"]);
+        assert_eq!(
+            json!("boolean"),
+            schema["definitions"]["LabelsTable"]["properties"]["prune"]["type"]
+        );
+        let options = &schema["definitions"]["Options"]["properties"];
+        assert_eq!(
+            json!("Whether pull requests can be squash merged."),
+            options["allow-squash-merge"]["description"]
+        );
+        let branch = &schema["definitions"]["ProtectedBranch"];
+        assert_eq!(json!(["name"]), branch["required"]);
+        assert_eq!(
+            json!(6.0),
+            branch["properties"]["required-approving-review-count"]["maximum"]
+        );
+        assert_eq!(
+            json!(["pull", "triage", "push", "maintain", "admin"]),
+            schema["definitions"]["Permission"]["enum"]
+        );
+    }
+
+    #[test]
+    fn schema_should_match_the_published_schema() {
+        // act
+        let schema = serde_json::to_string_pretty(&Schema::schema()).unwrap() + "\n";
+
+        // assert
+        assert_eq!(
+            include_str!("../../../../settings.schema.json"),
+            schema,
+            "run `gram settings schema --out settings.schema.json` to update it"
+        );
+    }
+}
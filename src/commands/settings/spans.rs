@@ -0,0 +1,423 @@
+//! Line numbers of the keys in a settings file.
+use super::format::FileFormat;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use toml::Spanned;
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+/// The line each settings key is defined on, using the same keys as a diff.
+///
+/// Members of arrays of tables, e.g. `[[labels]]`, are keyed by their name
+/// so `labels.bug.color` is the `color` line of the label named `bug`. The
+/// members of `[[labels.items]]` are keyed the same way.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Spans(HashMap<String, usize>);
+
+/// The fields which name a member of an array of tables.
+static MEMBER_NAMES: [&str; 4] = ["name", "username", "slug", "url"];
+
+/// The key of the list in a table which is keyed as the table itself, e.g.
+/// `labels.items`.
+static ITEMS: &str = "items";
+
+impl Spans {
+    /// Find the line of every key in a settings string of the given format,
+    /// using the positions the parser for that format gives.
+    ///
+    /// Json is read as yaml, which it is a subset of. A string which does not
+    /// parse has no lines.
+    pub fn parse_as(settings: &str, format: FileFormat) -> Self {
+        match format {
+            FileFormat::Toml => Spans::parse(settings),
+            FileFormat::Yaml | FileFormat::Json => Spans::parse_yaml(settings),
+        }
+    }
+
+    /// Find the line of every key in a settings toml string.
+    pub fn parse(settings: &str) -> Self {
+        let node = match toml::from_str::<Node>(settings) {
+            Ok(node) => node,
+            Err(_) => return Spans::default(),
+        };
+        let mut spans = Spans::default();
+        let line = |offset: usize| settings[..offset].matches('\n').count() + 1;
+        spans.insert_node("", &node, &line);
+        spans
+    }
+
+    /// Find the line of every key in a settings yaml or json string.
+    fn parse_yaml(settings: &str) -> Self {
+        let mut events = Events::default();
+        if Parser::new(settings.chars())
+            .load(&mut events, false)
+            .is_err()
+        {
+            return Spans::default();
+        }
+        let mut events = events.0.into_iter().peekable();
+        let node = match events.find(|(event, _)| *event == Event::DocumentStart) {
+            Some(_) => yaml_node(&mut events),
+            None => None,
+        };
+        let mut spans = Spans::default();
+        if let Some(node) = node {
+            spans.insert_node("", &node, &|line| line);
+        }
+        spans
+    }
+
+    /// The line of a key, or of the closest parent key that has one.
+    pub fn line(&self, key: &str) -> Option<usize> {
+        let mut key = key;
+        loop {
+            if let Some(line) = self.0.get(key) {
+                return Some(*line);
+            }
+            key = &key[..key.rfind('.')?];
+        }
+    }
+
+    /// Keep the earliest line of a key, a table can be added to after it starts.
+    fn insert(&mut self, key: String, line: usize) {
+        let earliest = self.0.entry(key).or_insert(line);
+        *earliest = line.min(*earliest);
+    }
+
+    /// Add the lines of the keys within a node, `line` turns a position from
+    /// the parser into a line.
+    fn insert_node(&mut self, key: &str, node: &Node, line: &dyn Fn(usize) -> usize) {
+        match node {
+            Node::Table(fields) => {
+                for (field, at, value) in fields {
+                    let field = match (key, field.as_str()) {
+                        ("", field) => field.to_owned(),
+                        (key, field) if field == ITEMS && value.is_array() => key.to_owned(),
+                        (key, field) => format!("{}.{}", key, field),
+                    };
+                    self.insert(field.clone(), line(*at));
+                    self.insert_node(&field, value, line);
+                }
+            }
+            Node::Array(members) => {
+                for (at, member) in members {
+                    let (name, name_at) = match member.name() {
+                        Some(name) => name,
+                        None => continue,
+                    };
+                    // A member defined again is at its last definition.
+                    let member_key = format!("{}.{}", key, name);
+                    self.0
+                        .insert(member_key.clone(), line(at.unwrap_or(name_at)));
+                    self.insert_node(&member_key, member, line);
+                }
+            }
+            Node::Scalar(_) => (),
+        }
+    }
+}
+
+/// A parsed settings file, keeping the position of each key and list member.
+enum Node {
+    /// Each key with its position and value.
+    Table(Vec<(String, usize, Node)>),
+    /// Each member with its position, if the parser gives one.
+    Array(Vec<(Option<usize>, Node)>),
+    /// A string, which may name a member, or any other value.
+    Scalar(Option<String>),
+}
+
+impl Node {
+    fn is_array(&self) -> bool {
+        matches!(self, Node::Array(_))
+    }
+
+    /// The name of a member of a list and its position.
+    fn name(&self) -> Option<(&str, usize)> {
+        let fields = match self {
+            Node::Table(fields) => fields,
+            _ => return None,
+        };
+        fields.iter().find_map(|(field, at, value)| match value {
+            Node::Scalar(Some(name)) if MEMBER_NAMES.contains(&field.as_str()) => {
+                Some((name.as_str(), *at))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Toml gives the byte offset of each key, and of each member of an inline
+/// array. Members of arrays of tables have no position.
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a settings value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let mut fields = Vec::new();
+        while let Some(key) = map.next_key::<Spanned<String>>()? {
+            let start = key.start();
+            fields.push((key.into_inner(), start, map.next_value::<Node>()?));
+        }
+        Ok(Node::Table(fields))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut members = Vec::new();
+        while let Some(member) = seq.next_element::<Spanned<Node>>()? {
+            // Tables from `[[...]]` headers are given an empty span.
+            let at = match (member.start(), member.end()) {
+                (0, 0) => None,
+                (start, _) => Some(start),
+            };
+            members.push((at, member.into_inner()));
+        }
+        Ok(Node::Array(members))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Node, E> {
+        Ok(Node::Scalar(Some(value.to_owned())))
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Node, E> {
+        Ok(Node::Scalar(None))
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Node, E> {
+        Ok(Node::Scalar(None))
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Node, E> {
+        Ok(Node::Scalar(None))
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Node, E> {
+        Ok(Node::Scalar(None))
+    }
+}
+
+type YamlEvents = Peekable<std::vec::IntoIter<(Event, Marker)>>;
+
+/// The events of a yaml document, each with where it starts.
+#[derive(Default)]
+struct Events(Vec<(Event, Marker)>);
+
+impl MarkedEventReceiver for Events {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        self.0.push((event, mark));
+    }
+}
+
+/// Build the node starting at the next event, positioned by line.
+///
+/// Only keys which are strings are kept, yaml allows any value as a key but
+/// settings never use them.
+fn yaml_node(events: &mut YamlEvents) -> Option<Node> {
+    let node = match events.next()?.0 {
+        Event::Scalar(value, ..) => Node::Scalar(Some(value)),
+        Event::MappingStart(_) => {
+            let mut fields = Vec::new();
+            loop {
+                match events.next()? {
+                    (Event::MappingEnd, _) => break,
+                    (Event::Scalar(key, ..), mark) => {
+                        fields.push((key, mark.line(), yaml_node(events)?));
+                    }
+                    _ => return None,
+                }
+            }
+            Node::Table(fields)
+        }
+        Event::SequenceStart(_) => {
+            let mut members = Vec::new();
+            loop {
+                let line = match events.peek()? {
+                    (Event::SequenceEnd, _) => break,
+                    (_, mark) => mark.line(),
+                };
+                members.push((Some(line), yaml_node(events)?));
+            }
+            events.next();
+            Node::Array(members)
+        }
+        _ => Node::Scalar(None),
+    };
+    Some(node)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Spans;
+    use crate::commands::settings::format::FileFormat;
+
+    static SETTINGS: &str = r#"description = "gram"
+
+[options]
+allow-squash-merge = false
+
+[[protected]]
+required-status-checks = [
+    "a=b",
+]
+name = "master"
+
+[[labels]]
+name = "bug"
+color = "d73a4a"
+"#;
+
+    #[test]
+    fn line_should_find_top_level_and_table_keys() {
+        // act
+        let spans = Spans::parse(SETTINGS);
+
+        // assert
+        assert_eq!(Some(1), spans.line("description"));
+        assert_eq!(Some(3), spans.line("options"));
+        assert_eq!(Some(4), spans.line("options.allow-squash-merge"));
+    }
+
+    #[test]
+    fn line_should_key_array_members_by_name_wherever_the_name_is() {
+        // act
+        let spans = Spans::parse(SETTINGS);
+
+        // assert
+        assert_eq!(Some(6), spans.line("protected"));
+        assert_eq!(Some(10), spans.line("protected.master"));
+        assert_eq!(
+            Some(7),
+            spans.line("protected.master.required-status-checks")
+        );
+        assert_eq!(Some(13), spans.line("labels.bug"));
+        assert_eq!(Some(14), spans.line("labels.bug.color"));
+    }
+
+    #[test]
+    fn line_should_key_label_items_as_labels() {
+        // act
+        let spans = Spans::parse(
+            "[labels]\nprune = true\n\n[[labels.items]]\nname = \"bug\"\ncolor = \"d73a4a\"\n",
+        );
+
+        // assert
+        assert_eq!(Some(1), spans.line("labels"));
+        assert_eq!(Some(2), spans.line("labels.prune"));
+        assert_eq!(Some(5), spans.line("labels.bug"));
+        assert_eq!(Some(6), spans.line("labels.bug.color"));
+    }
+
+    #[test]
+    fn line_should_find_dotted_keys_and_keys_in_inline_tables() {
+        // act
+        let spans = Spans::parse(
+            r#"description = "gram"
+options.allow-squash-merge = false
+actions = { enabled = true, variables = { REGION = "eu" } }
+labels = [{ name = "bug", color = "d73a4a" }]
+protected = [
+    { name = "master", required-status-checks = ["ci"] },
+    { name = "release" },
+]
+
+[[environments]]
+name = "production"
+variables = { REGION = "eu" }
+"#,
+        );
+
+        // assert
+        assert_eq!(Some(2), spans.line("options"));
+        assert_eq!(Some(2), spans.line("options.allow-squash-merge"));
+        assert_eq!(Some(3), spans.line("actions.variables.REGION"));
+        assert_eq!(Some(4), spans.line("labels.bug.color"));
+        assert_eq!(
+            Some(6),
+            spans.line("protected.master.required-status-checks")
+        );
+        assert_eq!(Some(7), spans.line("protected.release"));
+        assert_eq!(
+            Some(12),
+            spans.line("environments.production.variables.REGION")
+        );
+    }
+
+    #[test]
+    fn parse_as_should_find_yaml_keys_and_name_list_members() {
+        // act
+        let spans = Spans::parse_as(
+            "description: gram\noptions:\n  allow-squash-merge: false\nlabels:\n- name: bug\n  \
+             color: d73a4a\ntopics:\n  - cli\n",
+            FileFormat::Yaml,
+        );
+
+        // assert
+        assert_eq!(Some(1), spans.line("description"));
+        assert_eq!(Some(3), spans.line("options.allow-squash-merge"));
+        assert_eq!(Some(5), spans.line("labels.bug"));
+        assert_eq!(Some(6), spans.line("labels.bug.color"));
+        assert_eq!(Some(7), spans.line("topics"));
+        assert_eq!(None, spans.line("color"));
+    }
+
+    #[test]
+    fn parse_as_should_find_json_keys() {
+        // act
+        let spans = Spans::parse_as(
+            r#"{
+  "description": "gram",
+  "options": {
+    "allow-squash-merge": false
+  },
+  "labels": [
+    {
+      "name": "bug"
+    }
+  ]
+}
+"#,
+            FileFormat::Json,
+        );
+
+        // assert
+        assert_eq!(Some(2), spans.line("description"));
+        assert_eq!(Some(4), spans.line("options.allow-squash-merge"));
+        assert_eq!(Some(7), spans.line("labels.bug"));
+        assert_eq!(None, spans.line("name"));
+    }
+
+    #[test]
+    fn parse_as_should_have_no_lines_for_settings_which_do_not_parse() {
+        // act
+        let spans = Spans::parse_as("description = ", FileFormat::Toml);
+
+        // assert
+        assert_eq!(Spans::default(), spans);
+    }
+
+    #[test]
+    fn line_should_fall_back_to_the_closest_parent_key() {
+        // act
+        let spans = Spans::parse(SETTINGS);
+
+        // assert
+        assert_eq!(Some(13), spans.line("labels.bug.description"));
+        assert_eq!(Some(12), spans.line("labels.wontfix"));
+        assert_eq!(None, spans.line("collaborators.alice"));
+    }
+}
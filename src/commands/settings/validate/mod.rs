@@ -0,0 +1,833 @@
+use super::{
+    format::FileFormat,
+    remote::DEFAULT_SETTINGS_FILE,
+    resolve::{member_name, normalize},
+    spans::Spans,
+    FileReader, Schema,
+};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+use toml::{value::Table, Value};
+
+/// Check settings files without calling github.
+///
+/// Unknown keys, values of the wrong type and values github would reject
+/// are reported with the line and column they are on. Settings are checked
+/// against the same schema `gram settings schema` prints. No token is
+/// needed, so this can run in a pre-commit hook.
+#[derive(Debug, StructOpt)]
+pub struct Validate {
+    /// Paths to the settings files, defaults to .github/gram/settings.toml.
+    #[structopt(name = "files")]
+    pub settings_files: Vec<PathBuf>,
+}
+
+/// A problem with a settings file.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    pub file: PathBuf,
+    /// The line and column the problem is on, if it is known.
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file.display(),
+                line,
+                column,
+                self.message
+            ),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+impl Validate {
+    pub fn handle<F: FileReader>(self, reader: F) -> Result<()> {
+        let files = match self.settings_files.as_slice() {
+            [] => vec![PathBuf::from(DEFAULT_SETTINGS_FILE)],
+            files => files.to_vec(),
+        };
+        let problems = files
+            .iter()
+            .flat_map(|file| Validate::validate(&reader, file))
+            .map(|problem| problem.to_string())
+            .collect::<Vec<String>>();
+        match problems.as_slice() {
+            [] => Ok(()),
+            [..] => Err(anyhow!(
+                "Found {} problem(s) in settings!\n{}",
+                problems.len(),
+                problems.join("\n")
+            )),
+        }
+    }
+
+    /// Find every problem with a settings file and the files it extends.
+    pub fn validate<F: FileReader>(reader: &F, file: &Path) -> Vec<Problem> {
+        let mut checked = vec![normalize(file)];
+        let mut problems = Validate::check_file(reader, file, reader.file_format(), &mut checked);
+        // Problems only seen once files are merged, e.g. extends in a cycle.
+        if problems.is_empty() {
+            if let Err(e) = reader.read_settings(&file.to_owned()) {
+                problems.push(Problem {
+                    file: file.to_owned(),
+                    position: None,
+                    message: e.to_string(),
+                });
+            }
+        }
+        problems
+    }
+
+    /// Find every problem with a settings file, then with each file it
+    /// extends which is not yet in `checked`.
+    fn check_file<F: FileReader>(
+        reader: &F,
+        file: &Path,
+        format: Option<FileFormat>,
+        checked: &mut Vec<PathBuf>,
+    ) -> Vec<Problem> {
+        let problem = |position, message| Problem {
+            file: file.to_owned(),
+            position,
+            message,
+        };
+        let settings_str = match reader.read_to_string(file) {
+            Ok(settings_str) => settings_str,
+            Err(e) => return vec![problem(None, format!("Failed to read settings: {}", e))],
+        };
+        let format = format.unwrap_or_else(|| FileFormat::from_path(file));
+        let settings = match format.parse::<Value>(&settings_str) {
+            Ok(settings) => settings,
+            Err(e) => return vec![problem(e.position, e.message)],
+        };
+        let schema = match serde_json::to_value(Schema::schema()) {
+            Ok(schema) => schema,
+            Err(e) => return vec![problem(None, format!("Failed to build the schema: {}", e))],
+        };
+        let spans = Spans::parse_as(&settings_str, format);
+        let mut errors = Vec::new();
+        check_value(&schema, &schema, &settings, "", &mut errors);
+        let mut problems = errors
+            .into_iter()
+            .map(|(key, message)| problem(position(&settings_str, &spans, &key), message))
+            .collect::<Vec<Problem>>();
+        problems.sort_by_key(|problem| problem.position);
+        // Extended files are relative to this one, and have no explicit format.
+        let dir = file.parent().unwrap_or_else(|| Path::new(""));
+        let extends = settings.get("extends").and_then(Value::as_array);
+        for extended in extends.into_iter().flatten().filter_map(Value::as_str) {
+            let extended = normalize(&dir.join(extended));
+            if !checked.contains(&extended) {
+                checked.push(extended.clone());
+                problems.extend(Validate::check_file(reader, &extended, None, checked));
+            }
+        }
+        problems
+    }
+}
+
+/// The key of a field in a table, as used by [Spans](../spans/struct.Spans.html).
+fn key(prefix: &str, name: &str) -> String {
+    match prefix {
+        "" => name.to_owned(),
+        prefix => format!("{}.{}", prefix, name),
+    }
+}
+
+/// The schema a schema refers to, if it is a reference to a definition.
+///
+/// Properties with a description wrap their reference in `allOf`.
+fn definition<'a>(root: &'a JsonValue, schema: &'a JsonValue) -> &'a JsonValue {
+    let reference = schema["$ref"]
+        .as_str()
+        .or_else(|| schema["allOf"][0]["$ref"].as_str());
+    match reference {
+        Some(reference) => definition(
+            root,
+            &root["definitions"][reference.trim_start_matches("#/definitions/")],
+        ),
+        None => schema,
+    }
+}
+
+/// The strings a value must be one of, empty if it can be any value.
+///
+/// Enums with documented variants have an alternative for each variant.
+fn enum_values(schema: &JsonValue) -> Vec<&str> {
+    let alternatives = match schema["oneOf"].as_array() {
+        Some(alternatives) => alternatives.iter().collect(),
+        None => vec![schema],
+    };
+    alternatives
+        .into_iter()
+        .flat_map(|alternative| alternative["enum"].as_array().into_iter().flatten())
+        .filter_map(JsonValue::as_str)
+        .collect()
+}
+
+/// Whether a value is of the type a schema says, an enum without a type is
+/// one of some strings.
+fn has_type(schema: &JsonValue, value: &Value) -> bool {
+    let kind = match value {
+        Value::String(_) => "string",
+        Value::Integer(_) => "integer",
+        Value::Float(_) => "number",
+        Value::Boolean(_) => "boolean",
+        Value::Datetime(_) => "datetime",
+        Value::Array(_) => "array",
+        Value::Table(_) => "object",
+    };
+    match schema["type"].as_str() {
+        Some(expected) => expected == kind,
+        None if !enum_values(schema).is_empty() => kind == "string",
+        None => true,
+    }
+}
+
+fn check_value(
+    root: &JsonValue,
+    schema: &JsonValue,
+    value: &Value,
+    key: &str,
+    errors: &mut Vec<(String, String)>,
+) {
+    let schema = definition(root, schema);
+    // A value which can be one of several types is checked as the first it is.
+    if let Some(alternatives) = schema["anyOf"].as_array() {
+        let alternative = alternatives
+            .iter()
+            .map(|alternative| definition(root, alternative))
+            .find(|alternative| has_type(alternative, value));
+        match alternative {
+            Some(alternative) => check_value(root, alternative, value, key, errors),
+            None => errors.push((key.to_owned(), mismatch(root, schema, value, key))),
+        }
+        return;
+    }
+    let valid = has_type(schema, value)
+        && match value {
+            Value::String(string) => {
+                let values = enum_values(schema);
+                let pattern = schema["pattern"].as_str();
+                (values.is_empty() || values.contains(&string.as_str()))
+                    && pattern.is_none_or(|pattern| {
+                        Regex::new(pattern).is_ok_and(|pattern| pattern.is_match(string))
+                    })
+            }
+            Value::Integer(number) => {
+                let number = *number as f64;
+                schema["minimum"].as_f64().is_none_or(|min| min <= number)
+                    && schema["maximum"].as_f64().is_none_or(|max| number <= max)
+            }
+            Value::Array(members) => {
+                check_members(root, &schema["items"], members, key, errors);
+                true
+            }
+            Value::Table(table) => {
+                check_table(root, schema, table, key, errors);
+                true
+            }
+            _ => true,
+        };
+    if !valid {
+        errors.push((key.to_owned(), mismatch(root, schema, value, key)));
+    }
+}
+
+fn check_table(
+    root: &JsonValue,
+    schema: &JsonValue,
+    table: &Table,
+    prefix: &str,
+    errors: &mut Vec<(String, String)>,
+) {
+    let properties = &schema["properties"];
+    for (name, value) in table {
+        let key = key(prefix, name);
+        match (
+            properties.get(name.as_str()),
+            &schema["additionalProperties"],
+        ) {
+            // Labels can hold their list as `items`, its members are keyed as
+            // the members of a list are, the same as in a diff.
+            (Some(property), _) if name == "items" => {
+                check_value(root, property, value, prefix, errors)
+            }
+            (Some(property), _) => check_value(root, property, value, &key, errors),
+            (None, JsonValue::Bool(false)) => {
+                errors.push((key.clone(), unknown_key(&key, name, properties)))
+            }
+            (None, additional) => check_value(root, additional, value, &key, errors),
+        }
+    }
+    let required = schema["required"].as_array().into_iter().flatten();
+    for name in required.filter_map(JsonValue::as_str) {
+        if !table.contains_key(name) {
+            let message = format!("[{}]: missing required key [{}]", prefix, name);
+            errors.push((prefix.to_owned(), message));
+        }
+    }
+    // Keys of which exactly one must be set, e.g. where a secret is read from.
+    if let Some(alternatives) = schema["oneOf"].as_array() {
+        let names = alternatives
+            .iter()
+            .flat_map(|alternative| alternative["required"].as_array().into_iter().flatten())
+            .filter_map(JsonValue::as_str)
+            .collect::<Vec<&str>>();
+        if names
+            .iter()
+            .filter(|name| table.contains_key(**name))
+            .count()
+            != 1
+        {
+            let message = format!("[{}]: expected exactly one of {}", prefix, or(&names));
+            errors.push((prefix.to_owned(), message));
+        }
+    }
+}
+
+/// Check each member of a list, keying tables by their name so problems
+/// point at the right member.
+fn check_members(
+    root: &JsonValue,
+    items: &JsonValue,
+    members: &[Value],
+    key: &str,
+    errors: &mut Vec<(String, String)>,
+) {
+    let mut names = HashSet::new();
+    for member in members {
+        let name = member_name(member);
+        let member_key = match name {
+            Some(name) => format!("{}.{}", key, name),
+            None => key.to_owned(),
+        };
+        check_value(root, items, member, &member_key, errors);
+        if let Some(name) = name {
+            if !names.insert(name) {
+                let message = format!("[{}]: [{}] is defined more than once", key, name);
+                errors.push((member_key, message));
+            }
+        }
+    }
+}
+
+/// A value which is not what its schema allows.
+fn mismatch(root: &JsonValue, schema: &JsonValue, value: &Value, key: &str) -> String {
+    let got = match value {
+        Value::String(value) => format!("[{}]", value),
+        Value::Integer(value) => format!("[{}]", value),
+        value => format!("a {}", value.type_str()),
+    };
+    format!(
+        "[{}]: expected {}, got {}",
+        key,
+        describe(root, schema),
+        got
+    )
+}
+
+/// What a schema allows, in words.
+fn describe(root: &JsonValue, schema: &JsonValue) -> String {
+    let schema = definition(root, schema);
+    if let Some(alternatives) = schema["anyOf"].as_array() {
+        let kinds = alternatives
+            .iter()
+            .map(|alternative| describe(root, alternative))
+            .collect::<Vec<String>>();
+        return kinds.join(" or ");
+    }
+    let values = enum_values(schema);
+    match (values.as_slice(), schema["type"].as_str()) {
+        ([value], _) => (*value).to_owned(),
+        ([_, ..], _) => format!("one of {}", or(&values)),
+        (_, Some("boolean")) => "true or false".to_owned(),
+        (_, Some("string")) => match schema["pattern"].as_str() {
+            Some(pattern) => format!("a string matching {}", pattern),
+            None => "a string".to_owned(),
+        },
+        (_, Some("integer")) => match (schema["minimum"].as_f64(), schema["maximum"].as_f64()) {
+            (Some(min), Some(max)) => format!("a whole number from {} to {}", min, max),
+            _ => "a whole number".to_owned(),
+        },
+        (_, Some("array")) => match definition(root, &schema["items"])["type"].as_str() {
+            Some("string") => "a list of strings".to_owned(),
+            Some("object") => "a list of tables".to_owned(),
+            _ => "a list".to_owned(),
+        },
+        (_, Some("object")) => {
+            match definition(root, &schema["additionalProperties"])["type"].as_str() {
+                Some("string") => "a table of strings".to_owned(),
+                Some("object") => "a table of tables".to_owned(),
+                _ => "a table".to_owned(),
+            }
+        }
+        _ => "a value".to_owned(),
+    }
+}
+
+/// Names as `a, b or c`.
+fn or(names: &[&str]) -> String {
+    match names.split_last() {
+        Some((last, [])) => (*last).to_owned(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+/// An unknown key, with the closest known key if it looks like a typo.
+fn unknown_key(key: &str, name: &str, properties: &JsonValue) -> String {
+    let names = properties
+        .as_object()
+        .into_iter()
+        .flat_map(|properties| properties.keys())
+        .map(String::as_str)
+        .collect::<Vec<&str>>();
+    let closest = names
+        .iter()
+        .map(|known| (strsim::jaro_winkler(name, known), known))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    match closest {
+        Some((_, closest)) => format!("[{}]: unknown key, did you mean [{}]?", key, closest),
+        None => format!(
+            "[{}]: unknown key, expected one of {}",
+            key,
+            names.join(", ")
+        ),
+    }
+}
+
+/// The line and column of a key in a settings string.
+fn position(settings: &str, spans: &Spans, key: &str) -> Option<(usize, usize)> {
+    let line = spans.line(key)?;
+    let text = settings.lines().nth(line - 1)?;
+    // A member's header only holds the name of its array, so try each part of the key.
+    let column = key
+        .rsplit('.')
+        .find_map(|name| text.find(name))
+        .or_else(|| text.find(|c: char| !c.is_whitespace()))
+        .unwrap_or(0);
+    Some((line, column + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Problem, Validate};
+    use crate::commands::settings::{
+        export::Export, format::FileFormat, Actions, AllowedActions, Collaborator, ContentType,
+        DeploymentBranches, Environment, FileReader, GramSettings, Label, Labels, Options,
+        Permission, ProtectedBranch, Secret, Team, Visibility, Webhook, WorkflowPermission,
+    };
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    struct StringReader {
+        contents: String,
+    }
+
+    impl FileReader for StringReader {
+        fn read_to_string<P: AsRef<Path>>(&self, _: P) -> Result<String, std::io::Error> {
+            Ok(self.contents.clone())
+        }
+    }
+
+    fn validate(contents: &str) -> Vec<String> {
+        let reader = StringReader {
+            contents: contents.to_owned(),
+        };
+        Validate::validate(&reader, Path::new("settings.toml"))
+            .iter()
+            .map(Problem::to_string)
+            .collect()
+    }
+
+    struct FilesReader {
+        files: HashMap<&'static str, &'static str>,
+    }
+
+    impl FileReader for FilesReader {
+        fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String, std::io::Error> {
+            let path = path.as_ref().to_str().unwrap();
+            self.files
+                .get(path)
+                .map(|contents| (*contents).to_owned())
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+        }
+    }
+
+    #[test]
+    fn validate_should_accept_every_setting() {
+        // arrange
+        let settings = GramSettings {
+            extends: None,
+            description: Some("description".to_owned()),
+            homepage: Some("https://example.com".to_owned()),
+            topics: Some(vec!["cli".to_owned()]),
+            options: Some(Options {
+                visibility: Some(Visibility::Internal),
+                default_branch: Some("main".to_owned()),
+                has_issues: Some(true),
+                has_projects: Some(false),
+                has_wiki: Some(false),
+                has_discussions: Some(true),
+                is_template: Some(false),
+                allow_forking: Some(false),
+                web_commit_signoff_required: Some(true),
+                allow_squash_merge: Some(false),
+                allow_merge_commit: Some(true),
+                allow_rebase_merge: Some(true),
+                allow_auto_merge: Some(true),
+                allow_update_branch: Some(true),
+                delete_branch_on_merge: Some(false),
+                squash_merge_commit_title: Some("PR_TITLE".to_owned()),
+                squash_merge_commit_message: Some("COMMIT_MESSAGES".to_owned()),
+                merge_commit_title: Some("MERGE_MESSAGE".to_owned()),
+                merge_commit_message: Some("PR_BODY".to_owned()),
+            }),
+            protected: Some(vec![ProtectedBranch {
+                name: "master".to_owned(),
+                required_approving_review_count: Some(1),
+                dismiss_stale_reviews: Some(true),
+                require_code_owner_reviews: Some(true),
+                required_status_checks: Some(vec!["build".to_owned()]),
+                strict_status_checks: Some(true),
+                enforce_admins: Some(true),
+                required_linear_history: Some(true),
+                allow_force_pushes: Some(false),
+                allow_deletions: Some(false),
+            }]),
+            labels: Some(Labels {
+                prune: Some(true),
+                items: vec![Label {
+                    name: "bug".to_owned(),
+                    color: Some("d73a4a".to_owned()),
+                    description: Some("Something isn't working".to_owned()),
+                }],
+            }),
+            collaborators: Some(vec![Collaborator {
+                username: "alice".to_owned(),
+                permission: Permission::Maintain,
+            }]),
+            teams: Some(vec![Team {
+                slug: "core".to_owned(),
+                permission: Permission::Push,
+            }]),
+            webhooks: Some(vec![Webhook {
+                url: "https://ci.example.com/hook".to_owned(),
+                content_type: Some(ContentType::Json),
+                events: Some(vec!["push".to_owned()]),
+                active: Some(true),
+                secret_env: Some("CI_HOOK_SECRET".to_owned()),
+                ..Webhook::default()
+            }]),
+            environments: Some(vec![Environment {
+                name: "production".to_owned(),
+                wait_timer: Some(30),
+                reviewers: Some(vec!["alice".to_owned()]),
+                reviewer_teams: Some(vec!["core".to_owned()]),
+                deployment_branches: Some(DeploymentBranches::Custom),
+                branch_patterns: Some(vec!["release/*".to_owned()]),
+                variables: Some(
+                    vec![("REGION".to_owned(), "eu-west-1".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+            }]),
+            secrets: Some(
+                vec![
+                    (
+                        "DEPLOY_TOKEN".to_owned(),
+                        Secret {
+                            env: Some("DEPLOY_TOKEN".to_owned()),
+                            file: None,
+                        },
+                    ),
+                    (
+                        "SIGNING_KEY".to_owned(),
+                        Secret {
+                            env: None,
+                            file: Some(PathBuf::from("keys/signing.pem")),
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            actions: Some(Actions {
+                enabled: Some(true),
+                allowed_actions: Some(AllowedActions::Selected),
+                github_owned_allowed: Some(true),
+                verified_allowed: Some(false),
+                patterns_allowed: Some(vec!["docker/*".to_owned()]),
+                default_workflow_permissions: Some(WorkflowPermission::Read),
+                can_approve_pull_requests: Some(false),
+                variables: Some(
+                    vec![("REGION".to_owned(), "eu-west-1".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+            }),
+        };
+
+        // act
+        let problems = validate(&Export::to_string(&settings, FileFormat::Toml).unwrap());
+
+        // assert
+        assert_eq!(Vec::<String>::new(), problems);
+    }
+
+    #[test]
+    fn validate_should_suggest_the_closest_key_for_a_typo() {
+        // act
+        let problems = validate("[options]\n  allow-sqaush-merge = true\n");
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:2:3: [options.allow-sqaush-merge]: unknown key, \
+                 did you mean [allow-squash-merge]?"
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_list_the_keys_if_none_are_close() {
+        // act
+        let problems =
+            validate("[[teams]]\nslug = \"core\"\npermission = \"push\"\nrole = \"x\"\n");
+
+        // assert
+        assert_eq!(
+            vec!["settings.toml:4:1: [teams.core.role]: unknown key, expected one of permission, slug"],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_check_value_types_and_constraints() {
+        // arrange
+        let settings = r##"description = 1
+
+[[protected]]
+name = "master"
+required-approving-review-count = 7
+
+[[labels]]
+name = "bug"
+color = "red"
+
+[[labels]]
+name = "bug"
+
+[[collaborators]]
+username = "alice"
+permission = "write"
+
+[[teams]]
+permission = "pull"
+"##;
+
+        // act
+        let problems = validate(settings);
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:1:1: [description]: expected a string, got [1]",
+                "settings.toml:5:1: [protected.master.required-approving-review-count]: \
+                 expected a whole number from 0 to 6, got [7]",
+                "settings.toml:9:1: [labels.bug.color]: expected a string matching \
+                 ^#?[0-9a-fA-F]{6}$, got [red]",
+                "settings.toml:12:9: [labels]: [bug] is defined more than once",
+                "settings.toml:16:1: [collaborators.alice.permission]: expected one of \
+                 pull, triage, push, maintain or admin, got [write]",
+                "settings.toml:18:3: [teams]: missing required key [slug]",
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_check_the_files_a_file_extends() {
+        // arrange
+        let reader = FilesReader {
+            files: vec![
+                ("repo/settings.toml", "extends = [\"../base.toml\"]\n"),
+                ("base.toml", "description = \"base\"\ntopcis = [\"cli\"]\n"),
+            ]
+            .into_iter()
+            .collect(),
+        };
+
+        // act
+        let problems = Validate::validate(&reader, Path::new("repo/settings.toml"));
+
+        // assert
+        assert_eq!(
+            vec!["base.toml:2:1: [topcis]: unknown key, did you mean [topics]?"],
+            problems
+                .iter()
+                .map(Problem::to_string)
+                .collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn validate_should_point_at_a_dotted_key() {
+        // act
+        let problems = validate("options.allow-squash-marge = true\n");
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:1:9: [options.allow-squash-marge]: unknown key, \
+                 did you mean [allow-squash-merge]?"
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_check_a_table_of_labels() {
+        // act
+        let problems = validate(
+            "[labels]\nprnue = true\n\n[[labels.items]]\nname = \"bug\"\ncolor = \"red\"\n",
+        );
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:2:1: [labels.prnue]: unknown key, did you mean [prune]?",
+                "settings.toml:6:1: [labels.bug.color]: expected a string matching \
+                 ^#?[0-9a-fA-F]{6}$, got [red]",
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_accept_a_color_with_a_leading_hash() {
+        // act
+        let problems = validate("[[labels]]\nname = \"bug\"\ncolor = \"#D73A4A\"\n");
+
+        // assert
+        assert_eq!(Vec::<String>::new(), problems);
+    }
+
+    #[test]
+    fn validate_should_check_each_secret() {
+        // act
+        let problems = validate(
+            "[secrets]
+DEPLOY_TOKEN = { env = \"DEPLOY_TOKEN\", value = \"x\" }
+",
+        );
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:2:40: [secrets.DEPLOY_TOKEN.value]: unknown key, \
+                 expected one of env, file"
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_check_each_secret_has_exactly_one_source() {
+        // act
+        let problems = validate(
+            "[secrets]
+DEPLOY_TOKEN = { env = \"DEPLOY_TOKEN\", file = \"token\" }
+SIGNING_KEY = {}
+",
+        );
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:2:1: [secrets.DEPLOY_TOKEN]: expected exactly one of env or file",
+                "settings.toml:3:1: [secrets.SIGNING_KEY]: expected exactly one of env or file",
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_check_values_the_schema_limits() {
+        // act
+        let problems = validate(
+            "[options]
+visibility = \"secret\"
+merge-commit-title = \"PR_BODY\"
+
+[[environments]]
+name = \"production\"
+wait-timer = 50000
+",
+        );
+
+        // assert
+        assert_eq!(
+            vec![
+                "settings.toml:2:1: [options.visibility]: expected one of public, private \
+                 or internal, got [secret]",
+                "settings.toml:3:1: [options.merge-commit-title]: expected a string matching \
+                 ^(PR_TITLE|MERGE_MESSAGE)$, got [PR_BODY]",
+                "settings.toml:7:1: [environments.production.wait-timer]: expected a whole \
+                 number from 0 to 43200, got [50000]",
+            ],
+            problems
+        );
+    }
+
+    #[test]
+    fn validate_should_report_where_the_toml_is_invalid() {
+        // act
+        let problems = validate("description = \"a\"\n[options\n");
+
+        // assert
+        assert_eq!(1, problems.len());
+        assert!(
+            problems[0].starts_with("settings.toml:2:"),
+            "{}",
+            problems[0]
+        );
+    }
+
+    #[test]
+    fn handle_should_fail_if_there_are_problems() {
+        // arrange
+        let validate = Validate {
+            settings_files: vec![PathBuf::from("a.toml"), PathBuf::from("b.toml")],
+        };
+        let reader = StringReader {
+            contents: "descripton = \"a\"\n".to_owned(),
+        };
+
+        // act
+        let result = validate.handle(reader);
+
+        // assert
+        assert_eq!(
+            "Found 2 problem(s) in settings!\n\
+             a.toml:1:1: [descripton]: unknown key, did you mean [description]?\n\
+             b.toml:1:1: [descripton]: unknown key, did you mean [description]?",
+            format!("{}", result.err().unwrap())
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Authenticating as a Github App installation instead of with a token.
+use anyhow::{anyhow, Result};
+use openssl::{
+    hash::MessageDigest,
+    pkey::{PKey, Private},
+    sign::Signer,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// How long a signed JWT is valid for, github allows at most ten minutes.
+const JWT_LIFETIME_SECS: u64 = 9 * 60;
+/// How far back to date a JWT, in case the local clock runs ahead of github.
+const JWT_CLOCK_DRIFT_SECS: u64 = 60;
+/// Installation tokens expiring sooner than this are refreshed before use.
+const REFRESH_BEFORE_SECS: u64 = 5 * 60;
+
+/// A Github App which can act as one of its installations.
+pub struct App {
+    pub id: u64,
+    key: PKey<Private>,
+    /// The installation to use, looked up from the owner of each call if not given.
+    pub installation_id: Option<u64>,
+    /// Installations found for each owner.
+    installations: Mutex<HashMap<String, u64>>,
+    /// Unexpired tokens for each installation.
+    tokens: Mutex<HashMap<u64, InstallationToken>>,
+}
+
+impl fmt::Debug for App {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("App")
+            .field("id", &self.id)
+            .field("installation_id", &self.installation_id)
+            .finish()
+    }
+}
+
+#[derive(Serialize)]
+struct Claims {
+    iat: u64,
+    exp: u64,
+    iss: u64,
+}
+
+/// An installation of an app, as returned when looking it up.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Installation {
+    pub id: u64,
+}
+
+/// A token to act as an installation, returned from
+/// `POST /app/installations/{id}/access_tokens`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct InstallationToken {
+    pub token: String,
+    /// When the token expires, e.g. `2016-07-11T22:14:10Z`.
+    pub expires_at: String,
+}
+
+impl App {
+    /// Create an app from its id and a PEM private key downloaded from github.
+    pub fn new(id: u64, private_key: &[u8], installation_id: Option<u64>) -> Result<Self> {
+        let key = PKey::private_key_from_pem(private_key)
+            .map_err(|e| anyhow!("Failed to read the github app private key: {}", e))?;
+        Ok(Self {
+            id,
+            key,
+            installation_id,
+            installations: Mutex::new(HashMap::new()),
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Sign a JWT to authenticate as the app itself, `now` is in seconds
+    /// since the unix epoch.
+    pub fn jwt(&self, now: u64) -> Result<String> {
+        let header = encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+        let claims = encode(&serde_json::to_vec(&Claims {
+            iat: now - JWT_CLOCK_DRIFT_SECS,
+            exp: now + JWT_LIFETIME_SECS,
+            iss: self.id,
+        })?);
+        let message = format!("{}.{}", header, claims);
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(message.as_bytes())?;
+        Ok(format!("{}.{}", message, encode(&signer.sign_to_vec()?)))
+    }
+
+    /// The installation for an owner, if it is known.
+    pub fn installation(&self, owner: &str) -> Option<u64> {
+        self.installation_id
+            .or_else(|| self.installations.lock().unwrap().get(owner).copied())
+    }
+
+    pub fn add_installation(&self, owner: &str, installation_id: u64) {
+        self.installations
+            .lock()
+            .unwrap()
+            .insert(owner.to_owned(), installation_id);
+    }
+
+    /// A cached token for an installation, unless it is about to expire.
+    pub fn token(&self, installation_id: u64, now: u64) -> Option<String> {
+        self.tokens
+            .lock()
+            .unwrap()
+            .get(&installation_id)
+            .filter(|token| {
+                expires_at_secs(&token.expires_at)
+                    .map(|expires| expires > now + REFRESH_BEFORE_SECS)
+                    .unwrap_or(false)
+            })
+            .map(|token| token.token.clone())
+    }
+
+    pub fn add_token(&self, installation_id: u64, token: InstallationToken) {
+        self.tokens.lock().unwrap().insert(installation_id, token);
+    }
+}
+
+fn encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// The path to look up the installation which can access a call's url, i.e.
+/// the installation on the repository or organization the call is for.
+pub fn installation_path(url: &str) -> Option<(String, String)> {
+    let mut segments = url.trim_start_matches('/').split(&['/', '?'][..]);
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some("repos"), Some(owner), Some(repo)) => Some((
+            owner.to_owned(),
+            format!("/repos/{}/{}/installation", owner, repo),
+        )),
+        (Some("orgs"), Some(org), _) => {
+            Some((org.to_owned(), format!("/orgs/{}/installation", org)))
+        }
+        _ => None,
+    }
+}
+
+/// Seconds since the unix epoch of a UTC timestamp like `2016-07-11T22:14:10Z`.
+fn expires_at_secs(timestamp: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| timestamp.get(range)?.parse::<u64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    // Days since the epoch of a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{expires_at_secs, installation_path, App, InstallationToken};
+    use openssl::{hash::MessageDigest, pkey::PKey, rsa::Rsa, sign::Verifier};
+
+    fn private_key() -> Vec<u8> {
+        Rsa::generate(2048).unwrap().private_key_to_pem().unwrap()
+    }
+
+    #[test]
+    fn jwt_should_be_signed_with_the_app_key_and_claim_the_app_id() {
+        // arrange
+        let key = private_key();
+        let app = App::new(1234, &key, None).unwrap();
+
+        // act
+        let jwt = app.jwt(10_000).unwrap();
+
+        // assert
+        let parts = jwt.split('.').collect::<Vec<&str>>();
+        let decode = |part: &str| base64::decode_config(part, base64::URL_SAFE_NO_PAD).unwrap();
+        let claims = serde_json::from_slice::<serde_json::Value>(&decode(parts[1])).unwrap();
+        assert_eq!(
+            serde_json::json!({ "iat": 9_940, "exp": 10_540, "iss": 1234 }),
+            claims
+        );
+        let public = PKey::from_rsa(Rsa::private_key_from_pem(&key).unwrap()).unwrap();
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public).unwrap();
+        verifier
+            .update(format!("{}.{}", parts[0], parts[1]).as_bytes())
+            .unwrap();
+        assert!(verifier.verify(&decode(parts[2])).unwrap());
+    }
+
+    #[test]
+    fn new_should_error_if_the_private_key_is_invalid() {
+        // act
+        let app = App::new(1234, b"not a key", None);
+
+        // assert
+        assert_eq!(
+            "Failed to read the github app private key",
+            format!("{}", app.err().unwrap()).split(':').next().unwrap()
+        );
+    }
+
+    #[test]
+    fn token_should_only_be_reused_until_shortly_before_it_expires() {
+        // arrange
+        let app = App::new(1234, &private_key(), None).unwrap();
+        let expires = expires_at_secs("2020-05-01T12:00:00Z").unwrap();
+        app.add_token(
+            1,
+            InstallationToken {
+                token: "token".to_owned(),
+                expires_at: "2020-05-01T12:00:00Z".to_owned(),
+            },
+        );
+
+        // act
+        let fresh = app.token(1, expires - 10 * 60);
+        let expiring = app.token(1, expires - 60);
+
+        // assert
+        assert_eq!(Some("token".to_owned()), fresh);
+        assert_eq!(None, expiring);
+    }
+
+    #[test]
+    fn expires_at_secs_should_parse_utc_timestamps() {
+        // act
+        let epoch = expires_at_secs("1970-01-01T00:00:00Z");
+        let leap_day = expires_at_secs("2020-02-29T13:14:15Z");
+
+        // assert
+        assert_eq!(Some(0), epoch);
+        assert_eq!(Some(1_582_982_055), leap_day);
+    }
+
+    #[test]
+    fn installation_path_should_use_the_repository_or_organization_of_the_url() {
+        // act
+        let repo = installation_path("/repos/owner/repo/labels?per_page=100");
+        let org = installation_path("/orgs/org/teams/core/repos/org/repo");
+        let other = installation_path("/rate_limit");
+
+        // assert
+        assert_eq!(
+            Some((
+                "owner".to_owned(),
+                "/repos/owner/repo/installation".to_owned()
+            )),
+            repo
+        );
+        assert_eq!(
+            Some(("org".to_owned(), "/orgs/org/installation".to_owned())),
+            org
+        );
+        assert_eq!(None, other);
+    }
+}
@@ -0,0 +1,394 @@
+//! A fake github client for tests.
+use super::{
+    Account, ActionsPermissions, ActionsPublicKey, Branch, BranchPolicy, BranchProtection,
+    BranchProtectionUpdate, Collaborator, Environment, EnvironmentUpdate, GithubClient, Hook,
+    HookUpdate, Invitation, Label, LabelUpdate, OrgRepository, RateLimits, Repository,
+    RepositoryUpdate, Secret, SecretUpdate, SelectedActions, Team, Variable, WorkflowPermissions,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+/// Returns the configured value for each read, or an error if it is `None`.
+/// Every write is recorded in `calls`, unless `fail_writes` is set.
+pub struct FakeGithub {
+    pub repository: Option<Repository>,
+    pub protected_branches: Option<Vec<Branch>>,
+    pub branch_protection: Option<BranchProtection>,
+    pub labels: Option<Vec<Label>>,
+    pub collaborators: Option<Vec<Collaborator>>,
+    pub invitations: Option<Vec<Invitation>>,
+    pub teams: Option<Vec<Team>>,
+    pub topics: Option<Vec<String>>,
+    pub hooks: Option<Vec<Hook>>,
+    pub environments: Option<Vec<Environment>>,
+    pub deployment_branch_policies: Option<Vec<BranchPolicy>>,
+    pub environment_variables: Option<Vec<Variable>>,
+    pub actions_permissions: Option<ActionsPermissions>,
+    pub selected_actions: Option<SelectedActions>,
+    pub workflow_permissions: Option<WorkflowPermissions>,
+    pub actions_variables: Option<Vec<Variable>>,
+    pub secrets: Option<Vec<Secret>>,
+    pub secrets_public_key: Option<ActionsPublicKey>,
+    /// Returned for every user or team looked up.
+    pub account: Option<Account>,
+    pub rate_limit: Option<RateLimits>,
+    pub org_repositories: Option<Vec<OrgRepository>>,
+    pub fail_writes: bool,
+    pub calls: Mutex<Vec<String>>,
+}
+
+impl Default for FakeGithub {
+    fn default() -> Self {
+        Self {
+            repository: Some(Repository::default()),
+            protected_branches: Some(Vec::new()),
+            branch_protection: Some(BranchProtection::default()),
+            labels: Some(Vec::new()),
+            collaborators: Some(Vec::new()),
+            invitations: Some(Vec::new()),
+            teams: Some(Vec::new()),
+            topics: Some(Vec::new()),
+            hooks: Some(Vec::new()),
+            environments: Some(Vec::new()),
+            deployment_branch_policies: Some(Vec::new()),
+            environment_variables: Some(Vec::new()),
+            actions_permissions: Some(ActionsPermissions::default()),
+            selected_actions: Some(SelectedActions::default()),
+            workflow_permissions: Some(WorkflowPermissions::default()),
+            actions_variables: Some(Vec::new()),
+            secrets: Some(Vec::new()),
+            secrets_public_key: Some(ActionsPublicKey::default()),
+            account: Some(Account::default()),
+            rate_limit: Some(RateLimits::default()),
+            org_repositories: Some(Vec::new()),
+            fail_writes: false,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl FakeGithub {
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn read<T: Clone>(value: &Option<T>) -> Result<T> {
+        value.clone().ok_or_else(|| anyhow!("error"))
+    }
+
+    fn record(&self, call: String) -> Result<()> {
+        if self.fail_writes {
+            return Err(anyhow!("error"));
+        }
+        self.calls.lock().unwrap().push(call);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl GithubClient for FakeGithub {
+    async fn repository(&self, _: &str, _: &str) -> Result<Repository> {
+        FakeGithub::read(&self.repository)
+    }
+    async fn protected_branches(&self, _: &str, _: &str) -> Result<Vec<Branch>> {
+        FakeGithub::read(&self.protected_branches)
+    }
+    async fn branch_protection(&self, _: &str, _: &str, _: &str) -> Result<BranchProtection> {
+        FakeGithub::read(&self.branch_protection)
+    }
+    async fn update_repository(
+        &self,
+        owner: &str,
+        name: &str,
+        update: &RepositoryUpdate,
+    ) -> Result<Repository> {
+        self.record(format!("update {}/{} {:?}", owner, name, update))?;
+        Ok(Repository::default())
+    }
+    async fn protect_branch(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: &str,
+        protection: &BranchProtectionUpdate,
+    ) -> Result<()> {
+        self.record(format!(
+            "protect {}/{} {} {:?}",
+            owner, name, branch, protection
+        ))
+    }
+    async fn unprotect_branch(&self, owner: &str, name: &str, branch: &str) -> Result<()> {
+        self.record(format!("unprotect {}/{} {}", owner, name, branch))
+    }
+    async fn labels(&self, _: &str, _: &str) -> Result<Vec<Label>> {
+        FakeGithub::read(&self.labels)
+    }
+    async fn create_label(&self, owner: &str, name: &str, label: &LabelUpdate) -> Result<()> {
+        self.record(format!("create label {}/{} {:?}", owner, name, label))
+    }
+    async fn update_label(
+        &self,
+        owner: &str,
+        name: &str,
+        label: &str,
+        update: &LabelUpdate,
+    ) -> Result<()> {
+        self.record(format!(
+            "update label {}/{} {} {:?}",
+            owner, name, label, update
+        ))
+    }
+    async fn delete_label(&self, owner: &str, name: &str, label: &str) -> Result<()> {
+        self.record(format!("delete label {}/{} {}", owner, name, label))
+    }
+    async fn collaborators(&self, _: &str, _: &str) -> Result<Vec<Collaborator>> {
+        FakeGithub::read(&self.collaborators)
+    }
+    async fn add_collaborator(
+        &self,
+        owner: &str,
+        name: &str,
+        username: &str,
+        permission: &str,
+    ) -> Result<()> {
+        self.record(format!(
+            "add collaborator {}/{} {} {}",
+            owner, name, username, permission
+        ))
+    }
+    async fn remove_collaborator(&self, owner: &str, name: &str, username: &str) -> Result<()> {
+        self.record(format!(
+            "remove collaborator {}/{} {}",
+            owner, name, username
+        ))
+    }
+    async fn invitations(&self, _: &str, _: &str) -> Result<Vec<Invitation>> {
+        FakeGithub::read(&self.invitations)
+    }
+    async fn update_invitation(
+        &self,
+        owner: &str,
+        name: &str,
+        id: u64,
+        permissions: &str,
+    ) -> Result<()> {
+        self.record(format!(
+            "update invitation {}/{} {} {}",
+            owner, name, id, permissions
+        ))
+    }
+    async fn delete_invitation(&self, owner: &str, name: &str, id: u64) -> Result<()> {
+        self.record(format!("delete invitation {}/{} {}", owner, name, id))
+    }
+    async fn teams(&self, _: &str, _: &str) -> Result<Vec<Team>> {
+        FakeGithub::read(&self.teams)
+    }
+    async fn add_team(&self, owner: &str, name: &str, slug: &str, permission: &str) -> Result<()> {
+        self.record(format!(
+            "add team {}/{} {} {}",
+            owner, name, slug, permission
+        ))
+    }
+    async fn remove_team(&self, owner: &str, name: &str, slug: &str) -> Result<()> {
+        self.record(format!("remove team {}/{} {}", owner, name, slug))
+    }
+    async fn topics(&self, _: &str, _: &str) -> Result<Vec<String>> {
+        FakeGithub::read(&self.topics)
+    }
+    async fn replace_topics(&self, owner: &str, name: &str, topics: &[String]) -> Result<()> {
+        self.record(format!("replace topics {}/{} {:?}", owner, name, topics))
+    }
+    async fn hooks(&self, _: &str, _: &str) -> Result<Vec<Hook>> {
+        FakeGithub::read(&self.hooks)
+    }
+    async fn create_hook(&self, owner: &str, name: &str, hook: &HookUpdate) -> Result<()> {
+        self.record(format!("create hook {}/{} {:?}", owner, name, hook))
+    }
+    async fn update_hook(
+        &self,
+        owner: &str,
+        name: &str,
+        id: u64,
+        update: &HookUpdate,
+    ) -> Result<()> {
+        self.record(format!(
+            "update hook {}/{} {} {:?}",
+            owner, name, id, update
+        ))
+    }
+    async fn environments(&self, _: &str, _: &str) -> Result<Vec<Environment>> {
+        FakeGithub::read(&self.environments)
+    }
+    async fn update_environment(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        update: &EnvironmentUpdate,
+    ) -> Result<()> {
+        self.record(format!(
+            "update environment {}/{} {} {:?}",
+            owner, name, environment, update
+        ))
+    }
+    async fn deployment_branch_policies(
+        &self,
+        _: &str,
+        _: &str,
+        _: &str,
+    ) -> Result<Vec<BranchPolicy>> {
+        FakeGithub::read(&self.deployment_branch_policies)
+    }
+    async fn create_deployment_branch_policy(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        pattern: &str,
+    ) -> Result<()> {
+        self.record(format!(
+            "create branch policy {}/{} {} {}",
+            owner, name, environment, pattern
+        ))
+    }
+    async fn delete_deployment_branch_policy(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        id: u64,
+    ) -> Result<()> {
+        self.record(format!(
+            "delete branch policy {}/{} {} {}",
+            owner, name, environment, id
+        ))
+    }
+    async fn environment_variables(&self, _: &str, _: &str, _: &str) -> Result<Vec<Variable>> {
+        FakeGithub::read(&self.environment_variables)
+    }
+    async fn create_environment_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.record(format!(
+            "create environment variable {}/{} {} {:?}",
+            owner, name, environment, variable
+        ))
+    }
+    async fn update_environment_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.record(format!(
+            "update environment variable {}/{} {} {:?}",
+            owner, name, environment, variable
+        ))
+    }
+    async fn actions_permissions(&self, _: &str, _: &str) -> Result<ActionsPermissions> {
+        FakeGithub::read(&self.actions_permissions)
+    }
+    async fn update_actions_permissions(
+        &self,
+        owner: &str,
+        name: &str,
+        permissions: &ActionsPermissions,
+    ) -> Result<()> {
+        self.record(format!(
+            "update actions permissions {}/{} {:?}",
+            owner, name, permissions
+        ))
+    }
+    async fn selected_actions(&self, _: &str, _: &str) -> Result<SelectedActions> {
+        FakeGithub::read(&self.selected_actions)
+    }
+    async fn update_selected_actions(
+        &self,
+        owner: &str,
+        name: &str,
+        selected: &SelectedActions,
+    ) -> Result<()> {
+        self.record(format!(
+            "update selected actions {}/{} {:?}",
+            owner, name, selected
+        ))
+    }
+    async fn workflow_permissions(&self, _: &str, _: &str) -> Result<WorkflowPermissions> {
+        FakeGithub::read(&self.workflow_permissions)
+    }
+    async fn update_workflow_permissions(
+        &self,
+        owner: &str,
+        name: &str,
+        permissions: &WorkflowPermissions,
+    ) -> Result<()> {
+        self.record(format!(
+            "update workflow permissions {}/{} {:?}",
+            owner, name, permissions
+        ))
+    }
+    async fn actions_variables(&self, _: &str, _: &str) -> Result<Vec<Variable>> {
+        FakeGithub::read(&self.actions_variables)
+    }
+    async fn create_actions_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.record(format!(
+            "create actions variable {}/{} {:?}",
+            owner, name, variable
+        ))
+    }
+    async fn update_actions_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.record(format!(
+            "update actions variable {}/{} {:?}",
+            owner, name, variable
+        ))
+    }
+    async fn secrets(&self, _: &str, _: &str) -> Result<Vec<Secret>> {
+        FakeGithub::read(&self.secrets)
+    }
+    async fn secrets_public_key(&self, _: &str, _: &str) -> Result<ActionsPublicKey> {
+        FakeGithub::read(&self.secrets_public_key)
+    }
+    async fn put_secret(
+        &self,
+        owner: &str,
+        name: &str,
+        secret: &str,
+        update: &SecretUpdate,
+    ) -> Result<()> {
+        self.record(format!(
+            "put secret {}/{} {} {} {}",
+            owner, name, secret, update.key_id, update.encrypted_value
+        ))
+    }
+    async fn delete_secret(&self, owner: &str, name: &str, secret: &str) -> Result<()> {
+        self.record(format!("delete secret {}/{} {}", owner, name, secret))
+    }
+    async fn user(&self, _: &str) -> Result<Account> {
+        FakeGithub::read(&self.account)
+    }
+    async fn team(&self, _: &str, _: &str) -> Result<Account> {
+        FakeGithub::read(&self.account)
+    }
+    async fn rate_limit(&self) -> Result<RateLimits> {
+        FakeGithub::read(&self.rate_limit)
+    }
+    async fn org_repositories(&self, _: &str) -> Result<Vec<OrgRepository>> {
+        FakeGithub::read(&self.org_repositories)
+    }
+}
@@ -1,139 +1,2115 @@
 use anyhow::{anyhow, Result};
+use app::{installation_path, App, Installation, InstallationToken};
 use async_trait::async_trait;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use reqwest::{
     header,
     header::{HeaderMap, HeaderValue},
-    Client,
+    Certificate, Client, Method, Response,
 };
+use retry::{rate_limited, retries_server_errors, RateLimitPolicy, RateLimitWait, RetryPolicy};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
 use structopt::clap::{crate_name, crate_version};
 
+pub mod app;
+#[cfg(test)]
+pub mod fake;
+pub mod retry;
+pub mod sealed;
+
 pub static GITHUB_BASE_URL: &str = "https://api.github.com";
 static GRAM_USER_AGENT: &str = concat!(crate_name!(), " ", crate_version!());
+/// The most items github returns in a single page of a list.
+const PER_PAGE: u32 = 100;
+
+/// Characters which must be encoded when a name, e.g. a label, is used as
+/// a single segment of a url path.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// A repository. Github leaves out some fields depending on the token's
+/// access and the github version, these are `None`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Repository {
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub private: Option<bool>,
+    /// Only returned by newer github versions, `private` is used otherwise.
+    pub visibility: Option<String>,
+    pub default_branch: Option<String>,
+    pub has_issues: Option<bool>,
+    pub has_projects: Option<bool>,
+    pub has_wiki: Option<bool>,
+    pub has_discussions: Option<bool>,
+    pub is_template: Option<bool>,
+    pub allow_forking: Option<bool>,
+    pub web_commit_signoff_required: Option<bool>,
+    pub allow_squash_merge: Option<bool>,
+    pub allow_merge_commit: Option<bool>,
+    pub allow_rebase_merge: Option<bool>,
+    pub allow_auto_merge: Option<bool>,
+    pub allow_update_branch: Option<bool>,
+    pub delete_branch_on_merge: Option<bool>,
+    pub squash_merge_commit_title: Option<String>,
+    pub squash_merge_commit_message: Option<String>,
+    pub merge_commit_title: Option<String>,
+    pub merge_commit_message: Option<String>,
+}
+
+/// Repository fields which can be changed. Any field left as `None` is
+/// not sent, so github leaves it as is.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RepositoryUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_issues: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_projects: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_wiki: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_discussions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_template: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_forking: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_commit_signoff_required: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_squash_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_merge_commit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_rebase_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_auto_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_update_branch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_branch_on_merge: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub squash_merge_commit_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub squash_merge_commit_message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_commit_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge_commit_message: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Branch {
+    pub name: String,
+}
+
+/// Branch protection as returned by github. Any part of the protection
+/// which is turned off is missing.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BranchProtection {
+    pub required_status_checks: Option<RequiredStatusChecks>,
+    pub enforce_admins: Option<Enabled>,
+    pub required_pull_request_reviews: Option<RequiredPullRequestReviews>,
+    pub required_linear_history: Option<Enabled>,
+    pub allow_force_pushes: Option<Enabled>,
+    pub allow_deletions: Option<Enabled>,
+    pub restrictions: Option<Restrictions>,
+    pub required_conversation_resolution: Option<Enabled>,
+    pub block_creations: Option<Enabled>,
+    pub lock_branch: Option<Enabled>,
+    pub allow_fork_syncing: Option<Enabled>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RequiredStatusChecks {
+    pub strict: bool,
+    pub contexts: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RequiredPullRequestReviews {
+    #[serde(default)]
+    pub dismiss_stale_reviews: bool,
+    #[serde(default)]
+    pub require_code_owner_reviews: bool,
+    #[serde(default)]
+    pub required_approving_review_count: u32,
+    #[serde(default)]
+    pub require_last_push_approval: bool,
+    pub dismissal_restrictions: Option<Restrictions>,
+    pub bypass_pull_request_allowances: Option<Restrictions>,
+}
+
+/// The users, teams and apps a part of branch protection is limited to.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Restrictions {
+    #[serde(default)]
+    pub users: Vec<Account>,
+    #[serde(default)]
+    pub teams: Vec<Account>,
+    #[serde(default)]
+    pub apps: Vec<Account>,
+}
+
+impl Restrictions {
+    /// The same restrictions as github expects them when updating protection.
+    pub fn update(&self) -> RestrictionsUpdate {
+        let names = |accounts: &[Account], name: fn(&Account) -> Option<&String>| {
+            accounts
+                .iter()
+                .filter_map(|account| name(account).cloned())
+                .collect()
+        };
+        RestrictionsUpdate {
+            users: names(&self.users, |account| account.login.as_ref()),
+            teams: names(&self.teams, |account| account.slug.as_ref()),
+            apps: names(&self.apps, |account| account.slug.as_ref()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Enabled {
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Label {
+    pub name: String,
+    pub color: String,
+    pub description: Option<String>,
+}
+
+/// Body for creating or updating a label. Any field left as `None` is not sent.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LabelUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// A repository as listed for an organization.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct OrgRepository {
+    pub name: String,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+/// A user with direct access to a repository.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Collaborator {
+    pub login: String,
+    pub permissions: Permissions,
+}
+
+/// An invitation for a user to become a collaborator which they have not accepted yet.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Invitation {
+    pub id: u64,
+    /// Missing when the invitation was sent to an email address.
+    pub invitee: Option<Account>,
+    /// One of `read`, `triage`, `write`, `maintain` or `admin`.
+    pub permissions: String,
+}
+
+/// Body for changing the permission an invitation gives.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct InvitationUpdate {
+    pub permissions: String,
+}
+
+/// The permissions a collaborator has, each higher permission includes those below it.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Permissions {
+    pub admin: bool,
+    #[serde(default)]
+    pub maintain: bool,
+    pub push: bool,
+    #[serde(default)]
+    pub triage: bool,
+    pub pull: bool,
+}
+
+/// A team with access to a repository.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Team {
+    pub slug: String,
+    pub permission: String,
+}
+
+/// The rate limit budgets of the authenticated user, keyed by resource,
+/// e.g. `core` or `search`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RateLimits {
+    pub resources: BTreeMap<String, RateLimit>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    /// When the budget resets, in seconds since the unix epoch.
+    pub reset: u64,
+}
+
+/// Body for giving a user or team a permission on a repository.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PermissionUpdate {
+    pub permission: String,
+}
+
+/// Body for updating branch protection.
+///
+/// Github requires the first four fields to be present, a `None` value turns
+/// that part of the protection off.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BranchProtectionUpdate {
+    pub required_status_checks: Option<RequiredStatusChecks>,
+    pub enforce_admins: Option<bool>,
+    pub required_pull_request_reviews: Option<RequiredPullRequestReviewsUpdate>,
+    pub restrictions: Option<RestrictionsUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_linear_history: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_force_pushes: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_deletions: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_conversation_resolution: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_creations: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_branch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fork_syncing: Option<bool>,
+}
+
+/// Body for the pull request review part of a branch protection update.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RequiredPullRequestReviewsUpdate {
+    pub dismiss_stale_reviews: bool,
+    pub require_code_owner_reviews: bool,
+    pub required_approving_review_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_last_push_approval: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dismissal_restrictions: Option<RestrictionsUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bypass_pull_request_allowances: Option<RestrictionsUpdate>,
+}
+
+/// Body for limiting a part of branch protection to users, teams and apps,
+/// each given by their login or slug.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RestrictionsUpdate {
+    pub users: Vec<String>,
+    pub teams: Vec<String>,
+    pub apps: Vec<String>,
+}
+
+/// The topics of a repository, as both returned and sent by github.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Topics {
+    pub names: Vec<String>,
+}
+
+/// A page of the deployment environments of a repository.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Environments {
+    pub environments: Vec<Environment>,
+}
+
+/// A deployment environment. Github leaves out the branch policy when any
+/// branch can deploy.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Environment {
+    pub name: String,
+    #[serde(default)]
+    pub protection_rules: Vec<ProtectionRule>,
+    pub deployment_branch_policy: Option<DeploymentBranchPolicy>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProtectionRule {
+    WaitTimer {
+        wait_timer: u32,
+    },
+    RequiredReviewers {
+        reviewers: Vec<Reviewer>,
+    },
+    /// Rules gram does not manage, e.g. the branch policy which is also
+    /// returned as `deployment_branch_policy`.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", content = "reviewer")]
+pub enum Reviewer {
+    User(Account),
+    Team(Account),
+}
+
+/// A user, team or app, only users have a login and only teams and apps have a slug.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Account {
+    pub id: u64,
+    pub login: Option<String>,
+    pub slug: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct DeploymentBranchPolicy {
+    pub protected_branches: bool,
+    pub custom_branch_policies: bool,
+}
+
+/// A page of the branch name patterns which can deploy to an environment.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BranchPolicies {
+    pub branch_policies: Vec<BranchPolicy>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BranchPolicy {
+    pub id: u64,
+    pub name: String,
+    /// Either `branch` or `tag`, missing on github versions without tag policies.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+/// A page of actions variables.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Variables {
+    pub variables: Vec<Variable>,
+}
+
+/// An actions variable, also the body for creating or updating one.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+}
+
+/// Body for creating or updating an environment.
+///
+/// Github replaces all protection of an environment at once, a `None` branch
+/// policy lets any branch deploy.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct EnvironmentUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait_timer: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reviewers: Option<Vec<ReviewerId>>,
+    pub deployment_branch_policy: Option<DeploymentBranchPolicy>,
+}
+
+/// A reviewer of an environment, `kind` is either `User` or `Team`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ReviewerId {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: u64,
+}
+
+/// Whether actions can run on a repository and which, also the body for changing it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ActionsPermissions {
+    pub enabled: bool,
+    /// One of `all`, `local_only` or `selected`, left out when actions are disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_actions: Option<String>,
+}
+
+/// The actions which can run when only selected actions are allowed, also
+/// the body for changing them.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SelectedActions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_owned_allowed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified_allowed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patterns_allowed: Option<Vec<String>>,
+}
+
+/// What the `GITHUB_TOKEN` of a workflow can do, also the body for changing it.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct WorkflowPermissions {
+    /// Either `read` or `write`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_workflow_permissions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub can_approve_pull_request_reviews: Option<bool>,
+}
+
+/// A page of the actions secrets of a repository, github never returns their values.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Secrets {
+    pub secrets: Vec<Secret>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Secret {
+    pub name: String,
+    pub updated_at: String,
+}
+
+/// The key actions secrets of a repository must be encrypted with.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ActionsPublicKey {
+    pub key_id: String,
+    /// The base64 encoded public key.
+    pub key: String,
+}
+
+/// Body for creating or updating an actions secret, see [seal](sealed/fn.seal.html).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct SecretUpdate {
+    pub encrypted_value: String,
+    pub key_id: String,
+}
+
+/// A webhook on a repository.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Hook {
+    pub id: u64,
+    pub active: bool,
+    pub events: Vec<String>,
+    pub config: HookConfig,
+}
+
+/// Github masks the secret of a webhook, so it only shows whether one is set.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct HookConfig {
+    pub url: Option<String>,
+    pub content_type: Option<String>,
+    pub secret: Option<String>,
+}
+
+/// Body for creating or updating a webhook. Any field left as `None` is not sent.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct HookUpdate {
+    /// Always `web` when creating a webhook, and not sent when updating one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+    pub config: HookConfigUpdate,
+}
+
+#[derive(Clone, Default, Deserialize, PartialEq, Serialize)]
+pub struct HookConfigUpdate {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+/// Leaves the secret out, so it cannot end up in logs or error messages.
+impl fmt::Debug for HookConfigUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookConfigUpdate")
+            .field("url", &self.url)
+            .field("content_type", &self.content_type)
+            .field("secret", &self.secret.as_ref().map(|_| "********"))
+            .finish()
+    }
+}
+
+/// How gram authenticates with github.
+#[derive(Debug)]
+pub enum Auth {
+    /// A personal access token.
+    Token(String),
+    /// A Github App, acting as one of its installations.
+    App(App),
+}
+
+pub struct Github<'a> {
+    base_url: &'a str,
+    client: Client,
+    auth: Auth,
+    retry: RetryPolicy,
+    on_rate_limit_wait: Option<Box<WaitNotice>>,
+}
+
+/// Told each time a call waits for the rate limit to reset.
+type WaitNotice = dyn Fn(&RateLimitWait) + Send + Sync;
+
+impl<'a> Github<'a> {
+    pub fn new(token: String, base_url: &'a str) -> Result<Self> {
+        Github::with_ca_bundle(Auth::Token(token), base_url, None)
+    }
+
+    /// Build a client which also trusts the certificates in a PEM bundle,
+    /// e.g. for a Github Enterprise Server signed by an internal authority.
+    ///
+    /// The base url can have a path, e.g. `https://github.example.com/api/v3`.
+    pub fn with_ca_bundle(auth: Auth, base_url: &'a str, ca_bundle: Option<&[u8]>) -> Result<Self> {
+        let mut builder = Client::builder()
+            .user_agent(GRAM_USER_AGENT)
+            .default_headers(Github::default_headers());
+        for pem in ca_bundle.map(pem_certificates).unwrap_or_default() {
+            let certificate = Certificate::from_pem(pem.as_bytes())
+                .map_err(|e| anyhow!("Failed to read a certificate from the CA bundle: {}", e))?;
+            builder = builder.add_root_certificate(certificate);
+        }
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/'),
+            client: builder.build()?,
+            auth,
+            retry: RetryPolicy::default(),
+            on_rate_limit_wait: None,
+        })
+    }
+
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
+
+    /// Tell the caller each time a call waits for the rate limit to reset,
+    /// which can take minutes.
+    pub fn on_rate_limit_wait<N>(self, notify: N) -> Self
+    where
+        N: Fn(&RateLimitWait) + Send + Sync + 'static,
+    {
+        Self {
+            on_rate_limit_wait: Some(Box::new(notify)),
+            ..self
+        }
+    }
+
+    fn default_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        headers
+    }
+
+    /// The authorization header for a call to the given url.
+    ///
+    /// When authenticating as an app, this gets a token for the installation
+    /// which can access the url, unless there is one which is not about to
+    /// expire.
+    async fn authorization(&self, url: &str) -> Result<String> {
+        let app = match &self.auth {
+            Auth::Token(token) => return Ok(format!("token {}", token)),
+            Auth::App(app) => app,
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let jwt = format!("Bearer {}", app.jwt(now)?);
+        let lookup = installation_path(url);
+        let known = match &lookup {
+            Some((owner, _)) => app.installation(owner),
+            None => app.installation_id,
+        };
+        let installation_id = match (known, lookup) {
+            (Some(installation_id), _) => installation_id,
+            (None, Some((owner, path))) => {
+                let response = self.send_as(Method::GET, &path, None::<&()>, &jwt).await?;
+                let installation = response.json::<Installation>().await?;
+                app.add_installation(&owner, installation.id);
+                installation.id
+            }
+            (None, None) => {
+                return Err(anyhow!(
+                    "An installation id is required to call {} as a github app",
+                    url
+                ))
+            }
+        };
+        if let Some(token) = app.token(installation_id, now) {
+            return Ok(format!("token {}", token));
+        }
+        let path = format!("/app/installations/{}/access_tokens", installation_id);
+        let response = self.send_as(Method::POST, &path, None::<&()>, &jwt).await?;
+        let token = response.json::<InstallationToken>().await?;
+        let authorization = format!("token {}", token.token);
+        app.add_token(installation_id, token);
+        Ok(authorization)
+    }
+
+    async fn get<T>(&self, url: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let response = self.send(Method::GET, url, None::<&()>).await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    /// Get every page of a list, following the `Link` header until there is
+    /// no next page.
+    ///
+    /// Each page is read as `W` and `items` takes the list out of it, as
+    /// github wraps some lists in an object, e.g. `{"total_count": 2, "variables": [...]}`.
+    async fn get_all<W, T>(&self, url: &str, items: fn(W) -> Vec<T>) -> Result<Vec<T>>
+    where
+        W: DeserializeOwned,
+    {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let mut next = Some(format!("{}{}per_page={}", url, separator, PER_PAGE));
+        let mut all = Vec::new();
+        while let Some(url) = next {
+            let response = self.send(Method::GET, &url, None::<&()>).await?;
+            next = match next_link(&response) {
+                Some(link) => match link.strip_prefix(self.base_url) {
+                    Some(path) => Some(path.to_owned()),
+                    None => return Err(anyhow!("Refusing to follow next page link [{}]", link)),
+                },
+                None => None,
+            };
+            all.extend(items(response.json::<W>().await?));
+        }
+        Ok(all)
+    }
+
+    async fn post<B, T>(&self, url: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let response = self.send(Method::POST, url, Some(body)).await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    async fn patch<B, T>(&self, url: &str, body: &B) -> Result<T>
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let response = self.send(Method::PATCH, url, Some(body)).await?;
+        Ok(response.json::<T>().await?)
+    }
+
+    async fn put<B>(&self, url: &str, body: &B) -> Result<()>
+    where
+        B: Serialize + ?Sized,
+    {
+        self.send(Method::PUT, url, Some(body)).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, url: &str) -> Result<()> {
+        self.send(Method::DELETE, url, None::<&()>).await?;
+        Ok(())
+    }
+
+    /// Send a request, turning any non-success status into an error.
+    ///
+    /// Calls which hit a rate limit, or fail with a server error and are safe
+    /// to repeat, are retried according to the retry policy.
+    async fn send<B>(&self, method: Method, url: &str, body: Option<&B>) -> Result<Response>
+    where
+        B: Serialize + ?Sized,
+    {
+        let authorization = self.authorization(url).await?;
+        self.send_as(method, url, body, &authorization).await
+    }
+
+    /// Send a request with the given authorization header.
+    async fn send_as<B>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<&B>,
+        authorization: &str,
+    ) -> Result<Response>
+    where
+        B: Serialize + ?Sized,
+    {
+        let mut retries = 0;
+        loop {
+            let mut request = self
+                .client
+                .request(method.clone(), &format!("{}{}", self.base_url, url))
+                .header(header::AUTHORIZATION, authorization);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let response = request.send().await?;
+            let status = response.status();
+            if status == reqwest::StatusCode::UNAUTHORIZED {
+                let msg = format!(
+                    "Encountered a http status of 401 when calling {} on url {}. Is your token correct?",
+                    method, url
+                );
+                return Err(anyhow!("{}", msg));
+            }
+            let can_retry = retries < self.retry.max_retries;
+            if let Some(wait) = rate_limited(status, response.headers(), SystemTime::now()) {
+                if self.retry.rate_limit == RateLimitPolicy::Fail
+                    || wait > self.retry.max_wait
+                    || !can_retry
+                {
+                    return Err(anyhow!(
+                        "Hit the github rate limit when calling {} on url {}, it resets in {} seconds",
+                        method,
+                        url,
+                        wait.as_secs()
+                    ));
+                }
+                if let Some(notify) = &self.on_rate_limit_wait {
+                    notify(&RateLimitWait {
+                        method: method.clone(),
+                        url: url.to_owned(),
+                        wait,
+                    });
+                }
+                tokio::time::delay_for(wait).await;
+            } else if status.is_server_error() && can_retry && retries_server_errors(&method) {
+                tokio::time::delay_for(self.retry.backoff(retries)).await;
+            } else {
+                return response.error_for_status().map_err(|e| anyhow!("{}", e));
+            }
+            retries += 1;
+        }
+    }
+}
+
+/// Split a PEM bundle into its certificates, each is read on its own.
+fn pem_certificates(bundle: &[u8]) -> Vec<String> {
+    let end = "-----END CERTIFICATE-----";
+    String::from_utf8_lossy(bundle)
+        .split_inclusive(end)
+        .map(str::trim)
+        .filter(|pem| pem.ends_with(end))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The path of the protection of a branch, its name can hold a `/`, e.g. `release/1.0`.
+fn protection_path(owner: &str, name: &str, branch: &str) -> String {
+    format!(
+        "/repos/{}/{}/branches/{}/protection",
+        owner,
+        name,
+        utf8_percent_encode(branch, PATH_SEGMENT)
+    )
+}
+
+/// The path of an environment, its name can hold any character.
+fn environment_path(owner: &str, name: &str, environment: &str) -> String {
+    format!(
+        "/repos/{}/{}/environments/{}",
+        owner,
+        name,
+        utf8_percent_encode(environment, PATH_SEGMENT)
+    )
+}
+
+/// Get the url of the next page from the `Link` header of a response, e.g.
+/// `<https://api.github.com/...&page=2>; rel="next", <...>; rel="last"`.
+fn next_link(response: &Response) -> Option<String> {
+    let links = response.headers().get(header::LINK)?.to_str().ok()?;
+    links.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let url = parts.next()?.trim();
+        parts
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| url.trim_start_matches('<').trim_end_matches('>').to_owned())
+    })
+}
+
+/// Calls to the github api.
+///
+/// Methods which return a list return every item, not just the first page.
+#[async_trait]
+pub trait GithubClient {
+    async fn repository(&self, owner: &str, name: &str) -> Result<Repository>;
+    async fn protected_branches(&self, owner: &str, name: &str) -> Result<Vec<Branch>>;
+    async fn branch_protection(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: &str,
+    ) -> Result<BranchProtection>;
+    async fn update_repository(
+        &self,
+        owner: &str,
+        name: &str,
+        update: &RepositoryUpdate,
+    ) -> Result<Repository>;
+    async fn protect_branch(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: &str,
+        protection: &BranchProtectionUpdate,
+    ) -> Result<()>;
+    async fn unprotect_branch(&self, owner: &str, name: &str, branch: &str) -> Result<()>;
+    async fn labels(&self, owner: &str, name: &str) -> Result<Vec<Label>>;
+    async fn create_label(&self, owner: &str, name: &str, label: &LabelUpdate) -> Result<()>;
+    async fn update_label(
+        &self,
+        owner: &str,
+        name: &str,
+        label: &str,
+        update: &LabelUpdate,
+    ) -> Result<()>;
+    async fn delete_label(&self, owner: &str, name: &str, label: &str) -> Result<()>;
+    async fn collaborators(&self, owner: &str, name: &str) -> Result<Vec<Collaborator>>;
+    async fn add_collaborator(
+        &self,
+        owner: &str,
+        name: &str,
+        username: &str,
+        permission: &str,
+    ) -> Result<()>;
+    async fn remove_collaborator(&self, owner: &str, name: &str, username: &str) -> Result<()>;
+    async fn invitations(&self, owner: &str, name: &str) -> Result<Vec<Invitation>>;
+    async fn update_invitation(
+        &self,
+        owner: &str,
+        name: &str,
+        id: u64,
+        permissions: &str,
+    ) -> Result<()>;
+    async fn delete_invitation(&self, owner: &str, name: &str, id: u64) -> Result<()>;
+    async fn teams(&self, owner: &str, name: &str) -> Result<Vec<Team>>;
+    async fn add_team(&self, owner: &str, name: &str, slug: &str, permission: &str) -> Result<()>;
+    async fn remove_team(&self, owner: &str, name: &str, slug: &str) -> Result<()>;
+    async fn topics(&self, owner: &str, name: &str) -> Result<Vec<String>>;
+    async fn replace_topics(&self, owner: &str, name: &str, topics: &[String]) -> Result<()>;
+    async fn hooks(&self, owner: &str, name: &str) -> Result<Vec<Hook>>;
+    async fn environments(&self, owner: &str, name: &str) -> Result<Vec<Environment>>;
+    async fn update_environment(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        update: &EnvironmentUpdate,
+    ) -> Result<()>;
+    async fn deployment_branch_policies(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+    ) -> Result<Vec<BranchPolicy>>;
+    async fn create_deployment_branch_policy(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        pattern: &str,
+    ) -> Result<()>;
+    async fn delete_deployment_branch_policy(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        id: u64,
+    ) -> Result<()>;
+    async fn environment_variables(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+    ) -> Result<Vec<Variable>>;
+    async fn create_environment_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        variable: &Variable,
+    ) -> Result<()>;
+    async fn update_environment_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        variable: &Variable,
+    ) -> Result<()>;
+    async fn actions_permissions(&self, owner: &str, name: &str) -> Result<ActionsPermissions>;
+    async fn update_actions_permissions(
+        &self,
+        owner: &str,
+        name: &str,
+        permissions: &ActionsPermissions,
+    ) -> Result<()>;
+    async fn selected_actions(&self, owner: &str, name: &str) -> Result<SelectedActions>;
+    async fn update_selected_actions(
+        &self,
+        owner: &str,
+        name: &str,
+        selected: &SelectedActions,
+    ) -> Result<()>;
+    async fn workflow_permissions(&self, owner: &str, name: &str) -> Result<WorkflowPermissions>;
+    async fn update_workflow_permissions(
+        &self,
+        owner: &str,
+        name: &str,
+        permissions: &WorkflowPermissions,
+    ) -> Result<()>;
+    async fn actions_variables(&self, owner: &str, name: &str) -> Result<Vec<Variable>>;
+    async fn create_actions_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<()>;
+    async fn update_actions_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<()>;
+    async fn secrets(&self, owner: &str, name: &str) -> Result<Vec<Secret>>;
+    async fn secrets_public_key(&self, owner: &str, name: &str) -> Result<ActionsPublicKey>;
+    async fn put_secret(
+        &self,
+        owner: &str,
+        name: &str,
+        secret: &str,
+        update: &SecretUpdate,
+    ) -> Result<()>;
+    async fn delete_secret(&self, owner: &str, name: &str, secret: &str) -> Result<()>;
+    async fn user(&self, username: &str) -> Result<Account>;
+    async fn team(&self, org: &str, slug: &str) -> Result<Account>;
+    async fn create_hook(&self, owner: &str, name: &str, hook: &HookUpdate) -> Result<()>;
+    async fn update_hook(
+        &self,
+        owner: &str,
+        name: &str,
+        id: u64,
+        update: &HookUpdate,
+    ) -> Result<()>;
+    async fn rate_limit(&self) -> Result<RateLimits>;
+    async fn org_repositories(&self, org: &str) -> Result<Vec<OrgRepository>>;
+}
+
+#[async_trait]
+impl GithubClient for Github<'_> {
+    async fn repository(&self, owner: &str, name: &str) -> Result<Repository> {
+        self.get::<Repository>(&format!("/repos/{}/{}", owner, name))
+            .await
+    }
+    async fn protected_branches(&self, owner: &str, name: &str) -> Result<Vec<Branch>> {
+        self.get_all(
+            &format!("/repos/{}/{}/branches?protected=true", owner, name),
+            |page: Vec<Branch>| page,
+        )
+        .await
+    }
+    async fn branch_protection(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: &str,
+    ) -> Result<BranchProtection> {
+        self.get::<BranchProtection>(&protection_path(owner, name, branch))
+            .await
+    }
+    async fn update_repository(
+        &self,
+        owner: &str,
+        name: &str,
+        update: &RepositoryUpdate,
+    ) -> Result<Repository> {
+        self.patch::<RepositoryUpdate, Repository>(&format!("/repos/{}/{}", owner, name), update)
+            .await
+    }
+    async fn protect_branch(
+        &self,
+        owner: &str,
+        name: &str,
+        branch: &str,
+        protection: &BranchProtectionUpdate,
+    ) -> Result<()> {
+        self.put(&protection_path(owner, name, branch), protection)
+            .await
+    }
+    async fn unprotect_branch(&self, owner: &str, name: &str, branch: &str) -> Result<()> {
+        self.delete(&protection_path(owner, name, branch)).await
+    }
+    async fn labels(&self, owner: &str, name: &str) -> Result<Vec<Label>> {
+        self.get_all(
+            &format!("/repos/{}/{}/labels", owner, name),
+            |page: Vec<Label>| page,
+        )
+        .await
+    }
+    async fn create_label(&self, owner: &str, name: &str, label: &LabelUpdate) -> Result<()> {
+        self.post::<LabelUpdate, Label>(&format!("/repos/{}/{}/labels", owner, name), label)
+            .await?;
+        Ok(())
+    }
+    async fn update_label(
+        &self,
+        owner: &str,
+        name: &str,
+        label: &str,
+        update: &LabelUpdate,
+    ) -> Result<()> {
+        self.patch::<LabelUpdate, Label>(
+            &format!(
+                "/repos/{}/{}/labels/{}",
+                owner,
+                name,
+                utf8_percent_encode(label, PATH_SEGMENT)
+            ),
+            update,
+        )
+        .await?;
+        Ok(())
+    }
+    async fn delete_label(&self, owner: &str, name: &str, label: &str) -> Result<()> {
+        self.delete(&format!(
+            "/repos/{}/{}/labels/{}",
+            owner,
+            name,
+            utf8_percent_encode(label, PATH_SEGMENT)
+        ))
+        .await
+    }
+    async fn collaborators(&self, owner: &str, name: &str) -> Result<Vec<Collaborator>> {
+        self.get_all(
+            &format!("/repos/{}/{}/collaborators?affiliation=direct", owner, name),
+            |page: Vec<Collaborator>| page,
+        )
+        .await
+    }
+    async fn add_collaborator(
+        &self,
+        owner: &str,
+        name: &str,
+        username: &str,
+        permission: &str,
+    ) -> Result<()> {
+        self.put(
+            &format!(
+                "/repos/{}/{}/collaborators/{}",
+                owner,
+                name,
+                utf8_percent_encode(username, PATH_SEGMENT)
+            ),
+            &PermissionUpdate {
+                permission: permission.to_owned(),
+            },
+        )
+        .await
+    }
+    async fn remove_collaborator(&self, owner: &str, name: &str, username: &str) -> Result<()> {
+        self.delete(&format!(
+            "/repos/{}/{}/collaborators/{}",
+            owner,
+            name,
+            utf8_percent_encode(username, PATH_SEGMENT)
+        ))
+        .await
+    }
+    async fn invitations(&self, owner: &str, name: &str) -> Result<Vec<Invitation>> {
+        self.get_all(
+            &format!("/repos/{}/{}/invitations", owner, name),
+            |page: Vec<Invitation>| page,
+        )
+        .await
+    }
+    async fn update_invitation(
+        &self,
+        owner: &str,
+        name: &str,
+        id: u64,
+        permissions: &str,
+    ) -> Result<()> {
+        self.patch::<InvitationUpdate, Invitation>(
+            &format!("/repos/{}/{}/invitations/{}", owner, name, id),
+            &InvitationUpdate {
+                permissions: permissions.to_owned(),
+            },
+        )
+        .await?;
+        Ok(())
+    }
+    async fn delete_invitation(&self, owner: &str, name: &str, id: u64) -> Result<()> {
+        self.delete(&format!("/repos/{}/{}/invitations/{}", owner, name, id))
+            .await
+    }
+    async fn teams(&self, owner: &str, name: &str) -> Result<Vec<Team>> {
+        self.get_all(
+            &format!("/repos/{}/{}/teams", owner, name),
+            |page: Vec<Team>| page,
+        )
+        .await
+    }
+    async fn add_team(&self, owner: &str, name: &str, slug: &str, permission: &str) -> Result<()> {
+        self.put(
+            &format!("/orgs/{}/teams/{}/repos/{}/{}", owner, slug, owner, name),
+            &PermissionUpdate {
+                permission: permission.to_owned(),
+            },
+        )
+        .await
+    }
+    async fn remove_team(&self, owner: &str, name: &str, slug: &str) -> Result<()> {
+        self.delete(&format!(
+            "/orgs/{}/teams/{}/repos/{}/{}",
+            owner, slug, owner, name
+        ))
+        .await
+    }
+    async fn topics(&self, owner: &str, name: &str) -> Result<Vec<String>> {
+        let topics = self
+            .get::<Topics>(&format!("/repos/{}/{}/topics", owner, name))
+            .await?;
+        Ok(topics.names)
+    }
+    async fn replace_topics(&self, owner: &str, name: &str, topics: &[String]) -> Result<()> {
+        self.put(
+            &format!("/repos/{}/{}/topics", owner, name),
+            &Topics {
+                names: topics.to_vec(),
+            },
+        )
+        .await
+    }
+    async fn hooks(&self, owner: &str, name: &str) -> Result<Vec<Hook>> {
+        self.get_all(
+            &format!("/repos/{}/{}/hooks", owner, name),
+            |page: Vec<Hook>| page,
+        )
+        .await
+    }
+    async fn create_hook(&self, owner: &str, name: &str, hook: &HookUpdate) -> Result<()> {
+        self.post::<HookUpdate, Hook>(&format!("/repos/{}/{}/hooks", owner, name), hook)
+            .await?;
+        Ok(())
+    }
+    async fn update_hook(
+        &self,
+        owner: &str,
+        name: &str,
+        id: u64,
+        update: &HookUpdate,
+    ) -> Result<()> {
+        self.patch::<HookUpdate, Hook>(&format!("/repos/{}/{}/hooks/{}", owner, name, id), update)
+            .await?;
+        Ok(())
+    }
+    async fn environments(&self, owner: &str, name: &str) -> Result<Vec<Environment>> {
+        self.get_all(
+            &format!("/repos/{}/{}/environments", owner, name),
+            |page: Environments| page.environments,
+        )
+        .await
+    }
+    async fn update_environment(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        update: &EnvironmentUpdate,
+    ) -> Result<()> {
+        self.put(&environment_path(owner, name, environment), update)
+            .await
+    }
+    async fn deployment_branch_policies(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+    ) -> Result<Vec<BranchPolicy>> {
+        self.get_all(
+            &format!(
+                "{}/deployment-branch-policies",
+                environment_path(owner, name, environment)
+            ),
+            |page: BranchPolicies| page.branch_policies,
+        )
+        .await
+    }
+    async fn create_deployment_branch_policy(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        pattern: &str,
+    ) -> Result<()> {
+        self.post::<_, BranchPolicy>(
+            &format!(
+                "{}/deployment-branch-policies",
+                environment_path(owner, name, environment)
+            ),
+            &serde_json::json!({ "name": pattern }),
+        )
+        .await?;
+        Ok(())
+    }
+    async fn delete_deployment_branch_policy(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        id: u64,
+    ) -> Result<()> {
+        self.delete(&format!(
+            "{}/deployment-branch-policies/{}",
+            environment_path(owner, name, environment),
+            id
+        ))
+        .await
+    }
+    async fn environment_variables(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+    ) -> Result<Vec<Variable>> {
+        self.get_all(
+            &format!("{}/variables", environment_path(owner, name, environment)),
+            |page: Variables| page.variables,
+        )
+        .await
+    }
+    async fn create_environment_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.send(
+            Method::POST,
+            &format!("{}/variables", environment_path(owner, name, environment)),
+            Some(variable),
+        )
+        .await?;
+        Ok(())
+    }
+    async fn update_environment_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        environment: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.send(
+            Method::PATCH,
+            &format!(
+                "{}/variables/{}",
+                environment_path(owner, name, environment),
+                utf8_percent_encode(&variable.name, PATH_SEGMENT)
+            ),
+            Some(variable),
+        )
+        .await?;
+        Ok(())
+    }
+    async fn actions_permissions(&self, owner: &str, name: &str) -> Result<ActionsPermissions> {
+        self.get::<ActionsPermissions>(&format!("/repos/{}/{}/actions/permissions", owner, name))
+            .await
+    }
+    async fn update_actions_permissions(
+        &self,
+        owner: &str,
+        name: &str,
+        permissions: &ActionsPermissions,
+    ) -> Result<()> {
+        self.put(
+            &format!("/repos/{}/{}/actions/permissions", owner, name),
+            permissions,
+        )
+        .await
+    }
+    async fn selected_actions(&self, owner: &str, name: &str) -> Result<SelectedActions> {
+        self.get::<SelectedActions>(&format!(
+            "/repos/{}/{}/actions/permissions/selected-actions",
+            owner, name
+        ))
+        .await
+    }
+    async fn update_selected_actions(
+        &self,
+        owner: &str,
+        name: &str,
+        selected: &SelectedActions,
+    ) -> Result<()> {
+        self.put(
+            &format!(
+                "/repos/{}/{}/actions/permissions/selected-actions",
+                owner, name
+            ),
+            selected,
+        )
+        .await
+    }
+    async fn workflow_permissions(&self, owner: &str, name: &str) -> Result<WorkflowPermissions> {
+        self.get::<WorkflowPermissions>(&format!(
+            "/repos/{}/{}/actions/permissions/workflow",
+            owner, name
+        ))
+        .await
+    }
+    async fn update_workflow_permissions(
+        &self,
+        owner: &str,
+        name: &str,
+        permissions: &WorkflowPermissions,
+    ) -> Result<()> {
+        self.put(
+            &format!("/repos/{}/{}/actions/permissions/workflow", owner, name),
+            permissions,
+        )
+        .await
+    }
+    async fn actions_variables(&self, owner: &str, name: &str) -> Result<Vec<Variable>> {
+        self.get_all(
+            &format!("/repos/{}/{}/actions/variables", owner, name),
+            |page: Variables| page.variables,
+        )
+        .await
+    }
+    async fn create_actions_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.send(
+            Method::POST,
+            &format!("/repos/{}/{}/actions/variables", owner, name),
+            Some(variable),
+        )
+        .await?;
+        Ok(())
+    }
+    async fn update_actions_variable(
+        &self,
+        owner: &str,
+        name: &str,
+        variable: &Variable,
+    ) -> Result<()> {
+        self.send(
+            Method::PATCH,
+            &format!(
+                "/repos/{}/{}/actions/variables/{}",
+                owner,
+                name,
+                utf8_percent_encode(&variable.name, PATH_SEGMENT)
+            ),
+            Some(variable),
+        )
+        .await?;
+        Ok(())
+    }
+    async fn secrets(&self, owner: &str, name: &str) -> Result<Vec<Secret>> {
+        self.get_all(
+            &format!("/repos/{}/{}/actions/secrets", owner, name),
+            |page: Secrets| page.secrets,
+        )
+        .await
+    }
+    async fn secrets_public_key(&self, owner: &str, name: &str) -> Result<ActionsPublicKey> {
+        self.get::<ActionsPublicKey>(&format!(
+            "/repos/{}/{}/actions/secrets/public-key",
+            owner, name
+        ))
+        .await
+    }
+    async fn put_secret(
+        &self,
+        owner: &str,
+        name: &str,
+        secret: &str,
+        update: &SecretUpdate,
+    ) -> Result<()> {
+        self.put(
+            &format!(
+                "/repos/{}/{}/actions/secrets/{}",
+                owner,
+                name,
+                utf8_percent_encode(secret, PATH_SEGMENT)
+            ),
+            update,
+        )
+        .await
+    }
+    async fn delete_secret(&self, owner: &str, name: &str, secret: &str) -> Result<()> {
+        self.delete(&format!(
+            "/repos/{}/{}/actions/secrets/{}",
+            owner,
+            name,
+            utf8_percent_encode(secret, PATH_SEGMENT)
+        ))
+        .await
+    }
+    async fn user(&self, username: &str) -> Result<Account> {
+        self.get::<Account>(&format!("/users/{}", username)).await
+    }
+    async fn team(&self, org: &str, slug: &str) -> Result<Account> {
+        self.get::<Account>(&format!("/orgs/{}/teams/{}", org, slug))
+            .await
+    }
+    async fn rate_limit(&self) -> Result<RateLimits> {
+        self.get::<RateLimits>("/rate_limit").await
+    }
+    async fn org_repositories(&self, org: &str) -> Result<Vec<OrgRepository>> {
+        self.get_all(
+            &format!("/orgs/{}/repos", org),
+            |page: Vec<OrgRepository>| page,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        app::{App, Installation, InstallationToken},
+        pem_certificates,
+        retry::{RateLimitPolicy, RetryPolicy},
+        Account, Auth, Branch, BranchProtection, Collaborator, DeploymentBranchPolicy,
+        EnvironmentUpdate, Environments, Github, GithubClient, HookUpdate, ProtectionRule,
+        Repository, RepositoryUpdate, Restrictions, RestrictionsUpdate, Reviewer, Variable,
+        Variables,
+    };
+    use mockito::{mock, Matcher};
+    use serde::Serialize;
+    use serde_json;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn to_json_value<T: Serialize>(string: &T) -> String {
+        serde_json::to_string(string).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_should_error_with_message_if_call_returns_401() {
+        // arrange
+        let _m = mock("GET", "/repos/owner/repo")
+            .with_status(401)
+            .with_header("content-type", "application")
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github.get::<Repository>("/repos/owner/repo").await;
+
+        // assert
+        assert!(
+            response.is_err(),
+            "expected response to be an error, got {:#?}",
+            response
+        );
+        assert_eq!(
+            "Encountered a http status of 401 when calling GET on url /repos/owner/repo. Is your token correct?",
+            &format!("{}", response.err().unwrap())
+        )
+    }
+
+    fn app(installation_id: Option<u64>) -> Auth {
+        let key = openssl::rsa::Rsa::generate(2048)
+            .unwrap()
+            .private_key_to_pem()
+            .unwrap();
+        Auth::App(App::new(1234, &key, installation_id).unwrap())
+    }
+
+    fn installation_token(expires_at: &str) -> String {
+        to_json_value(&InstallationToken {
+            token: "installation-token".to_owned(),
+            expires_at: expires_at.to_owned(),
+        })
+    }
+
+    #[tokio::test]
+    async fn app_should_look_up_the_installation_and_reuse_its_token() {
+        // arrange
+        let installation = mock("GET", "/repos/owner/app-repo/installation")
+            .match_header("authorization", Matcher::Regex("^Bearer .+".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(to_json_value(&Installation { id: 42 }))
+            .expect(1)
+            .create();
+        let token = mock("POST", "/app/installations/42/access_tokens")
+            .match_header("authorization", Matcher::Regex("^Bearer .+".into()))
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(installation_token("2999-01-01T00:00:00Z"))
+            .expect(1)
+            .create();
+        let repository = mock("GET", "/repos/owner/app-repo")
+            .match_header("authorization", "token installation-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(to_json_value(&Repository::default()))
+            .expect(2)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::with_ca_bundle(app(None), &url, None).unwrap();
+
+        // act
+        let first = github.repository("owner", "app-repo").await;
+        let second = github.repository("owner", "app-repo").await;
+
+        // assert
+        assert!(first.is_ok(), "expected ok, got {:#?}", first);
+        assert!(second.is_ok(), "expected ok, got {:#?}", second);
+        installation.assert();
+        token.assert();
+        repository.assert();
+    }
+
+    #[tokio::test]
+    async fn app_should_refresh_a_token_which_is_about_to_expire() {
+        // arrange
+        let token = mock("POST", "/app/installations/7/access_tokens")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(installation_token("2000-01-01T00:00:00Z"))
+            .expect(2)
+            .create();
+        let _repository = mock("GET", "/repos/owner/expiring")
+            .match_header("authorization", "token installation-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(to_json_value(&Repository::default()))
+            .create();
+        let url = mockito::server_url();
+        let github = Github::with_ca_bundle(app(Some(7)), &url, None).unwrap();
+
+        // act
+        github.repository("owner", "expiring").await.unwrap();
+        github.repository("owner", "expiring").await.unwrap();
+
+        // assert
+        token.assert();
+    }
+
+    #[tokio::test]
+    async fn get_should_call_urls_under_the_path_of_the_base_url() {
+        // arrange
+        let _m = mock("GET", "/api/v3/repos/owner/enterprise")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(to_json_value(&Repository::default()))
+            .create();
+        let url = format!("{}/api/v3/", mockito::server_url());
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github.repository("owner", "enterprise").await;
+
+        // assert
+        assert!(
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
+            response
+        );
+    }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-pub struct Repository {
-    pub description: Option<String>,
-    pub allow_squash_merge: bool,
-    pub allow_merge_commit: bool,
-    pub allow_rebase_merge: bool,
-    pub delete_branch_on_merge: bool,
-}
+    #[test]
+    fn pem_certificates_should_split_a_bundle_into_certificates() {
+        // arrange
+        let bundle = "# internal ca\n\
+                      -----BEGIN CERTIFICATE-----\na\n-----END CERTIFICATE-----\n\
+                      -----BEGIN CERTIFICATE-----\nb\n-----END CERTIFICATE-----\n";
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-pub struct Branch {
-    pub name: String,
-}
+        // act
+        let certificates = pem_certificates(bundle.as_bytes());
 
-pub struct Github<'a> {
-    base_url: &'a str,
-    client: Client,
-}
+        // assert
+        assert_eq!(
+            vec![
+                "# internal ca\n-----BEGIN CERTIFICATE-----\na\n-----END CERTIFICATE-----",
+                "-----BEGIN CERTIFICATE-----\nb\n-----END CERTIFICATE-----"
+            ],
+            certificates
+        );
+    }
 
-impl<'a> Github<'a> {
-    pub fn new(token: String, base_url: &'a str) -> Self {
-        let client = Client::builder()
-            .user_agent(GRAM_USER_AGENT)
-            .default_headers(Github::default_headers(&token))
-            .build()
-            .unwrap();
-        Self { base_url, client }
+    #[test]
+    fn with_ca_bundle_should_error_if_a_certificate_is_invalid() {
+        // arrange
+        let bundle = b"-----BEGIN CERTIFICATE-----\nnot a certificate\n-----END CERTIFICATE-----\n";
+
+        // act
+        let github = Github::with_ca_bundle(Auth::Token("token".to_owned()), "", Some(bundle));
+
+        // assert
+        assert!(github.is_err());
     }
 
-    fn default_headers(token: &str) -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::CONTENT_TYPE,
-            HeaderValue::from_static("application/json"),
+    #[tokio::test]
+    async fn get_should_retry_server_errors_until_out_of_retries() {
+        // arrange
+        let m = mock("GET", "/repos/owner/flaky")
+            .with_status(502)
+            .expect(3)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                backoff: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            });
+
+        // act
+        let response = github.get::<Repository>("/repos/owner/flaky").await;
+
+        // assert
+        assert!(response.is_err());
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn get_should_fail_on_rate_limit_if_the_policy_is_to_fail() {
+        // arrange
+        let m = mock("GET", "/repos/owner/limited")
+            .with_status(403)
+            .with_header("retry-after", "60")
+            .expect(1)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                rate_limit: RateLimitPolicy::Fail,
+                ..RetryPolicy::default()
+            });
+
+        // act
+        let response = github.get::<Repository>("/repos/owner/limited").await;
+
+        // assert
+        assert_eq!(
+            "Hit the github rate limit when calling GET on url /repos/owner/limited, it resets in 60 seconds",
+            format!("{}", response.err().unwrap())
         );
-        headers.insert(
-            header::AUTHORIZATION,
-            HeaderValue::from_bytes(format!("token {}", token).as_bytes())
-                .expect("failed building authorization header from token"),
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn get_should_wait_for_a_rate_limit_which_resets_soon() {
+        // arrange
+        let m = mock("GET", "/repos/owner/waiting")
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .expect(2)
+            .create();
+        let url = mockito::server_url();
+        let waits = Arc::new(Mutex::new(Vec::new()));
+        let notified = waits.clone();
+        let github = Github::new("token".to_owned(), &url)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 1,
+                ..RetryPolicy::default()
+            })
+            .on_rate_limit_wait(move |wait| notified.lock().unwrap().push(wait.to_string()));
+
+        // act
+        let response = github.get::<Repository>("/repos/owner/waiting").await;
+
+        // assert
+        assert!(response.is_err());
+        assert_eq!(
+            vec!["Hit the github rate limit when calling GET on url /repos/owner/waiting, waiting 0 seconds"],
+            *waits.lock().unwrap()
         );
-        headers
+        m.assert();
     }
 
-    async fn get<T>(&self, url: &str) -> Result<T>
-    where
-        T: DeserializeOwned,
-    {
-        let response = self
-            .client
-            .get(&format!("{}{}", self.base_url, url))
-            .send()
-            .await?;
-        match response.status() {
-            reqwest::StatusCode::UNAUTHORIZED => {
-                let msg = format!(
-                    "Encountered a http status of 401 when calling GET on url {}. Is your token correct?",
+    #[tokio::test]
+    async fn post_should_not_retry_server_errors() {
+        // arrange
+        let m = mock("POST", "/repos/owner/repo/hooks")
+            .with_status(502)
+            .expect(1)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url)
+            .unwrap()
+            .with_retry_policy(RetryPolicy {
+                max_retries: 2,
+                backoff: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            });
+
+        // act
+        let response = github
+            .create_hook("owner", "repo", &HookUpdate::default())
+            .await;
+
+        // assert
+        assert!(response.is_err());
+        m.assert();
+    }
+
+    fn branches(names: &[&str]) -> String {
+        let branches = names
+            .iter()
+            .map(|name| Branch {
+                name: (*name).to_owned(),
+            })
+            .collect::<Vec<Branch>>();
+        to_json_value(&branches)
+    }
+
+    #[tokio::test]
+    async fn protected_branches_should_follow_next_links_across_pages() {
+        // arrange
+        let url = mockito::server_url();
+        let page = |page: &str| {
+            Matcher::AllOf(vec![
+                Matcher::UrlEncoded("protected".into(), "true".into()),
+                Matcher::UrlEncoded("per_page".into(), "100".into()),
+                Matcher::UrlEncoded("page".into(), page.into()),
+            ])
+        };
+        let _first = mock("GET", "/repos/owner/paged/branches")
+            .match_query(Matcher::Exact("protected=true&per_page=100".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header(
+                "link",
+                &format!(
+                    "<{}/repos/owner/paged/branches?protected=true&per_page=100&page=2>; rel=\"next\", \
+                     <{}/repos/owner/paged/branches?protected=true&per_page=100&page=3>; rel=\"last\"",
+                    url, url
+                ),
+            )
+            .with_body(branches(&["a", "b"]))
+            .create();
+        let _second = mock("GET", "/repos/owner/paged/branches")
+            .match_query(page("2"))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header(
+                "link",
+                &format!(
+                    "<{}/repos/owner/paged/branches?protected=true&per_page=100&page=3>; rel=\"next\"",
                     url
-                );
-                Err(anyhow!("{}", msg))
-            }
-            _ => {
-                let r = response.error_for_status().map_err(|e| anyhow!("{}", e))?;
-                Ok(r.json::<T>().await?)
-            }
-        }
+                ),
+            )
+            .with_body(branches(&["c"]))
+            .create();
+        let _third = mock("GET", "/repos/owner/paged/branches")
+            .match_query(page("3"))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(branches(&["d"]))
+            .create();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github.protected_branches("owner", "paged").await;
+
+        // assert
+        let names = response
+            .unwrap()
+            .into_iter()
+            .map(|branch| branch.name)
+            .collect::<Vec<String>>();
+        assert_eq!(vec!["a", "b", "c", "d"], names);
     }
-}
 
-#[async_trait]
-pub trait GithubClient {
-    async fn repository(&self, owner: &str, name: &str) -> Result<Repository>;
-    async fn protected_branches(&self, owner: &str, name: &str) -> Result<Vec<Branch>>;
-}
+    #[tokio::test]
+    async fn get_all_should_follow_next_links_across_pages_of_a_wrapped_list() {
+        // arrange
+        let url = mockito::server_url();
+        let variables = |names: &[&str]| {
+            let variables = names
+                .iter()
+                .map(|name| Variable {
+                    name: (*name).to_owned(),
+                    value: "value".to_owned(),
+                })
+                .collect();
+            to_json_value(&Variables { variables })
+        };
+        let _first = mock("GET", "/repos/owner/wrapped/actions/variables")
+            .match_query(Matcher::Exact("per_page=100".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header(
+                "link",
+                &format!(
+                    "<{}/repos/owner/wrapped/actions/variables?per_page=100&page=2>; rel=\"next\"",
+                    url
+                ),
+            )
+            .with_body(variables(&["A", "B"]))
+            .create();
+        let _second = mock("GET", "/repos/owner/wrapped/actions/variables")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("per_page".into(), "100".into()),
+                Matcher::UrlEncoded("page".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(variables(&["C"]))
+            .create();
+        let github = Github::new("token".to_owned(), &url).unwrap();
 
-#[async_trait]
-impl GithubClient for Github<'_> {
-    async fn repository(&self, owner: &str, name: &str) -> Result<Repository> {
-        self.get::<Repository>(&format!("/repos/{}/{}", owner, name))
-            .await
+        // act
+        let response = github
+            .get_all(
+                "/repos/owner/wrapped/actions/variables",
+                |page: Variables| page.variables,
+            )
+            .await;
+
+        // assert
+        let names = response
+            .unwrap()
+            .into_iter()
+            .map(|variable| variable.name)
+            .collect::<Vec<String>>();
+        assert_eq!(vec!["A", "B", "C"], names);
     }
-    async fn protected_branches(&self, owner: &str, name: &str) -> Result<Vec<Branch>> {
-        self.get::<Vec<Branch>>(&format!(
-            "/repos/{}/{}/branches?protected=true",
-            owner, name
-        ))
-        .await
+
+    #[tokio::test]
+    async fn collaborators_should_keep_the_query_when_asking_for_full_pages() {
+        // arrange
+        let _m = mock("GET", "/repos/owner/single-page/collaborators")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("affiliation".into(), "direct".into()),
+                Matcher::UrlEncoded("per_page".into(), "100".into()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(to_json_value(&vec![Collaborator::default()]))
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github.collaborators("owner", "single-page").await;
+
+        // assert
+        assert_eq!(1, response.unwrap().len());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::{Github, Repository};
-    use mockito::mock;
-    use serde::Serialize;
-    use serde_json;
+    #[tokio::test]
+    async fn get_all_should_refuse_to_follow_a_next_link_to_another_host() {
+        // arrange
+        let _m = mock("GET", "/repos/owner/elsewhere/labels")
+            .match_query(Matcher::UrlEncoded("per_page".into(), "100".into()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("link", "<https://example.com/labels?page=2>; rel=\"next\"")
+            .with_body("[]")
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
 
-    fn to_json_value<T: Serialize>(string: &T) -> String {
-        serde_json::to_string(string).unwrap()
+        // act
+        let response = github.labels("owner", "elsewhere").await;
+
+        // assert
+        assert_eq!(
+            "Refusing to follow next page link [https://example.com/labels?page=2]",
+            format!("{}", response.err().unwrap())
+        );
     }
 
     #[tokio::test]
-    async fn get_should_error_with_message_if_call_returns_401() {
+    async fn update_repository_should_only_send_fields_which_are_set() {
         // arrange
-        let _m = mock("GET", "/repos/owner/repo")
-            .with_status(401)
-            .with_header("content-type", "application")
+        let _m = mock("PATCH", "/repos/owner/repo")
+            .match_body(Matcher::Json(
+                serde_json::json!({ "allow_squash_merge": false }),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(to_json_value(&Repository::default()))
             .create();
         let url = mockito::server_url();
-        let github = Github::new("token".to_owned(), &url);
+        let github = Github::new("token".to_owned(), &url).unwrap();
+        let update = RepositoryUpdate {
+            allow_squash_merge: Some(false),
+            ..RepositoryUpdate::default()
+        };
 
         // act
-        let response = github.get::<Repository>("/repos/owner/repo").await;
+        let response = github.update_repository("owner", "repo", &update).await;
 
         // assert
         assert!(
-            response.is_err(),
-            "expected response to be an error, got {:#?}",
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
             response
         );
-        assert_eq!(
-            "Encountered a http status of 401 when calling GET on url /repos/owner/repo. Is your token correct?",
-            &format!("{}", response.err().unwrap())
+    }
+
+    #[tokio::test]
+    async fn delete_label_should_encode_the_label_name() {
+        // arrange
+        let _m = mock("DELETE", "/repos/owner/repo/labels/good%20first%20issue")
+            .with_status(204)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github
+            .delete_label("owner", "repo", "good first issue")
+            .await;
+
+        // assert
+        assert!(
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn remove_collaborator_should_encode_the_username() {
+        // arrange
+        let _m = mock("DELETE", "/repos/owner/repo/collaborators/octo%2Fcat")
+            .with_status(204)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github
+            .remove_collaborator("owner", "repo", "octo/cat")
+            .await;
+
+        // assert
+        assert!(
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_secret_should_encode_the_secret_name() {
+        // arrange
+        let _m = mock("DELETE", "/repos/owner/repo/actions/secrets/DEPLOY%2FTOKEN")
+            .with_status(204)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github.delete_secret("owner", "repo", "DEPLOY/TOKEN").await;
+
+        // assert
+        assert!(
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn unprotect_branch_should_encode_the_branch_name() {
+        // arrange
+        let _m = mock(
+            "DELETE",
+            "/repos/owner/repo/branches/release%2F1.0/protection",
         )
+        .with_status(204)
+        .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+
+        // act
+        let response = github
+            .unprotect_branch("owner", "repo", "release/1.0")
+            .await;
+
+        // assert
+        assert!(
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
+            response
+        );
+    }
+
+    #[test]
+    fn restrictions_should_update_with_logins_and_slugs() {
+        // arrange
+        let json = r#"
+            {
+                "url": "https://api.github.com/repos/o/r/branches/master/protection/restrictions",
+                "users": [{ "id": 1, "login": "octocat" }],
+                "teams": [{ "id": 2, "slug": "core" }],
+                "apps": [{ "id": 3, "slug": "deploy-bot" }]
+            }
+        "#;
+        let restrictions = serde_json::from_str::<Restrictions>(json).unwrap();
+
+        // act
+        let update = restrictions.update();
+
+        // assert
+        assert_eq!(
+            RestrictionsUpdate {
+                users: vec!["octocat".to_owned()],
+                teams: vec!["core".to_owned()],
+                apps: vec!["deploy-bot".to_owned()],
+            },
+            update
+        );
+    }
+
+    #[test]
+    fn branch_protection_should_deserialize_with_missing_parts() {
+        // arrange
+        let json = r#"
+            {
+                "url": "https://api.github.com/repos/o/r/branches/master/protection",
+                "required_pull_request_reviews": {
+                    "dismiss_stale_reviews": true,
+                    "required_approving_review_count": 2
+                },
+                "enforce_admins": { "url": "", "enabled": true },
+                "allow_deletions": { "enabled": false }
+            }
+        "#;
+
+        // act
+        let protection = serde_json::from_str::<BranchProtection>(json).unwrap();
+
+        // assert
+        let reviews = protection.required_pull_request_reviews.unwrap();
+        assert!(reviews.dismiss_stale_reviews);
+        assert!(!reviews.require_code_owner_reviews);
+        assert_eq!(2, reviews.required_approving_review_count);
+        assert!(protection.enforce_admins.unwrap().enabled);
+        assert!(!protection.allow_deletions.unwrap().enabled);
+        assert!(protection.required_status_checks.is_none());
+        assert!(protection.required_linear_history.is_none());
     }
 
     #[test]
@@ -166,9 +2142,104 @@ mod test {
 
         // assert
         assert_eq!(description, repo.description);
-        assert_eq!(allow_merge_commit, repo.allow_merge_commit);
-        assert_eq!(allow_squash_merge, repo.allow_squash_merge);
-        assert_eq!(allow_rebase_merge, repo.allow_rebase_merge);
-        assert_eq!(delete_branch_on_merge, repo.delete_branch_on_merge);
+        assert_eq!(Some(allow_merge_commit), repo.allow_merge_commit);
+        assert_eq!(Some(allow_squash_merge), repo.allow_squash_merge);
+        assert_eq!(Some(allow_rebase_merge), repo.allow_rebase_merge);
+        assert_eq!(Some(delete_branch_on_merge), repo.delete_branch_on_merge);
+        assert!(repo.has_issues.is_none());
+    }
+
+    #[test]
+    fn environments_should_deserialize_protection_rules_and_skip_unknown_ones() {
+        // arrange
+        let json = r#"
+            {
+                "total_count": 1,
+                "environments": [{
+                    "id": 1,
+                    "name": "production",
+                    "protection_rules": [
+                        { "id": 2, "type": "wait_timer", "wait_timer": 30 },
+                        {
+                            "id": 3,
+                            "type": "required_reviewers",
+                            "reviewers": [
+                                { "type": "User", "reviewer": { "id": 4, "login": "alice" } },
+                                { "type": "Team", "reviewer": { "id": 5, "slug": "core" } }
+                            ]
+                        },
+                        { "id": 6, "type": "branch_policy" }
+                    ],
+                    "deployment_branch_policy": {
+                        "protected_branches": false,
+                        "custom_branch_policies": true
+                    }
+                }]
+            }
+        "#;
+
+        // act
+        let environments = serde_json::from_str::<Environments>(json).unwrap();
+
+        // assert
+        let environment = &environments.environments[0];
+        assert_eq!(
+            vec![
+                ProtectionRule::WaitTimer { wait_timer: 30 },
+                ProtectionRule::RequiredReviewers {
+                    reviewers: vec![
+                        Reviewer::User(Account {
+                            id: 4,
+                            login: Some("alice".to_owned()),
+                            slug: None,
+                        }),
+                        Reviewer::Team(Account {
+                            id: 5,
+                            login: None,
+                            slug: Some("core".to_owned()),
+                        }),
+                    ],
+                },
+                ProtectionRule::Other,
+            ],
+            environment.protection_rules
+        );
+        assert_eq!(
+            Some(DeploymentBranchPolicy {
+                protected_branches: false,
+                custom_branch_policies: true,
+            }),
+            environment.deployment_branch_policy
+        );
+    }
+
+    #[tokio::test]
+    async fn update_environment_should_encode_the_name_and_send_no_branch_policy() {
+        // arrange
+        let _m = mock("PUT", "/repos/owner/repo/environments/staging%20eu")
+            .match_body(Matcher::Json(serde_json::json!({
+                "wait_timer": 5,
+                "deployment_branch_policy": null
+            })))
+            .with_status(200)
+            .create();
+        let url = mockito::server_url();
+        let github = Github::new("token".to_owned(), &url).unwrap();
+        let update = EnvironmentUpdate {
+            wait_timer: Some(5),
+            ..EnvironmentUpdate::default()
+        };
+
+        // act
+        let response = github
+            .update_environment("owner", "repo", "staging eu", &update)
+            .await;
+
+        // assert
+        assert!(
+            response.is_ok(),
+            "expected response to be ok, got {:#?}",
+            response
+        );
     }
 }
@@ -0,0 +1,241 @@
+//! When and how long to wait before retrying a call to github.
+use anyhow::{anyhow, Result};
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static RATE_LIMIT_REMAINING: &str = "x-ratelimit-remaining";
+static RATE_LIMIT_RESET: &str = "x-ratelimit-reset";
+static RETRY_AFTER: &str = "retry-after";
+
+/// What to do when github says the rate limit has been used up.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RateLimitPolicy {
+    /// Sleep until the rate limit resets, then retry.
+    #[default]
+    Wait,
+    /// Fail straight away.
+    Fail,
+}
+
+impl FromStr for RateLimitPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wait" => Ok(RateLimitPolicy::Wait),
+            "fail" => Ok(RateLimitPolicy::Fail),
+            _ => Err(anyhow!(
+                "Unknown rate limit policy [{}], expected wait or fail",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RateLimitPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitPolicy::Wait => write!(f, "wait"),
+            RateLimitPolicy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// How calls which fail for a transient reason are retried.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    pub rate_limit: RateLimitPolicy,
+    /// The longest gram will sleep waiting for a rate limit to reset.
+    pub max_wait: Duration,
+    /// How many times a call is retried before giving up.
+    pub max_retries: u32,
+    /// The delay before the first retry of a server error, doubled for
+    /// each retry after that.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            rate_limit: RateLimitPolicy::Wait,
+            max_wait: Duration::from_secs(15 * 60),
+            max_retries: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the given retry of a server error, with up to one
+    /// more `backoff` of jitter so parallel callers don't retry in step.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self.backoff * 2u32.saturating_pow(retry);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.subsec_nanos())
+            .unwrap_or_default();
+        delay + self.backoff.mul_f64(f64::from(nanos) / 1e9)
+    }
+}
+
+/// A call which is waiting for the rate limit to reset before it is retried,
+/// given to the caller so it can say why gram has paused.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimitWait {
+    pub method: Method,
+    pub url: String,
+    pub wait: Duration,
+}
+
+impl fmt::Display for RateLimitWait {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Hit the github rate limit when calling {} on url {}, waiting {} seconds",
+            self.method,
+            self.url,
+            self.wait.as_secs()
+        )
+    }
+}
+
+/// Whether a call which failed with a server error can be sent again.
+///
+/// A server error does not mean github did nothing, so a repeated `POST`
+/// could create a second webhook or label. gram only uses `PATCH` to set
+/// fields to given values, so it is as safe to repeat as `PUT`.
+pub fn retries_server_errors(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::PUT | Method::DELETE | Method::PATCH
+    )
+}
+
+/// How long to wait for a rate limit, if the response is from hitting one.
+///
+/// Secondary rate limits give a `Retry-After` header, the primary rate limit
+/// only says no calls remain and when it resets.
+pub fn rate_limited(status: StatusCode, headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    if let Some(seconds) = header(RETRY_AFTER) {
+        return Some(Duration::from_secs(seconds));
+    }
+    match (header(RATE_LIMIT_REMAINING), header(RATE_LIMIT_RESET)) {
+        (Some(0), Some(reset)) => {
+            let reset = UNIX_EPOCH + Duration::from_secs(reset);
+            Some(reset.duration_since(now).unwrap_or_default())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rate_limited, retries_server_errors, RetryPolicy};
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        Method, StatusCode,
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn headers(headers: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.insert(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn rate_limited_should_wait_until_the_primary_rate_limit_resets() {
+        // arrange
+        let headers = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1060"),
+        ]);
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+
+        // act
+        let wait = rate_limited(StatusCode::FORBIDDEN, &headers, now);
+
+        // assert
+        assert_eq!(Some(Duration::from_secs(60)), wait);
+    }
+
+    #[test]
+    fn rate_limited_should_prefer_retry_after_for_secondary_rate_limits() {
+        // arrange
+        let headers = headers(&[
+            ("retry-after", "30"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1060"),
+        ]);
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+
+        // act
+        let wait = rate_limited(StatusCode::TOO_MANY_REQUESTS, &headers, now);
+
+        // assert
+        assert_eq!(Some(Duration::from_secs(30)), wait);
+    }
+
+    #[test]
+    fn rate_limited_should_not_treat_other_forbidden_responses_as_rate_limits() {
+        // arrange
+        let headers = headers(&[
+            ("x-ratelimit-remaining", "4000"),
+            ("x-ratelimit-reset", "1060"),
+        ]);
+
+        // act
+        let wait = rate_limited(StatusCode::FORBIDDEN, &headers, UNIX_EPOCH);
+
+        // assert
+        assert_eq!(None, wait);
+    }
+
+    #[test]
+    fn retries_server_errors_should_not_repeat_posts() {
+        // assert
+        for method in &[Method::GET, Method::PUT, Method::DELETE, Method::PATCH] {
+            assert!(retries_server_errors(method), "{}", method);
+        }
+        assert!(!retries_server_errors(&Method::POST));
+    }
+
+    #[test]
+    fn backoff_should_double_for_each_retry_with_bounded_jitter() {
+        // arrange
+        let policy = RetryPolicy {
+            backoff: Duration::from_millis(100),
+            ..RetryPolicy::default()
+        };
+
+        // act
+        let delays = (0..3)
+            .map(|retry| policy.backoff(retry))
+            .collect::<Vec<Duration>>();
+
+        // assert
+        for (retry, delay) in delays.into_iter().enumerate() {
+            let base = Duration::from_millis(100 * 2u64.pow(retry as u32));
+            assert!(delay >= base, "retry {} waited {:?}", retry, delay);
+            assert!(
+                delay <= base + Duration::from_millis(100),
+                "retry {} waited {:?}",
+                retry,
+                delay
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Encrypting secrets for github with a libsodium sealed box.
+//!
+//! Github only accepts secret values encrypted with the public key of the
+//! repository, see https://docs.github.com/en/rest/guides/encrypting-secrets-for-the-rest-api.
+use super::{ActionsPublicKey, SecretUpdate};
+use anyhow::{anyhow, Result};
+use crypto_box::{aead::OsRng, PublicKey};
+
+/// Encrypt a secret value so only github can read it.
+pub fn seal(public_key: &ActionsPublicKey, value: &[u8]) -> Result<SecretUpdate> {
+    let key = base64::decode(&public_key.key)
+        .ok()
+        .and_then(|key| PublicKey::from_slice(&key).ok())
+        .ok_or_else(|| anyhow!("Github returned an invalid public key for secrets"))?;
+    let encrypted = key
+        .seal(&mut OsRng, value)
+        .map_err(|_| anyhow!("Failed to encrypt secret"))?;
+    Ok(SecretUpdate {
+        encrypted_value: base64::encode(&encrypted),
+        key_id: public_key.key_id.clone(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::seal;
+    use crate::github::ActionsPublicKey;
+    use crypto_box::SecretKey;
+
+    fn keypair() -> (SecretKey, ActionsPublicKey) {
+        let secret_key = SecretKey::from_bytes([7; 32]);
+        let public_key = ActionsPublicKey {
+            key_id: "568250167242549743".to_owned(),
+            key: base64::encode(secret_key.public_key().as_bytes()),
+        };
+        (secret_key, public_key)
+    }
+
+    #[test]
+    fn seal_should_encrypt_a_value_only_the_secret_key_can_open() {
+        // arrange
+        let (secret_key, public_key) = keypair();
+
+        // act
+        let update = seal(&public_key, b"hunter2").unwrap();
+
+        // assert
+        assert_eq!("568250167242549743", update.key_id);
+        let encrypted = base64::decode(&update.encrypted_value).unwrap();
+        // A sealed box is an ephemeral public key and a mac followed by the value.
+        assert_eq!(32 + 16 + 7, encrypted.len());
+        assert_eq!(b"hunter2".to_vec(), secret_key.unseal(&encrypted).unwrap());
+        let other_key = SecretKey::from_bytes([8; 32]);
+        assert!(other_key.unseal(&encrypted).is_err());
+    }
+
+    #[test]
+    fn seal_should_error_if_the_public_key_is_invalid() {
+        // arrange
+        let public_key = ActionsPublicKey {
+            key_id: "1".to_owned(),
+            key: base64::encode(b"too short"),
+        };
+
+        // act
+        let result = seal(&public_key, b"hunter2");
+
+        // assert
+        assert_eq!(
+            "Github returned an invalid public key for secrets",
+            result.err().unwrap().to_string()
+        );
+    }
+}